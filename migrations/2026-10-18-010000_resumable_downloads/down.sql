@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS `downloaded_segments`;
+CREATE TABLE `downloaded_segments` (
+	`download_id` INTEGER NOT NULL,
+	`start_index` INTEGER NOT NULL,
+	`end_index` INTEGER NOT NULL,
+	FOREIGN KEY(`download_id`) REFERENCES file_downloads(`id`)
+);
+
+ALTER TABLE `file_downloads` DROP COLUMN `content_length`;
@@ -0,0 +1,11 @@
+ALTER TABLE `file_downloads` ADD COLUMN `content_length` BIGINT;
+
+DROP TABLE `downloaded_segments`;
+CREATE TABLE `downloaded_segments` (
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`download_id` INTEGER NOT NULL,
+	`start_index` BIGINT NOT NULL,
+	`end_index` BIGINT NOT NULL,
+	FOREIGN KEY(`download_id`) REFERENCES file_downloads(`id`) ON DELETE CASCADE
+);
+CREATE INDEX `idx_downloaded_segments_download_id` ON `downloaded_segments`(`download_id`);
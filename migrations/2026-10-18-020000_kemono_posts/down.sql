@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS `kemono_attachments`;
+DROP TABLE IF EXISTS `kemono_posts`;
+DROP TABLE IF EXISTS `kemono_creators`;
@@ -0,0 +1,37 @@
+CREATE TABLE `kemono_creators` (
+	`provider_domain` TEXT NOT NULL,
+	`service` TEXT NOT NULL,
+	`creator_id` TEXT NOT NULL,
+	`name` TEXT NOT NULL,
+	`created_at` DATETIME NOT NULL,
+	`updated_at` DATETIME NOT NULL,
+	`deleted_at` DATETIME,
+	PRIMARY KEY(`service`, `creator_id`)
+);
+
+CREATE TABLE `kemono_posts` (
+	`provider_domain` TEXT NOT NULL,
+	`service` TEXT NOT NULL,
+	`creator_id` TEXT NOT NULL,
+	`post_id` TEXT NOT NULL,
+	`title` TEXT NOT NULL,
+	`content` TEXT NOT NULL,
+	`published_at` DATETIME,
+	`edited_at` DATETIME,
+	`created_at` DATETIME NOT NULL,
+	`updated_at` DATETIME NOT NULL,
+	`deleted_at` DATETIME,
+	PRIMARY KEY(`service`, `creator_id`, `post_id`)
+);
+CREATE INDEX `idx_kemono_posts_creator_id` ON `kemono_posts`(`service`, `creator_id`);
+
+CREATE TABLE `kemono_attachments` (
+	`service` TEXT NOT NULL,
+	`creator_id` TEXT NOT NULL,
+	`post_id` TEXT NOT NULL,
+	`path` TEXT NOT NULL,
+	`name` TEXT NOT NULL,
+	`position` INTEGER NOT NULL,
+	`created_at` DATETIME NOT NULL,
+	PRIMARY KEY(`service`, `creator_id`, `post_id`, `path`)
+);
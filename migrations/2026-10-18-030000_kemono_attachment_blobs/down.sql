@@ -0,0 +1,3 @@
+ALTER TABLE `kemono_attachments` DROP COLUMN `content_length`;
+ALTER TABLE `kemono_attachments` DROP COLUMN `content_hash`;
+ALTER TABLE `kemono_attachments` DROP COLUMN `media_type`;
@@ -0,0 +1,3 @@
+ALTER TABLE `kemono_attachments` ADD COLUMN `media_type` TEXT;
+ALTER TABLE `kemono_attachments` ADD COLUMN `content_hash` TEXT;
+ALTER TABLE `kemono_attachments` ADD COLUMN `content_length` BIGINT;
@@ -0,0 +1,9 @@
+DROP TRIGGER IF EXISTS `kemono_posts_search_update`;
+DROP TRIGGER IF EXISTS `kemono_posts_search_delete`;
+DROP TRIGGER IF EXISTS `kemono_posts_search_insert`;
+DROP TABLE IF EXISTS `kemono_posts_search`;
+
+DROP TRIGGER IF EXISTS `soundgasm_tracks_search_update`;
+DROP TRIGGER IF EXISTS `soundgasm_tracks_search_delete`;
+DROP TRIGGER IF EXISTS `soundgasm_tracks_search_insert`;
+DROP TABLE IF EXISTS `soundgasm_tracks_search`;
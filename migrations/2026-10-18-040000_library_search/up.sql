@@ -0,0 +1,63 @@
+-- Full-text indexes over the library. They only hold the index and read the
+-- text from the library tables by rowid, so a VACUUM, which may renumber
+-- those rowids, has to be followed by a 'rebuild' of both indexes.
+CREATE VIRTUAL TABLE `soundgasm_tracks_search` USING fts5(
+	`profile_slug`,
+	`track_slug` UNINDEXED,
+	`title`,
+	`description`,
+	content = 'soundgasm_tracks',
+	content_rowid = 'rowid',
+	tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO `soundgasm_tracks_search` (`soundgasm_tracks_search`) VALUES ('rebuild');
+
+CREATE TRIGGER `soundgasm_tracks_search_insert` AFTER INSERT ON `soundgasm_tracks` BEGIN
+	INSERT INTO `soundgasm_tracks_search` (`rowid`, `profile_slug`, `track_slug`, `title`, `description`)
+	VALUES (new.`rowid`, new.`profile_slug`, new.`track_slug`, new.`title`, new.`description`);
+END;
+
+CREATE TRIGGER `soundgasm_tracks_search_delete` AFTER DELETE ON `soundgasm_tracks` BEGIN
+	INSERT INTO `soundgasm_tracks_search` (`soundgasm_tracks_search`, `rowid`, `profile_slug`, `track_slug`, `title`, `description`)
+	VALUES ('delete', old.`rowid`, old.`profile_slug`, old.`track_slug`, old.`title`, old.`description`);
+END;
+
+CREATE TRIGGER `soundgasm_tracks_search_update`
+AFTER UPDATE OF `profile_slug`, `track_slug`, `title`, `description` ON `soundgasm_tracks` BEGIN
+	INSERT INTO `soundgasm_tracks_search` (`soundgasm_tracks_search`, `rowid`, `profile_slug`, `track_slug`, `title`, `description`)
+	VALUES ('delete', old.`rowid`, old.`profile_slug`, old.`track_slug`, old.`title`, old.`description`);
+	INSERT INTO `soundgasm_tracks_search` (`rowid`, `profile_slug`, `track_slug`, `title`, `description`)
+	VALUES (new.`rowid`, new.`profile_slug`, new.`track_slug`, new.`title`, new.`description`);
+END;
+
+CREATE VIRTUAL TABLE `kemono_posts_search` USING fts5(
+	`service` UNINDEXED,
+	`creator_id` UNINDEXED,
+	`post_id` UNINDEXED,
+	`title`,
+	`content`,
+	content = 'kemono_posts',
+	content_rowid = 'rowid',
+	tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO `kemono_posts_search` (`kemono_posts_search`) VALUES ('rebuild');
+
+CREATE TRIGGER `kemono_posts_search_insert` AFTER INSERT ON `kemono_posts` BEGIN
+	INSERT INTO `kemono_posts_search` (`rowid`, `service`, `creator_id`, `post_id`, `title`, `content`)
+	VALUES (new.`rowid`, new.`service`, new.`creator_id`, new.`post_id`, new.`title`, new.`content`);
+END;
+
+CREATE TRIGGER `kemono_posts_search_delete` AFTER DELETE ON `kemono_posts` BEGIN
+	INSERT INTO `kemono_posts_search` (`kemono_posts_search`, `rowid`, `service`, `creator_id`, `post_id`, `title`, `content`)
+	VALUES ('delete', old.`rowid`, old.`service`, old.`creator_id`, old.`post_id`, old.`title`, old.`content`);
+END;
+
+CREATE TRIGGER `kemono_posts_search_update`
+AFTER UPDATE OF `service`, `creator_id`, `post_id`, `title`, `content` ON `kemono_posts` BEGIN
+	INSERT INTO `kemono_posts_search` (`kemono_posts_search`, `rowid`, `service`, `creator_id`, `post_id`, `title`, `content`)
+	VALUES ('delete', old.`rowid`, old.`service`, old.`creator_id`, old.`post_id`, old.`title`, old.`content`);
+	INSERT INTO `kemono_posts_search` (`rowid`, `service`, `creator_id`, `post_id`, `title`, `content`)
+	VALUES (new.`rowid`, new.`service`, new.`creator_id`, new.`post_id`, new.`title`, new.`content`);
+END;
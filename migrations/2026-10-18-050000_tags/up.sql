@@ -0,0 +1,20 @@
+CREATE TABLE `tags` (
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`name` TEXT NOT NULL COLLATE NOCASE,
+	`created_at` DATETIME NOT NULL,
+	UNIQUE(`name`)
+);
+
+-- `source` and `item_id` name a library item, e.g. `soundgasm` and
+-- `<profile_slug>/<track_slug>`. Extracted tags are replaced whenever the
+-- item is scanned again, manual ones are kept.
+CREATE TABLE `item_tags` (
+	`tag_id` INTEGER NOT NULL,
+	`source` TEXT NOT NULL,
+	`item_id` TEXT NOT NULL,
+	`is_manual` BOOLEAN NOT NULL,
+	`created_at` DATETIME NOT NULL,
+	PRIMARY KEY(`tag_id`, `source`, `item_id`),
+	FOREIGN KEY(`tag_id`) REFERENCES tags(`id`)
+);
+CREATE INDEX `idx_item_tags_item` ON `item_tags`(`source`, `item_id`);
@@ -0,0 +1,10 @@
+-- Profiles and creators that `sync` checks for new items. `url` is the
+-- canonical profile URL, so the same profile can't be subscribed twice.
+CREATE TABLE `subscriptions` (
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`source` TEXT NOT NULL,
+	`url` TEXT NOT NULL,
+	`created_at` DATETIME NOT NULL,
+	`last_synced_at` DATETIME,
+	UNIQUE(`url`)
+);
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS `kemono_posts_revisions`;
+DROP TABLE IF EXISTS `kemono_post_revisions`;
+DROP TRIGGER IF EXISTS `soundgasm_tracks_revisions`;
+DROP TABLE IF EXISTS `track_revisions`;
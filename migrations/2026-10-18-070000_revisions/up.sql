@@ -0,0 +1,39 @@
+-- Earlier versions of item metadata. Whenever an update changes the title
+-- or description, the replaced values are copied here by a trigger, so
+-- every way of updating an item keeps its history.
+CREATE TABLE `track_revisions` (
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`profile_slug` TEXT NOT NULL,
+	`track_slug` TEXT NOT NULL,
+	`title` TEXT NOT NULL,
+	`description` TEXT NOT NULL,
+	`replaced_at` DATETIME NOT NULL
+);
+CREATE INDEX `idx_track_revisions_track` ON `track_revisions`(`profile_slug`, `track_slug`);
+
+CREATE TRIGGER `soundgasm_tracks_revisions` AFTER UPDATE OF `title`, `description` ON `soundgasm_tracks`
+WHEN old.`title` IS NOT new.`title` OR old.`description` IS NOT new.`description`
+BEGIN
+	INSERT INTO `track_revisions` (`profile_slug`, `track_slug`, `title`, `description`, `replaced_at`)
+	VALUES (old.`profile_slug`, old.`track_slug`, old.`title`, old.`description`, datetime('now'));
+END;
+
+-- `edited_at` is the edit time Kemono reported for the replaced version
+CREATE TABLE `kemono_post_revisions` (
+	`id` INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	`service` TEXT NOT NULL,
+	`creator_id` TEXT NOT NULL,
+	`post_id` TEXT NOT NULL,
+	`title` TEXT NOT NULL,
+	`content` TEXT NOT NULL,
+	`edited_at` DATETIME,
+	`replaced_at` DATETIME NOT NULL
+);
+CREATE INDEX `idx_kemono_post_revisions_post` ON `kemono_post_revisions`(`service`, `creator_id`, `post_id`);
+
+CREATE TRIGGER `kemono_posts_revisions` AFTER UPDATE OF `title`, `content` ON `kemono_posts`
+WHEN old.`title` IS NOT new.`title` OR old.`content` IS NOT new.`content`
+BEGIN
+	INSERT INTO `kemono_post_revisions` (`service`, `creator_id`, `post_id`, `title`, `content`, `edited_at`, `replaced_at`)
+	VALUES (old.`service`, old.`creator_id`, old.`post_id`, old.`title`, old.`content`, old.`edited_at`, datetime('now'));
+END;
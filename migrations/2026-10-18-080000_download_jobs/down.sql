@@ -0,0 +1,5 @@
+ALTER TABLE `file_downloads` DROP COLUMN `label`;
+ALTER TABLE `file_downloads` DROP COLUMN `last_error`;
+ALTER TABLE `file_downloads` DROP COLUMN `attempts`;
+ALTER TABLE `file_downloads` DROP COLUMN `priority`;
+ALTER TABLE `file_downloads` DROP COLUMN `state`;
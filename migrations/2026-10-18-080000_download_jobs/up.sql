@@ -0,0 +1,9 @@
+-- Every file download is a job that outlives the process. `state` is one of
+-- queued, running, paused, failed or done. Queued jobs are started by
+-- highest `priority` first, then in the order they were first queued.
+ALTER TABLE `file_downloads` ADD COLUMN `state` TEXT NOT NULL DEFAULT 'queued';
+ALTER TABLE `file_downloads` ADD COLUMN `priority` INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE `file_downloads` ADD COLUMN `attempts` INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE `file_downloads` ADD COLUMN `last_error` TEXT;
+-- What the file is for, like `profile/track`, for listing the queue
+ALTER TABLE `file_downloads` ADD COLUMN `label` TEXT;
@@ -0,0 +1 @@
+ALTER TABLE `kemono_attachments` DROP COLUMN `media_type_checked_at`;
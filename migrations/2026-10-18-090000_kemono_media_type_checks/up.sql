@@ -0,0 +1,3 @@
+-- When the server was last asked for the type of an attachment, so those of
+-- unsupported types are only looked up once
+ALTER TABLE `kemono_attachments` ADD COLUMN `media_type_checked_at` DATETIME;
@@ -0,0 +1,121 @@
+use log::{error, info};
+
+use crate::error::Error;
+use crate::file_store::download_manager::{DownloadEvent, DownloadManager};
+use crate::media_sources::kemono::KemonoPostAttachment;
+use crate::media_sources::soundgasm::SoundgasmAudioTrack;
+use crate::media_types::LibraryItem;
+use crate::progress::JobKind;
+use crate::Context;
+
+/// Downloads every cataloged item without a stored file. `profile_slug`
+/// matches either a soundgasm profile or a Kemono creator id. If any
+/// download failed, returns an error of the first failure's kind.
+pub async fn download_command(
+	profile_slug: Option<String>,
+	concurrency: usize,
+	context: &mut Context,
+) -> Result<(), Error> {
+	let detected = KemonoPostAttachment::detect_media_types(context).await;
+	if detected > 0 {
+		info!("Detected the type of {} Kemono attachments", detected);
+	}
+
+	let tracks = SoundgasmAudioTrack::find_undownloaded(context, profile_slug.as_deref()).await?;
+	let attachments =
+		KemonoPostAttachment::find_undownloaded(context, profile_slug.as_deref()).await?;
+
+	if tracks.is_empty() && attachments.is_empty() {
+		println!("Nothing to download");
+		return Ok(());
+	}
+
+	info!(
+		"Downloading {} tracks and {} attachments",
+		tracks.len(),
+		attachments.len()
+	);
+
+	let mut download_manager = DownloadManager::new(
+		context.file_store.clone(),
+		context.http.clone(),
+		context.progress.clone(),
+		concurrency,
+	)?;
+
+	let mut downloaded = 0;
+	let mut failed = 0;
+	let mut first_err = None;
+
+	// Downloads queued by an earlier run go first, in the order they had
+	let items = tracks
+		.into_iter()
+		.map(LibraryItem::SoundgasmTrack)
+		.chain(attachments.into_iter().map(LibraryItem::KemonoAttachment));
+	for item in items {
+		let label = item.get_label();
+
+		if let Err(err) = download_manager.enqueue(item, 0).await {
+			error!("Failed to queue {}: {}", label, err);
+			failed += 1;
+			first_err.get_or_insert(err);
+		}
+	}
+
+	// Paused downloads stay paused until resumed in the TUI
+	let paused = download_manager.get_paused_count();
+	let mut job = context.progress.start_job(
+		JobKind::Batch,
+		"Downloading",
+		Some((download_manager.get_pending_count() - paused) as u64),
+	);
+
+	while let Some(event) = download_manager.next_event(context).await {
+		job.advance(1);
+
+		match event {
+			DownloadEvent::Completed { url, items, blob } => {
+				for item in &items {
+					info!(
+						"Downloaded {} from {} to {}",
+						item.get_label(),
+						url,
+						blob.path.display()
+					);
+				}
+				downloaded += items.len();
+			}
+			DownloadEvent::Failed { url, items, error } => {
+				for item in &items {
+					error!(
+						"Failed to download {} from {}: {}",
+						item.get_label(),
+						url,
+						error
+					);
+				}
+				failed += items.len();
+				first_err.get_or_insert(error);
+			}
+		}
+	}
+
+	job.finish();
+	println!(
+		"Downloaded {} tracks and attachments, {} failed",
+		downloaded, failed
+	);
+	if paused > 0 {
+		println!("{} paused downloads were left paused", paused);
+	}
+
+	match first_err {
+		Some(err) => Err(err.with_message(format!(
+			"{} of {} downloads failed, the first with: {}",
+			failed,
+			downloaded + failed,
+			err
+		))),
+		None => Ok(()),
+	}
+}
@@ -0,0 +1,75 @@
+use similar::TextDiff;
+
+use crate::error::Error;
+use crate::media_sources::{recognize_pointer_from_string, PointerType};
+use crate::media_types::MetadataVersion;
+use crate::Context;
+
+/// Shows how the title and description of a track or post changed between
+/// scans.
+pub async fn history_command(url: String, context: &mut Context) -> Result<(), Error> {
+	let history = match recognize_pointer_from_string(&url) {
+		Some(PointerType::SoundgasmTrack(track)) => track.get_history(context).await?,
+		Some(PointerType::KemonoPost(post)) => post.get_history(context).await?,
+		Some(_) => {
+			return Err(Error::InvalidInput(format!(
+				"Only tracks and posts have a history: {}",
+				url
+			)))
+		}
+		None => {
+			return Err(Error::InvalidInput(format!(
+				"Unrecognized media source for: {}",
+				url
+			)))
+		}
+	};
+
+	let Some(first) = history.first() else {
+		return Err(Error::InvalidInput(format!(
+			"Not in the library yet, scan it first: {}",
+			url
+		)));
+	};
+
+	println!("{}  cataloged", format_time(first));
+	println!("title: {}", first.title);
+	for line in first.description.lines() {
+		println!("  {}", line);
+	}
+
+	for (previous, version) in history.iter().zip(history.iter().skip(1)) {
+		println!();
+		println!("{}  changed", format_time(version));
+
+		if previous.title != version.title {
+			println!("-title: {}", previous.title);
+			println!("+title: {}", version.title);
+		}
+
+		if previous.description != version.description {
+			// Without the trailing newlines the last lines would be marked as
+			// missing one
+			let old_description = format!("{}\n", previous.description);
+			let new_description = format!("{}\n", version.description);
+
+			print!(
+				"{}",
+				TextDiff::from_lines(&old_description, &new_description)
+					.unified_diff()
+					.context_radius(2)
+			);
+		}
+	}
+
+	if history.len() == 1 {
+		println!();
+		println!("No changes since it was cataloged");
+	}
+
+	Ok(())
+}
+
+fn format_time(version: &MetadataVersion) -> String {
+	version.since.format("%Y-%m-%d %H:%M").to_string()
+}
@@ -1,5 +1,25 @@
+mod download;
 mod gui;
+mod history;
+mod progress_bars;
 mod scan;
+mod search;
+mod status;
+mod subscribe;
+mod sync;
+mod tag;
+mod tui;
+mod verify;
 
+pub use download::download_command;
 pub use gui::start_gui;
+pub use history::history_command;
+pub use progress_bars::show_progress;
 pub use scan::scan_command;
+pub use search::{search_command, SearchArgs};
+pub use status::status_command;
+pub use subscribe::subscribe_command;
+pub use sync::sync_command;
+pub use tag::{tag_command, TagAction};
+pub use tui::tui_command;
+pub use verify::verify_command;
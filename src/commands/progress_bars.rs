@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::progress::{JobId, JobKind, ProgressEvent, ProgressState};
+
+/// Draws a bar on stderr for every running job until all senders are gone.
+/// Nothing is drawn if stderr isn't a terminal.
+pub async fn show_progress(mut rx: UnboundedReceiver<ProgressEvent>) {
+	let bars = MultiProgress::new();
+	let mut state = ProgressState::default();
+	let mut job_bars: HashMap<JobId, ProgressBar> = HashMap::new();
+
+	while let Some(event) = rx.recv().await {
+		state.apply(&event);
+
+		match event {
+			ProgressEvent::Started {
+				job,
+				kind,
+				label,
+				total,
+			} => {
+				let bar = match total {
+					Some(total) => ProgressBar::new(total),
+					None => ProgressBar::no_length(),
+				};
+				// Batches sum up the jobs below them, so they stay on top
+				let bar = match kind {
+					JobKind::Batch => bars.insert(0, bar),
+					_ => bars.add(bar),
+				};
+
+				bar.set_style(get_style(kind));
+				bar.set_message(label);
+				job_bars.insert(job, bar);
+			}
+			ProgressEvent::Advanced { job, done, total } => {
+				if let Some(bar) = job_bars.get(&job) {
+					if let Some(total) = total {
+						bar.set_length(total);
+					}
+					bar.set_position(done);
+				}
+			}
+			ProgressEvent::Finished { job } => {
+				if let Some(bar) = job_bars.remove(&job) {
+					bar.finish_and_clear();
+				}
+			}
+			ProgressEvent::Failed { job, message } => {
+				if let Some(bar) = job_bars.remove(&job) {
+					bar.finish_and_clear();
+					let _ = bars.println(format!("Failed {}: {}", bar.message(), message));
+				}
+			}
+		}
+
+		let downloaded = state.get_downloaded_bytes();
+		if downloaded > 0 {
+			for (job, progress) in &state.jobs {
+				if progress.kind != JobKind::Batch {
+					continue;
+				}
+
+				if let Some(bar) = job_bars.get(job) {
+					bar.set_message(format!(
+						"{}, {} downloaded",
+						progress.label,
+						HumanBytes(downloaded)
+					));
+				}
+			}
+		}
+	}
+}
+
+fn get_style(kind: JobKind) -> ProgressStyle {
+	let template = if kind.counts_bytes() {
+		"{msg:40!} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec} ETA {eta}"
+	} else {
+		"{msg:40!} [{bar:30}] {pos}/{len} ETA {eta}"
+	};
+
+	ProgressStyle::with_template(template)
+		.unwrap()
+		.progress_chars("=> ")
+}
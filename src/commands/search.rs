@@ -0,0 +1,262 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::error::Error;
+use crate::media_types::{LibraryItem, MediaType, SearchFilters};
+use crate::Context;
+
+/// Widest a title gets in the table before it's cut off
+const TABLE_TITLE_WIDTH: usize = 60;
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+	/// words that must all match; "quoted phrases" and prefix* work too
+	query: Vec<String>,
+
+	/// only show items from this soundgasm profile or Kemono creator id
+	#[arg(short, long, value_name = "SLUG")]
+	profile: Option<String>,
+
+	/// only show items of this type, e.g. mp3, mp4, jpg or pdf
+	#[arg(short = 't', long = "type", value_name = "TYPE", value_parser = parse_media_type)]
+	media_type: Option<MediaType>,
+
+	/// only show items with this tag, can be repeated
+	#[arg(long = "tag", value_name = "TAG")]
+	tags: Vec<String>,
+
+	/// only show items that have been downloaded
+	#[arg(long, conflicts_with = "not_downloaded")]
+	downloaded: bool,
+
+	/// only show items that haven't been downloaded
+	#[arg(long)]
+	not_downloaded: bool,
+
+	/// only show items that have been removed from their source
+	#[arg(long)]
+	deleted: bool,
+
+	/// only show items published (or cataloged) on or after this date
+	#[arg(long, value_name = "YYYY-MM-DD")]
+	since: Option<chrono::NaiveDate>,
+
+	/// only show items published (or cataloged) on or before this date
+	#[arg(long, value_name = "YYYY-MM-DD")]
+	until: Option<chrono::NaiveDate>,
+
+	/// most results to show
+	#[arg(short, long, default_value_t = 50, value_name = "COUNT")]
+	limit: i64,
+
+	/// how to print the results
+	#[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+	format: OutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+	Table,
+	/// one JSON object per line
+	Json,
+	Csv,
+}
+
+fn parse_media_type(value: &str) -> Result<MediaType, Error> {
+	MediaType::from_extension(value)
+		.or_else(|| MediaType::from_str(value).ok())
+		.ok_or_else(|| Error::InvalidInput(format!("unknown media type: {}", value)))
+}
+
+impl SearchArgs {
+	fn get_filters(&self) -> SearchFilters {
+		SearchFilters {
+			media_type: self.media_type,
+			tags: self.tags.clone(),
+			author: self.profile.clone(),
+			is_downloaded: match (self.downloaded, self.not_downloaded) {
+				(true, _) => Some(true),
+				(_, true) => Some(false),
+				_ => None,
+			},
+			is_deleted: self.deleted.then_some(true),
+			since: self.since.map(|date| date.and_time(chrono::NaiveTime::MIN)),
+			// Include the whole day
+			until: self
+				.until
+				.and_then(|date| date.succ_opt())
+				.map(|date| date.and_time(chrono::NaiveTime::MIN)),
+			limit: Some(self.limit),
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct SearchResultRow {
+	source: String,
+	media_type: String,
+	author: String,
+	item: String,
+	title: String,
+	url: String,
+	/// Where the file is stored, if it has been downloaded
+	path: Option<PathBuf>,
+}
+
+impl SearchResultRow {
+	fn new(item: &LibraryItem, context: &Context) -> Self {
+		Self {
+			source: item.get_source().as_ref().to_lowercase(),
+			media_type: item.get_type().to_string(),
+			author: item.get_author(),
+			item: item.get_label(),
+			title: item.get_title(),
+			url: item.get_url(),
+			path: item
+				.get_stored_path()
+				.map(|path| context.file_store.resolve_path(path)),
+		}
+	}
+}
+
+pub async fn search_command(args: SearchArgs, context: &mut Context) -> Result<(), Error> {
+	let query = args.query.join(" ");
+	let items = context.search(&query, &args.get_filters()).await?;
+
+	let rows = items
+		.iter()
+		.map(|item| SearchResultRow::new(item, context))
+		.collect::<Vec<_>>();
+
+	match args.format {
+		OutputFormat::Table => {
+			print_table(&rows);
+			Ok(())
+		}
+		OutputFormat::Json => print_json_lines(&rows),
+		OutputFormat::Csv => print_csv(&rows),
+	}
+}
+
+fn print_error(err: impl Display) -> Error {
+	Error::Filesystem(format!("Unable to print search results: {}", err))
+}
+
+fn print_json_lines(rows: &[SearchResultRow]) -> Result<(), Error> {
+	let mut stdout = std::io::stdout().lock();
+
+	for row in rows {
+		let line = serde_json::to_string(row).map_err(print_error)?;
+		writeln!(stdout, "{}", line).map_err(print_error)?;
+	}
+
+	Ok(())
+}
+
+fn print_csv(rows: &[SearchResultRow]) -> Result<(), Error> {
+	let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+
+	for row in rows {
+		writer.serialize(row).map_err(print_error)?;
+	}
+
+	writer.flush().map_err(print_error)
+}
+
+fn print_table(rows: &[SearchResultRow]) {
+	if rows.is_empty() {
+		println!("No matches");
+		return;
+	}
+
+	let titles = rows
+		.iter()
+		.map(|row| truncate_to_width(&row.title, TABLE_TITLE_WIDTH))
+		.collect::<Vec<_>>();
+
+	let type_width = column_width("TYPE", rows.iter().map(|row| row.media_type.as_str()));
+	let author_width = column_width("AUTHOR", rows.iter().map(|row| row.author.as_str()));
+	let title_width = column_width("TITLE", titles.iter().map(String::as_str));
+
+	println!(
+		"{} {} {} PATH",
+		pad_to_width("TYPE", type_width),
+		pad_to_width("AUTHOR", author_width),
+		pad_to_width("TITLE", title_width)
+	);
+
+	for (row, title) in rows.iter().zip(&titles) {
+		let path = match &row.path {
+			Some(path) => path.display().to_string(),
+			None => "-".to_string(),
+		};
+
+		println!(
+			"{} {} {} {}",
+			pad_to_width(&row.media_type, type_width),
+			pad_to_width(&row.author, author_width),
+			pad_to_width(title, title_width),
+			path
+		);
+	}
+}
+
+fn column_width<'a>(header: &str, values: impl Iterator<Item = &'a str>) -> usize {
+	values
+		.map(UnicodeWidthStr::width)
+		.fold(header.width(), usize::max)
+}
+
+/// Pads by display width, so wide characters still line up
+fn pad_to_width(text: &str, width: usize) -> String {
+	format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+	// Titles can contain newlines, which would break the table
+	let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+	if text.width() <= max_width {
+		return text;
+	}
+
+	let mut truncated = String::new();
+	let mut width = 0;
+
+	for c in text.chars() {
+		let char_width = c.width().unwrap_or(0);
+		if width + char_width > max_width - 1 {
+			break;
+		}
+		truncated.push(c);
+		width += char_width;
+	}
+
+	truncated.push('…');
+	truncated
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{pad_to_width, truncate_to_width};
+
+	#[test]
+	fn test_truncate_to_width() {
+		assert_eq!(truncate_to_width("short", 10), "short");
+		assert_eq!(truncate_to_width("a longer title", 8), "a longe…");
+		assert_eq!(truncate_to_width("日本語のタイトル", 7), "日本語…");
+		assert_eq!(truncate_to_width("two\nlines", 20), "two lines");
+	}
+
+	#[test]
+	fn test_pad_to_width() {
+		assert_eq!(pad_to_width("日本", 6), "日本  ");
+		assert_eq!(pad_to_width("toolong", 3), "toolong");
+	}
+}
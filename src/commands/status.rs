@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use indicatif::HumanBytes;
+
+use crate::error::Error;
+use crate::file_store::download_manager::{DownloadState, FileDownloadRow};
+use crate::Context;
+
+/// Lists the download jobs that haven't finished, running ones first and
+/// the queue in the order it will be downloaded.
+pub async fn status_command(context: &mut Context) -> Result<(), Error> {
+	let db_error =
+		|err: diesel::result::Error| Error::Database(format!("Unable to load downloads: {}", err));
+
+	let mut jobs = FileDownloadRow::load_jobs(&mut context.conn).map_err(db_error)?;
+	// Stable, so the queue keeps its order within each state
+	jobs.sort_by_key(|job| job.get_state());
+
+	let mut counts = HashMap::<DownloadState, usize>::new();
+	for job in &jobs {
+		*counts.entry(job.get_state()).or_default() += 1;
+	}
+	let count = |state| counts.get(&state).copied().unwrap_or_default();
+
+	let pending = jobs
+		.iter()
+		.filter(|job| job.get_state() != DownloadState::Done)
+		.collect::<Vec<_>>();
+
+	if !pending.is_empty() {
+		println!(
+			"{:<8} {:>8} {:>8} {:>23}  FILE",
+			"STATE", "PRIORITY", "ATTEMPTS", "DOWNLOADED"
+		);
+	}
+
+	for job in pending {
+		let downloaded = HumanBytes(
+			job
+				.get_downloaded_bytes(&mut context.conn)
+				.map_err(db_error)?,
+		);
+		let progress = match job.get_content_length() {
+			Some(length) => format!("{} / {}", downloaded, HumanBytes(length)),
+			None => downloaded.to_string(),
+		};
+
+		println!(
+			"{:<8} {:>8} {:>8} {:>23}  {}",
+			job.get_state().as_str(),
+			job.priority,
+			job.attempts,
+			progress,
+			job.label.as_deref().unwrap_or(&job.url)
+		);
+
+		if let Some(last_error) = &job.last_error {
+			println!("{:<8} {}", "", last_error);
+		}
+	}
+
+	println!(
+		"{} running, {} queued, {} paused, {} failed, {} done",
+		count(DownloadState::Running),
+		count(DownloadState::Queued),
+		count(DownloadState::Paused),
+		count(DownloadState::Failed),
+		count(DownloadState::Done)
+	);
+
+	Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::error::Error;
+use crate::media_sources::{Subscription, SubscriptionTarget};
+use crate::Context;
+
+/// Subscribes to the profile a URL points to, or lists the subscriptions if
+/// no URL is given.
+pub async fn subscribe_command(url: Option<String>, context: &mut Context) -> Result<(), Error> {
+	match url {
+		Some(url) => subscribe(&url, context).await,
+		None => list_subscriptions(context).await,
+	}
+}
+
+async fn subscribe(url: &str, context: &mut Context) -> Result<(), Error> {
+	let target = SubscriptionTarget::from_url(url)
+		.ok_or_else(|| Error::InvalidInput(format!("Unrecognized media source for: {}", url)))?;
+
+	if Subscription::add(context, &target).await? {
+		println!("Subscribed to {}, run sync to fetch it", target.get_url());
+	} else {
+		println!("Already subscribed to {}", target.get_url());
+	}
+
+	Ok(())
+}
+
+async fn list_subscriptions(context: &mut Context) -> Result<(), Error> {
+	for subscription in Subscription::list(context).await? {
+		let last_synced = match subscription.last_synced_at {
+			Some(synced_at) => synced_at.format("%Y-%m-%d %H:%M").to_string(),
+			None => "never synced".to_string(),
+		};
+
+		println!(
+			"{:<16} {:<10} {}",
+			last_synced, subscription.source, subscription.url
+		);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,90 @@
+use log::{error, info};
+
+use crate::error::Error;
+use crate::media_sources::Subscription;
+use crate::Context;
+
+/// Checks every subscription for new tracks and posts. If any subscription or
+/// item failed to sync, returns an error of the first failure's kind.
+pub async fn sync_command(context: &mut Context) -> Result<(), Error> {
+	let subscriptions = Subscription::list(context).await?;
+
+	if subscriptions.is_empty() {
+		println!("No subscriptions, add one with subscribe <url>");
+		return Ok(());
+	}
+
+	let mut new_count = 0;
+	let mut failed_items = 0;
+	let mut failed = 0;
+	let mut first_err = None;
+
+	for mut subscription in subscriptions {
+		let Some(target) = subscription.get_target() else {
+			let err = Error::InvalidInput(format!(
+				"Unrecognized subscription URL: {}",
+				subscription.url
+			));
+			error!("{}", err);
+			first_err.get_or_insert(err);
+			failed += 1;
+			continue;
+		};
+
+		info!("Syncing {}", subscription.url);
+		let synced_at = chrono::Utc::now().naive_utc();
+
+		let report = match target.sync(context).await {
+			Ok(report) => report,
+			Err(err) => {
+				error!("Failed to sync {}: {}", subscription.url, err);
+				eprintln!("Failed to sync {}: {}", subscription.url, err);
+				first_err.get_or_insert(err);
+				failed += 1;
+				continue;
+			}
+		};
+
+		let since = match subscription.last_synced_at {
+			Some(last_synced_at) => last_synced_at.format("%Y-%m-%d %H:%M").to_string(),
+			None => "subscribing".to_string(),
+		};
+		println!(
+			"{}: {} new since {}",
+			subscription.url,
+			report.new_items.len(),
+			since
+		);
+
+		for item in &report.new_items {
+			println!("  {} {}", item.title, item.url);
+		}
+		for (url, err) in &report.failed {
+			println!("  failed {}: {}", url, err);
+		}
+		if let Some((_, err)) = report.failed.first() {
+			first_err.get_or_insert(err.clone());
+		}
+		new_count += report.new_items.len();
+		failed_items += report.failed.len();
+
+		if let Err(err) = subscription.set_synced(context, synced_at).await {
+			error!("{}", err);
+			first_err.get_or_insert(err);
+			failed += 1;
+		}
+	}
+
+	println!(
+		"Found {} new items, {} items and {} subscriptions failed",
+		new_count, failed_items, failed
+	);
+
+	match first_err {
+		Some(err) => Err(err.with_message(format!(
+			"{} items and {} subscriptions failed to sync, the first with: {}",
+			failed_items, failed, err
+		))),
+		None => Ok(()),
+	}
+}
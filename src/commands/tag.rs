@@ -0,0 +1,125 @@
+use clap::Subcommand;
+
+use crate::error::Error;
+use crate::media_sources::{recognize_pointer_from_string, PointerType};
+use crate::media_types::{list_tags_with_counts, normalize_tag, TaggedItem};
+use crate::Context;
+
+#[derive(Subcommand, Debug)]
+pub enum TagAction {
+	/// tag a cataloged track or post
+	#[command(arg_required_else_help = true)]
+	Add {
+		/// URL of the track or post
+		url: String,
+
+		/// tags to add
+		#[arg(required = true)]
+		tags: Vec<String>,
+	},
+	/// remove tags from a cataloged track or post
+	#[command(arg_required_else_help = true)]
+	Remove {
+		/// URL of the track or post
+		url: String,
+
+		/// tags to remove
+		#[arg(required = true)]
+		tags: Vec<String>,
+	},
+	/// list the tags of one item, or every tag in the library
+	List {
+		/// URL of the track or post
+		url: Option<String>,
+	},
+}
+
+pub async fn tag_command(action: TagAction, context: &mut Context) -> Result<(), Error> {
+	match action {
+		TagAction::Add { url, tags } => add_tags(&url, &tags, context).await,
+		TagAction::Remove { url, tags } => remove_tags(&url, &tags, context).await,
+		TagAction::List { url: Some(url) } => list_item_tags(&url, context).await,
+		TagAction::List { url: None } => list_all_tags(context).await,
+	}
+}
+
+/// Finds the library item a URL points to. Only tracks and posts that have
+/// already been scanned can be tagged.
+async fn find_tagged_item(url: &str, context: &mut Context) -> Result<TaggedItem, Error> {
+	let (tagged_item, is_in_library) = match recognize_pointer_from_string(url) {
+		Some(PointerType::SoundgasmTrack(track)) => {
+			(track.get_tagged_item(), track.is_in_library(context).await)
+		}
+		Some(PointerType::KemonoPost(post)) => {
+			(post.get_tagged_item(), post.is_in_library(context).await)
+		}
+		Some(_) => {
+			return Err(Error::InvalidInput(format!(
+				"Only tracks and posts can be tagged: {}",
+				url
+			)))
+		}
+		None => {
+			return Err(Error::InvalidInput(format!(
+				"Unrecognized media source for: {}",
+				url
+			)))
+		}
+	};
+
+	if !is_in_library {
+		return Err(Error::InvalidInput(format!(
+			"Not in the library yet, scan it first: {}",
+			url
+		)));
+	}
+
+	Ok(tagged_item)
+}
+
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, Error> {
+	tags
+		.iter()
+		.map(|tag| {
+			normalize_tag(tag).ok_or_else(|| Error::InvalidInput(format!("Invalid tag: {:?}", tag)))
+		})
+		.collect()
+}
+
+async fn add_tags(url: &str, tags: &[String], context: &mut Context) -> Result<(), Error> {
+	let tags = normalize_tags(tags)?;
+	let tagged_item = find_tagged_item(url, context).await?;
+
+	tagged_item.add_tags(context, &tags).await?;
+	println!("Tagged {} with {}", tagged_item.item_id, tags.join(", "));
+
+	Ok(())
+}
+
+async fn remove_tags(url: &str, tags: &[String], context: &mut Context) -> Result<(), Error> {
+	let tags = normalize_tags(tags)?;
+	let tagged_item = find_tagged_item(url, context).await?;
+
+	let removed = tagged_item.remove_tags(context, &tags).await?;
+	println!("Removed {} tags from {}", removed, tagged_item.item_id);
+
+	Ok(())
+}
+
+async fn list_item_tags(url: &str, context: &mut Context) -> Result<(), Error> {
+	let tagged_item = find_tagged_item(url, context).await?;
+
+	for tag in tagged_item.get_tags(context).await? {
+		println!("{}", tag);
+	}
+
+	Ok(())
+}
+
+async fn list_all_tags(context: &mut Context) -> Result<(), Error> {
+	for (tag, count) in list_tags_with_counts(context).await? {
+		println!("{:>6} {}", count, tag);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use indicatif::HumanBytes;
+use ratatui::{
+	crossterm::event::{KeyCode, KeyEvent},
+	layout::{Constraint, Layout, Rect},
+	style::{Color, Modifier, Style, Stylize},
+	text::Line,
+	widgets::LineGauge,
+	Frame,
+};
+
+use super::{get_block, FOCUSED_STYLE};
+use crate::file_store::download_manager::{DownloadInfo, DownloadState};
+use crate::progress::ProgressState;
+
+/// How far back the download speed is averaged
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(3);
+
+/// Queued, running, paused and failed downloads, one line each
+#[derive(Debug, Default)]
+pub struct DownloadsPanel {
+	downloads: Vec<DownloadInfo>,
+	selected: usize,
+	/// First download in view
+	offset: usize,
+	throughput: Throughput,
+}
+
+impl DownloadsPanel {
+	/// Replaces the list, keeping the same download selected if it's still
+	/// there
+	pub fn set_downloads(&mut self, downloads: Vec<DownloadInfo>) {
+		let selected_url = self.get_selected().map(|download| download.url.clone());
+
+		self.downloads = downloads;
+		self.selected = selected_url
+			.and_then(|url| {
+				self
+					.downloads
+					.iter()
+					.position(|download| download.url == url)
+			})
+			.unwrap_or(self.selected)
+			.min(self.downloads.len().saturating_sub(1));
+	}
+
+	pub fn get_selected(&self) -> Option<&DownloadInfo> {
+		self.downloads.get(self.selected)
+	}
+
+	/// Counts the bytes downloaded so far towards the download speed
+	pub fn record_downloaded(&mut self, now: Instant, downloaded: u64) {
+		self.throughput.record(now, downloaded);
+	}
+
+	/// Moves the selection. Returns true if the key was used.
+	pub fn on(&mut self, key: KeyEvent) -> bool {
+		let last = self.downloads.len().saturating_sub(1);
+
+		self.selected = match key.code {
+			KeyCode::Up | KeyCode::Char('k') => self.selected.saturating_sub(1),
+			KeyCode::Down | KeyCode::Char('j') => (self.selected + 1).min(last),
+			KeyCode::Home => 0,
+			KeyCode::End => last,
+			_ => return false,
+		};
+
+		true
+	}
+
+	pub fn view(&mut self, frame: &mut Frame, area: Rect, progress: &ProgressState, focused: bool) {
+		let title = format!(
+			"Downloads ({}), {}/s",
+			self.downloads.len(),
+			HumanBytes(self.throughput.get_bytes_per_second() as u64)
+		);
+		let block = get_block(&title, focused);
+		let inner = block.inner(area);
+		frame.render_widget(block, area);
+
+		if self.downloads.is_empty() {
+			frame.render_widget(
+				Line::from("Nothing queued, press d on a result to download it"),
+				inner,
+			);
+			return;
+		}
+
+		// Scrolls just enough to keep the selected download in view
+		let height = inner.height.max(1) as usize;
+		self.offset = self
+			.offset
+			.min(self.selected)
+			.max((self.selected + 1).saturating_sub(height));
+
+		let rows = Layout::vertical(vec![Constraint::Length(1); height]).split(inner);
+
+		for ((index, download), row) in self
+			.downloads
+			.iter()
+			.enumerate()
+			.skip(self.offset)
+			.zip(rows.iter())
+		{
+			let [label_area, state_area, progress_area] = Layout::horizontal([
+				Constraint::Fill(3),
+				Constraint::Length(8),
+				Constraint::Fill(2),
+			])
+			.spacing(1)
+			.areas(*row);
+
+			let label_style = match focused && index == self.selected {
+				true => FOCUSED_STYLE.add_modifier(Modifier::REVERSED),
+				false => Style::new(),
+			};
+			frame.render_widget(
+				Line::styled(download.label.as_str(), label_style),
+				label_area,
+			);
+			frame.render_widget(Line::from(download.state.as_str()), state_area);
+
+			let job = download.job.and_then(|job| progress.jobs.get(&job));
+
+			match (download.state, job, &download.error) {
+				(DownloadState::Running, Some(job), _) => {
+					let ratio = match job.total {
+						Some(total) if total > 0 => (job.done as f64 / total as f64).min(1.0),
+						_ => 0.0,
+					};
+					let label = match job.total {
+						Some(total) => format!("{}/{}", HumanBytes(job.done), HumanBytes(total)),
+						None => HumanBytes(job.done).to_string(),
+					};
+
+					let gauge = LineGauge::default()
+						.ratio(ratio)
+						.label(label)
+						.filled_style(Style::new().fg(Color::Green));
+					frame.render_widget(gauge, progress_area);
+				}
+				(DownloadState::Failed, _, Some(error)) => {
+					frame.render_widget(Line::from(error.to_string().red()), progress_area);
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Bytes per second over the last few seconds
+#[derive(Debug, Default)]
+struct Throughput {
+	samples: VecDeque<(Instant, u64)>,
+}
+
+impl Throughput {
+	fn record(&mut self, now: Instant, downloaded: u64) {
+		self.samples.push_back((now, downloaded));
+
+		while let Some(&(time, _)) = self.samples.front() {
+			if now.duration_since(time) <= THROUGHPUT_WINDOW {
+				break;
+			}
+			self.samples.pop_front();
+		}
+	}
+
+	fn get_bytes_per_second(&self) -> f64 {
+		let (Some(&(first_time, first)), Some(&(last_time, last))) =
+			(self.samples.front(), self.samples.back())
+		else {
+			return 0.0;
+		};
+
+		let elapsed = last_time.duration_since(first_time).as_secs_f64();
+		if elapsed == 0.0 {
+			return 0.0;
+		}
+
+		// Failed downloads stop counting, which can make the total drop
+		last.saturating_sub(first) as f64 / elapsed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use ratatui::{
+		backend::TestBackend,
+		crossterm::event::{KeyCode, KeyEvent},
+		Terminal,
+	};
+	use reqwest::Url;
+
+	use super::{DownloadsPanel, Throughput};
+	use crate::error::Error;
+	use crate::file_store::download_manager::{DownloadInfo, DownloadState};
+	use crate::progress::{JobKind, ProgressSender, ProgressState};
+
+	fn download(name: &str, state: DownloadState) -> DownloadInfo {
+		DownloadInfo {
+			url: Url::parse(&format!("https://media.soundgasm.net/sounds/{}.m4a", name)).unwrap(),
+			label: format!("sgdl-test/{}", name),
+			state,
+			job: None,
+			error: None,
+		}
+	}
+
+	#[test]
+	fn test_view_downloads() {
+		let (progress, mut progress_rx) = ProgressSender::channel();
+		let mut job = progress.start_job(JobKind::Download, "running.m4a", None);
+		job.set_done(512, Some(2048));
+
+		let mut state = ProgressState::default();
+		while let Ok(event) = progress_rx.try_recv() {
+			state.apply(&event);
+		}
+
+		let mut panel = DownloadsPanel::default();
+		panel.set_downloads(vec![
+			DownloadInfo {
+				job: Some(job.get_id()),
+				..download("running", DownloadState::Running)
+			},
+			download("queued", DownloadState::Queued),
+			DownloadInfo {
+				error: Some(Error::NotFound("gone".to_string())),
+				..download("failed", DownloadState::Failed)
+			},
+		]);
+
+		let mut terminal = Terminal::new(TestBackend::new(80, 5)).unwrap();
+		terminal
+			.draw(|frame| panel.view(frame, frame.area(), &state, true))
+			.unwrap();
+
+		let lines = terminal
+			.backend()
+			.buffer()
+			.content()
+			.chunks(80)
+			.map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+			.collect::<Vec<_>>();
+		assert!(lines[0].contains("Downloads (3)"));
+		assert!(lines[1].contains("sgdl-test/running") && lines[1].contains("512 B/2.00 KiB"));
+		assert!(lines[2].contains("sgdl-test/queued") && lines[2].contains("queued"));
+		assert!(lines[3].contains("sgdl-test/failed") && lines[3].contains("gone"));
+
+		// The selection follows the download when the list changes
+		panel.on(KeyEvent::from(KeyCode::Down));
+		panel.set_downloads(vec![
+			download("queued", DownloadState::Running),
+			download("failed", DownloadState::Failed),
+		]);
+		assert_eq!(panel.get_selected().unwrap().label, "sgdl-test/queued");
+
+		job.finish();
+	}
+
+	#[test]
+	fn test_throughput() {
+		let start = Instant::now();
+		let mut throughput = Throughput::default();
+		assert_eq!(throughput.get_bytes_per_second(), 0.0);
+
+		throughput.record(start, 0);
+		throughput.record(start + Duration::from_secs(1), 1000);
+		throughput.record(start + Duration::from_secs(2), 3000);
+		assert_eq!(throughput.get_bytes_per_second(), 1500.0);
+
+		// Older samples drop out of the window
+		throughput.record(start + Duration::from_secs(5), 3000);
+		assert_eq!(throughput.get_bytes_per_second(), 0.0);
+	}
+}
@@ -0,0 +1,80 @@
+use ratatui::{
+	crossterm::event::{KeyCode, KeyEvent},
+	layout::Rect,
+	style::{Modifier, Style},
+	text::Line,
+	widgets::{Paragraph, Wrap},
+	Frame,
+};
+
+use super::get_block;
+use crate::media_types::LibraryItem;
+
+/// Everything known about the selected item, with its full description
+#[derive(Debug, Default)]
+pub struct ItemDetails {
+	scroll: u16,
+}
+
+impl ItemDetails {
+	/// Starts at the top again, for when another item is selected
+	pub fn reset_scroll(&mut self) {
+		self.scroll = 0;
+	}
+
+	/// Scrolls the description. Returns true if the key was used.
+	pub fn on(&mut self, key: KeyEvent) -> bool {
+		match key.code {
+			KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+			KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+			KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+			KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+			KeyCode::Home => self.scroll = 0,
+			_ => return false,
+		}
+
+		true
+	}
+
+	pub fn view(&mut self, frame: &mut Frame, area: Rect, item: Option<&LibraryItem>, focused: bool) {
+		let block = get_block("Details", focused);
+
+		let Some(item) = item else {
+			frame.render_widget(Paragraph::new("Nothing selected").block(block), area);
+			return;
+		};
+
+		let stored = match item.get_stored_path() {
+			Some(path) => format!("Stored at {}", path.display()),
+			None => "Not downloaded".to_string(),
+		};
+
+		let mut lines = vec![
+			Line::styled(item.get_title(), Style::new().add_modifier(Modifier::BOLD)),
+			Line::from(format!(
+				"{} on {}, {}",
+				item.get_author(),
+				item.get_source(),
+				item.get_type()
+			)),
+			Line::from(item.get_url()),
+			Line::from(stored),
+			Line::default(),
+		];
+		lines.extend(
+			item
+				.get_description()
+				.lines()
+				.map(|line| Line::from(line.to_string())),
+		);
+
+		let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+
+		// Stops scrolling once the end of the description is in view
+		let height = paragraph.line_count(area.width.saturating_sub(2));
+		let max_scroll = height.saturating_sub(area.height.saturating_sub(2) as usize);
+		self.scroll = self.scroll.min(max_scroll as u16);
+
+		frame.render_widget(paragraph.scroll((self.scroll, 0)).block(block), area);
+	}
+}
@@ -1,102 +1,146 @@
-use rxtui::prelude::*;
+use ratatui::{
+	crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+	layout::Rect,
+	widgets::Paragraph,
+	Frame,
+};
+use unicode_width::UnicodeWidthStr;
 
-use crate::commands::tui::Msg;
+use super::get_block;
 
-#[derive(Default, MockComponent)]
+/// A single line of editable text
+#[derive(Debug, Default)]
 pub struct LineInput {
-	component: Input,
+	title: String,
+	value: String,
+	/// Byte offset of the cursor in `value`, always on a char boundary
+	cursor: usize,
 }
 
-impl Component<Msg, NoUserEvent> for LineInput {
-	fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
-		use tuirealm::command::Direction;
-
-		match ev {
-			Event::Keyboard(key) => {
-				log::debug!("LineInput KeyEvent: {:?}", key);
-				match key.code {
-					Key::Char(c) => {
-						self.perform(Cmd::Type(c));
-					}
-					Key::Backspace => {
-						self.perform(Cmd::Delete);
-					}
-					Key::Enter => {
-						self.perform(Cmd::Submit);
-					}
-					Key::Esc => {
-						self.perform(Cmd::Cancel);
-					}
-					Key::Left => {
-						self.perform(Cmd::Move(Direction::Left));
-					}
-					Key::Right => {
-						self.perform(Cmd::Move(Direction::Right));
-					}
-					Key::Up => {
-						self.perform(Cmd::Move(Direction::Up));
-					}
-					Key::Down => {
-						self.perform(Cmd::Move(Direction::Down));
-					}
-					Key::PageUp => {
-						self.perform(Cmd::Scroll(Direction::Up));
-					}
-					Key::PageDown => {
-						self.perform(Cmd::Scroll(Direction::Down));
-					}
-					_ => {}
+impl LineInput {
+	pub fn new(title: impl Into<String>) -> Self {
+		Self {
+			title: title.into(),
+			..Default::default()
+		}
+	}
+
+	pub fn get_value(&self) -> &str {
+		&self.value
+	}
+
+	/// Empties the input, returning what was typed
+	pub fn take_value(&mut self) -> String {
+		self.cursor = 0;
+		std::mem::take(&mut self.value)
+	}
+
+	/// Edits the text or moves the cursor. Returns true if the text changed.
+	pub fn on(&mut self, key: KeyEvent) -> bool {
+		let is_control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+		match key.code {
+			KeyCode::Char('u') if is_control => {
+				// Like in a shell, clears everything before the cursor
+				self.value.drain(..self.cursor);
+				self.cursor = 0;
+				true
+			}
+			KeyCode::Char(c) if !is_control => {
+				self.value.insert(self.cursor, c);
+				self.cursor += c.len_utf8();
+				true
+			}
+			KeyCode::Backspace => match self.value[..self.cursor].chars().next_back() {
+				Some(c) => {
+					self.cursor -= c.len_utf8();
+					self.value.remove(self.cursor);
+					true
+				}
+				None => false,
+			},
+			KeyCode::Delete if self.cursor < self.value.len() => {
+				self.value.remove(self.cursor);
+				true
+			}
+			KeyCode::Left => {
+				if let Some(c) = self.value[..self.cursor].chars().next_back() {
+					self.cursor -= c.len_utf8();
 				}
-				None
+				false
 			}
-			Event::Paste(value) => {
-				log::debug!("Paste into LineInput: {}", value);
-				value.chars().for_each(|c| {
-					self.perform(Cmd::Type(c));
-				});
-				None
+			KeyCode::Right => {
+				if let Some(c) = self.value[self.cursor..].chars().next() {
+					self.cursor += c.len_utf8();
+				}
+				false
+			}
+			KeyCode::Home => {
+				self.cursor = 0;
+				false
 			}
-			_ => None,
+			KeyCode::End => {
+				self.cursor = self.value.len();
+				false
+			}
+			_ => false,
+		}
+	}
+
+	pub fn view(&self, frame: &mut Frame, area: Rect, focused: bool) {
+		// Scrolls sideways so the cursor stays inside the borders
+		let width = area.width.saturating_sub(2) as usize;
+		let cursor_x = self.value[..self.cursor].width();
+		let scroll = cursor_x.saturating_sub(width.saturating_sub(1));
+
+		let paragraph = Paragraph::new(self.value.as_str())
+			.scroll((0, scroll as u16))
+			.block(get_block(&self.title, focused));
+		frame.render_widget(paragraph, area);
+
+		if focused {
+			frame.set_cursor_position((area.x + 1 + (cursor_x - scroll) as u16, area.y + 1));
 		}
 	}
 }
 
-// impl MockComponent for LineInput {
-// 	fn view(&mut self, frame: &mut Frame, area: Rect) {
-// 		let block = Block::new().style().borders(attr.border);
-
-// 		let block = if let Some(title) = attr.title {
-// 			block.title(title)
-// 		} else {
-// 			block
-// 		};
-
-// 		let block = if let Some(title) = attr.title_bottom {
-// 			block.title_bottom(title)
-// 		} else {
-// 			block
-// 		};
-
-// 		let p = Paragraph::new(self.state.value())
-// 			// .wrap(Wrap { trim: true })
-// 			.block(block);
-
-// 		// let p = if self.focused { p.yellow() } else { p };
-
-// 		frame.render_widget(p, area);
-// 	}
-
-// 	// fn handle_events(&mut self, event: &Event) {
-// 	// 	match event {
-// 	// 		Event::Paste(value) => {
-// 	// 			log::debug!("Paste into LineInput: {}", value);
-// 	// 			self.state = InputState::new(value.clone());
-// 	// 		}
-// 	// 		Event::Key(key) => {
-// 	// 			log::debug!("LineInput KeyEvent: {:?}", key);
-// 	// 			self.state.handle_event(event);
-// 	// 		}
-// 	// 		_ => {}
-// 	// 	}
-// 	// }
-// }
+#[cfg(test)]
+mod tests {
+	use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+	use super::LineInput;
+
+	fn press(input: &mut LineInput, code: KeyCode) -> bool {
+		input.on(KeyEvent::new(code, KeyModifiers::NONE))
+	}
+
+	#[test]
+	fn test_line_input_editing() {
+		let mut input = LineInput::new("Search");
+
+		for c in "rainé".chars() {
+			assert!(press(&mut input, KeyCode::Char(c)));
+		}
+		assert_eq!(input.get_value(), "rainé");
+
+		// Moving the cursor doesn't change the text
+		assert!(!press(&mut input, KeyCode::Left));
+		assert!(!press(&mut input, KeyCode::Left));
+		assert!(press(&mut input, KeyCode::Backspace));
+		assert_eq!(input.get_value(), "rané");
+
+		assert!(press(&mut input, KeyCode::Delete));
+		assert!(press(&mut input, KeyCode::Delete));
+		assert_eq!(input.get_value(), "ra");
+		assert!(!press(&mut input, KeyCode::Delete));
+
+		assert!(!press(&mut input, KeyCode::Home));
+		assert!(press(&mut input, KeyCode::Char('b')));
+		assert_eq!(input.get_value(), "bra");
+
+		assert!(!press(&mut input, KeyCode::End));
+		assert!(input.on(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL)));
+		assert_eq!(input.get_value(), "");
+		assert!(!press(&mut input, KeyCode::Backspace));
+	}
+}
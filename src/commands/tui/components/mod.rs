@@ -1,5 +1,30 @@
+mod downloads_panel;
+mod item_details;
 mod line_input;
+mod player_bar;
 mod popup_input;
+mod results_table;
 
+use ratatui::{
+	style::{Color, Style},
+	widgets::{Block, Borders},
+};
+
+pub use downloads_panel::DownloadsPanel;
+pub use item_details::ItemDetails;
 pub use line_input::LineInput;
+pub use player_bar::view_player;
 pub use popup_input::PopupInput;
+pub use results_table::ResultsTable;
+
+const FOCUSED_STYLE: Style = Style::new().fg(Color::Yellow);
+
+/// The border around every component, highlighted while it has the focus
+fn get_block(title: &str, focused: bool) -> Block<'_> {
+	let block = Block::default().borders(Borders::ALL).title(title);
+
+	match focused {
+		true => block.border_style(FOCUSED_STYLE),
+		false => block,
+	}
+}
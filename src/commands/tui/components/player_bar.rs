@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use ratatui::{
+	layout::{Constraint, Layout, Rect},
+	style::{Color, Style, Stylize},
+	text::Line,
+	widgets::LineGauge,
+	Frame,
+};
+
+use crate::player::Player;
+
+/// The playing track on a single line, with its position and the volume
+pub fn view_player(frame: &mut Frame, area: Rect, player: Option<&Player>) {
+	let Some(player) = player else {
+		frame.render_widget(
+			Line::from(" Nothing playing, press Space on a result to play it".dark_gray()),
+			area,
+		);
+		return;
+	};
+
+	let [track_area, position_area, volume_area] = Layout::horizontal([
+		Constraint::Fill(2),
+		Constraint::Fill(3),
+		Constraint::Length(8),
+	])
+	.spacing(1)
+	.areas(area);
+
+	let track = match (player.get_loading(), player.get_track()) {
+		(Some(loading), _) => format!(" Loading {}", loading.label),
+		(None, Some(track)) => {
+			let state = match (player.is_finished(), player.is_paused()) {
+				(true, _) => "■",
+				(false, true) => "⏸",
+				(false, false) => "▶",
+			};
+			format!(" {} {}", state, track.label)
+		}
+		(None, None) => String::new(),
+	};
+	frame.render_widget(Line::from(track), track_area);
+
+	if let Some(track) = player.get_track() {
+		let position = player.get_position();
+		let (ratio, label) = match track.duration {
+			Some(duration) if !duration.is_zero() => (
+				(position.as_secs_f64() / duration.as_secs_f64()).min(1.0),
+				format!(
+					"{}/{}",
+					format_position(position),
+					format_position(duration)
+				),
+			),
+			_ => (0.0, format_position(position)),
+		};
+
+		let gauge = LineGauge::default()
+			.ratio(ratio)
+			.label(label)
+			.filled_style(Style::new().fg(Color::Cyan));
+		frame.render_widget(gauge, position_area);
+	}
+
+	let volume = format!("vol {:>3}%", (player.get_volume() * 100.0).round() as u32);
+	frame.render_widget(Line::from(volume).right_aligned(), volume_area);
+}
+
+/// Minutes and seconds, with hours for long tracks
+fn format_position(position: Duration) -> String {
+	let seconds = position.as_secs();
+
+	match seconds / 3600 {
+		0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+		hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::format_position;
+
+	#[test]
+	fn test_format_position() {
+		assert_eq!(format_position(Duration::from_millis(59_900)), "0:59");
+		assert_eq!(format_position(Duration::from_secs(754)), "12:34");
+		assert_eq!(format_position(Duration::from_secs(3600 + 62)), "1:01:02");
+	}
+}
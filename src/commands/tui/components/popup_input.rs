@@ -1,42 +1,49 @@
-use tuirealm::MockComponent;
+use ratatui::{
+	crossterm::event::KeyEvent,
+	layout::{Constraint, Flex, Layout, Rect},
+	widgets::Clear,
+	Frame,
+};
 
 use super::LineInput;
 
-#[derive(Default, MockComponent)]
+/// Widest the popup gets on large terminals
+const POPUP_WIDTH: u16 = 80;
+
+/// A line input drawn in a box over the middle of the screen
+#[derive(Debug)]
 pub struct PopupInput {
-	component: LineInput,
+	input: LineInput,
 }
 
-// impl Component<Msg, UserEvent> for PopupInput {
-// 	fn on(&mut self, ev: Event<UserEvent>) -> Msg {
-// 		// TODO
-// 	}
-
-// 	fn update(&mut self, action: &Self::Action) {
-// 		self.input.update(action);
-// 	}
-
-// 	fn handle_events(&mut self, event: &Event) {
-// 		self.input.handle_events(event);
-// 	}
-
-// 	fn draw(&mut self, frame: &mut Frame, area: Rect, attr: Self::Attr) {
-// 		// Center the popup in the area
-// 		let popup_width = 40;
-// 		let popup_height = 3;
-// 		let x = area.x + (area.width.saturating_sub(popup_width)) / 2;
-// 		let y = area.y + (area.height.saturating_sub(popup_height)) / 2;
-// 		let popup_area = Rect {
-// 			x,
-// 			y,
-// 			width: popup_width,
-// 			height: popup_height,
-// 		};
-
-// 		// Draw a clear background for the popup
-// 		frame.render_widget(widgets::Clear, popup_area);
-
-// 		// Draw the input with a border
-// 		self.input.draw(frame, popup_area, attr);
-// 	}
-// }
+impl PopupInput {
+	pub fn new(title: impl Into<String>) -> Self {
+		Self {
+			input: LineInput::new(title),
+		}
+	}
+
+	/// Empties the input, returning what was typed
+	pub fn take_value(&mut self) -> String {
+		self.input.take_value()
+	}
+
+	/// Edits the text. Returns true if the text changed.
+	pub fn on(&mut self, key: KeyEvent) -> bool {
+		self.input.on(key)
+	}
+
+	/// Always drawn focused, since it covers whatever had the focus before
+	pub fn view(&self, frame: &mut Frame, area: Rect) {
+		let [popup_area] = Layout::vertical([Constraint::Length(3)])
+			.flex(Flex::Center)
+			.areas(area);
+		let [popup_area] = Layout::horizontal([Constraint::Max(POPUP_WIDTH)])
+			.flex(Flex::Center)
+			.horizontal_margin(2)
+			.areas(popup_area);
+
+		frame.render_widget(Clear, popup_area);
+		self.input.view(frame, popup_area, true);
+	}
+}
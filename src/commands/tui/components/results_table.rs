@@ -0,0 +1,108 @@
+use indicatif::HumanBytes;
+use ratatui::{
+	crossterm::event::{KeyCode, KeyEvent},
+	layout::{Constraint, Rect},
+	style::{Modifier, Style},
+	widgets::{Row, Table, TableState},
+	Frame,
+};
+
+use super::{get_block, FOCUSED_STYLE};
+use crate::media_types::LibraryItem;
+
+/// How many rows Page Up and Page Down move
+const PAGE_ROWS: isize = 10;
+
+/// The items matching the search, one row each
+#[derive(Debug, Default)]
+pub struct ResultsTable {
+	items: Vec<LibraryItem>,
+	state: TableState,
+}
+
+impl ResultsTable {
+	/// Replaces the rows, keeping the same item selected if it's still
+	/// there and selecting the first one otherwise
+	pub fn set_items(&mut self, items: Vec<LibraryItem>) {
+		let selected_url = self.get_selected().map(LibraryItem::get_url);
+
+		self.items = items;
+		let selected = selected_url
+			.and_then(|url| self.items.iter().position(|item| item.get_url() == url))
+			.or(if self.items.is_empty() { None } else { Some(0) });
+
+		self.state = TableState::default();
+		self.state.select(selected);
+	}
+
+	pub fn get_selected(&self) -> Option<&LibraryItem> {
+		self.items.get(self.state.selected()?)
+	}
+
+	/// Moves the selection. Returns true if another row was selected.
+	pub fn on(&mut self, key: KeyEvent) -> bool {
+		let offset = match key.code {
+			KeyCode::Up | KeyCode::Char('k') => -1,
+			KeyCode::Down | KeyCode::Char('j') => 1,
+			KeyCode::PageUp => -PAGE_ROWS,
+			KeyCode::PageDown => PAGE_ROWS,
+			KeyCode::Home => isize::MIN,
+			KeyCode::End => isize::MAX,
+			_ => return false,
+		};
+
+		let Some(selected) = self.state.selected() else {
+			return false;
+		};
+		let last = self.items.len().saturating_sub(1);
+		let next = selected.saturating_add_signed(offset).min(last);
+
+		self.state.select(Some(next));
+
+		next != selected
+	}
+
+	pub fn view(&mut self, frame: &mut Frame, area: Rect, focused: bool) {
+		let header = Row::new(["Title", "Author", "Downloaded", "Size"])
+			.style(Style::new().add_modifier(Modifier::BOLD));
+
+		let rows = self.items.iter().map(|item| {
+			let size = match item.get_stored_size() {
+				Some(size) => HumanBytes(size).to_string(),
+				None => "-".to_string(),
+			};
+			let downloaded = match item.get_stored_path() {
+				Some(_) => "yes",
+				None => "no",
+			};
+
+			// Titles can contain newlines, which would hide the rest of the row
+			Row::new([
+				item
+					.get_title()
+					.split_whitespace()
+					.collect::<Vec<_>>()
+					.join(" "),
+				item.get_author(),
+				downloaded.to_string(),
+				size,
+			])
+		});
+
+		let title = format!("Results ({})", self.items.len());
+		let table = Table::new(
+			rows,
+			[
+				Constraint::Fill(1),
+				Constraint::Max(20),
+				Constraint::Length(10),
+				Constraint::Length(10),
+			],
+		)
+		.header(header)
+		.block(get_block(&title, focused))
+		.row_highlight_style(FOCUSED_STYLE.add_modifier(Modifier::REVERSED));
+
+		frame.render_stateful_widget(table, area, &mut self.state);
+	}
+}
@@ -1,197 +1,966 @@
 mod components;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use ratatui::layout::{Constraint, Direction, Layout};
-use tuirealm::{
-	terminal::{CrosstermTerminalAdapter, TerminalAdapter, TerminalBridge},
-	Application, EventListenerCfg, NoUserEvent, PollStrategy, Update,
+use futures_util::StreamExt;
+use ratatui::{
+	crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+	layout::{Constraint, Layout},
+	style::{Color, Stylize},
+	text::Line,
+	DefaultTerminal, Frame,
 };
+use reqwest::Url;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
+use crate::context::ScanReport;
+use crate::error::Error;
+use crate::file_store::download_manager::{DownloadEvent, DownloadManager, DownloadState};
+use crate::media_types::{build_match_query, LibraryItem, SearchFilters};
+use crate::player::{AudioOutput, PlaybackSource, Player};
+use crate::progress::{JobKind, ProgressEvent, ProgressSender, ProgressState};
 use crate::Context;
-use components::{LineInput, PopupInput};
+use components::{view_player, DownloadsPanel, ItemDetails, LineInput, PopupInput, ResultsTable};
 
-pub async fn tui_command(context: &mut Context) {
-	let mut model = Model::new(context);
+/// Longest to wait for input before checking on everything else
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-	// let's loop until quit is true
-	while !model.exit {
-		// Tick
-		if let Ok(messages) = model.app.tick(PollStrategy::Once) {
-			for msg in messages.into_iter() {
-				let mut msg = Some(msg);
-				while msg.is_some() {
-					msg = model.update(msg);
-				}
-			}
-		}
+/// Downloads picked in the TUI go ahead of those queued by `download`
+const DOWNLOAD_PRIORITY: i32 = 1;
 
-		// Redraw
-		if model.redraw {
-			model.view();
-			model.redraw = false;
-		}
-	}
+const DOWNLOAD_CONCURRENCY: usize = 2;
+
+/// How far the arrow keys move in the playing track
+const SEEK_STEP: Duration = Duration::from_secs(10);
+
+const VOLUME_STEP: f32 = 0.1;
+
+pub async fn tui_command(audio_output: AudioOutput, context: &mut Context) -> Result<(), Error> {
+	let mut terminal = ratatui::init();
 
-	// Terminate terminal
-	let _ = model.terminal.restore();
+	let result = Model::new(audio_output, context).run(&mut terminal).await;
+
+	ratatui::restore();
+
+	result
 }
 
-#[derive(PartialEq, Eq, Clone, Hash)]
-pub enum Id {
-	App,
+/// The components that can have the focus, in the order Tab cycles through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Id {
 	SearchInput,
-	TrackList,
-	Instructions,
+	ResultsTable,
+	ItemDetails,
+	DownloadsPanel,
+	/// Keeps the focus until it's closed
 	AddUrlPopup,
 }
 
-#[derive(PartialEq, Eq, Clone, Hash)]
+impl Id {
+	fn next(self) -> Self {
+		match self {
+			Self::SearchInput => Self::ResultsTable,
+			Self::ResultsTable => Self::ItemDetails,
+			Self::ItemDetails => Self::DownloadsPanel,
+			Self::DownloadsPanel => Self::SearchInput,
+			Self::AddUrlPopup => Self::AddUrlPopup,
+		}
+	}
+
+	fn prev(self) -> Self {
+		match self {
+			Self::SearchInput => Self::DownloadsPanel,
+			Self::ResultsTable => Self::SearchInput,
+			Self::ItemDetails => Self::ResultsTable,
+			Self::DownloadsPanel => Self::ItemDetails,
+			Self::AddUrlPopup => Self::AddUrlPopup,
+		}
+	}
+}
+
+#[derive(Debug)]
 enum Msg {
-	None,
 	Exit,
+	Focus(Id),
 	SearchUpdate(String),
+	SelectionChanged,
+	Download(LibraryItem),
+	PauseDownload(Url),
+	ResumeDownload(Url),
+	RetryDownload(Url),
+	CancelDownload(Url),
+	DownloadFinished(DownloadEvent),
 	AddUrl(String),
+	ScanFinished(String, Result<ScanReport, Error>),
+	Play(LibraryItem),
+	TogglePause,
+	SeekBack,
+	SeekForward,
+	VolumeDown,
+	VolumeUp,
+	TrackLoaded(Result<String, Error>),
 }
 
-struct Model<'a, T>
-where
-	T: TerminalAdapter,
-{
-	/// Application
-	app: Application<Id, Msg, NoUserEvent>,
+struct Model<'a> {
+	context: &'a mut Context,
 	/// Indicates that the application must quit
 	exit: bool,
 	/// Tells whether to redraw interface
 	redraw: bool,
-	/// Used to draw to terminal
-	terminal: TerminalBridge<T>,
-
+	focus: Id,
 	search_input: LineInput,
-	add_url_input: PopupInput,
-	context: &'a mut Context,
-	// table_state: TableState,
-	// download_manager: DownloadManager,
-	// download_progress: HashMap<Url, DownloadProgress>,
-	// progress_tx: mpsc::Sender<(Url, DownloadProgress)>,
-	// receiver_rx: mpsc::Receiver<(Url, DownloadProgress)>,
+	results_table: ResultsTable,
+	item_details: ItemDetails,
+	downloads_panel: DownloadsPanel,
+	add_url_popup: PopupInput,
+	/// Gets the focus back when the popup closes
+	focus_before_popup: Id,
+	/// URLs being scanned in the background
+	scans: Vec<String>,
+	scan_tx: UnboundedSender<(String, Result<ScanReport, Error>)>,
+	scan_rx: UnboundedReceiver<(String, Result<ScanReport, Error>)>,
+	/// Opens a connection of its own, so it's only created once something
+	/// is downloaded
+	download_manager: Option<DownloadManager>,
+	audio_output: AudioOutput,
+	/// Opened once something is played, so a missing sound card only
+	/// matters then
+	player: Option<Player>,
+	progress_rx: UnboundedReceiver<ProgressEvent>,
+	progress_state: ProgressState,
+	/// What happened last, like a finished download or why a search failed
+	status: Option<Line<'static>>,
 }
 
-impl<'a> Model<'a, CrosstermTerminalAdapter> {
-	fn new(context: &'a mut Context) -> Self {
-		let mut app: Application<Id, Msg, NoUserEvent> = Application::init(
-			EventListenerCfg::default().crossterm_input_listener(Duration::from_millis(10), 10),
-		);
-
-		let search_input = LineInput::default();
-		let add_url_input = PopupInput::default();
+impl<'a> Model<'a> {
+	fn new(audio_output: AudioOutput, context: &'a mut Context) -> Self {
+		// Progress is drawn by the TUI instead of as bars on stderr
+		let (progress, progress_rx) = ProgressSender::channel();
+		context.progress = progress;
 
-		assert!(app
-			.mount(Id::SearchInput, Box::new(LineInput::default()), vec![])
-			.is_ok());
-
-		// We need to give focus to input then
-		assert!(app.active(&Id::SearchInput).is_ok());
+		let (scan_tx, scan_rx) = mpsc::unbounded_channel();
 
 		Self {
-			app: Self::init_app(),
+			context,
 			exit: false,
 			redraw: true,
-			terminal: TerminalBridge::init_crossterm().expect("Cannot initialize terminal"),
-			search_input,
-			add_url_input,
-			context,
+			focus: Id::SearchInput,
+			search_input: LineInput::new("Search"),
+			results_table: ResultsTable::default(),
+			item_details: ItemDetails::default(),
+			downloads_panel: DownloadsPanel::default(),
+			add_url_popup: PopupInput::new("Add URL"),
+			focus_before_popup: Id::SearchInput,
+			scans: Vec::new(),
+			scan_tx,
+			scan_rx,
+			download_manager: None,
+			audio_output,
+			player: None,
+			progress_rx,
+			progress_state: ProgressState::default(),
+			status: None,
 		}
 	}
-}
 
-impl<'a, T> Model<'a, T>
-where
-	T: TerminalAdapter,
-{
-	pub fn view(&mut self) {
-		assert!(self
-			.terminal
-			.draw(|f| {
-				let area = f.area();
-
-				let chunks = Layout::default()
-					.direction(Direction::Vertical)
-					.margin(1)
-					.constraints(
-						[
-							Constraint::Length(3), // Search Input
-							Constraint::Length(3), // Item List
-							Constraint::Length(1), // Instructions
-						]
-						.as_ref(),
-					)
-					.split(area);
-
-				self.app.view(&Id::SearchInput, f, chunks[0]);
-				self.app.view(&Id::TrackList, f, chunks[1]);
-				self.app.view(&Id::Instructions, f, chunks[2]);
-
-				self.app.view(&Id::AddUrlPopup, f, area);
-			})
-			.is_ok());
+	async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+		let terminal_error =
+			|err: std::io::Error| Error::Filesystem(format!("Terminal error: {}", err));
+		// Read without blocking, so downloads, scans and playback running
+		// on the same worker thread keep going while waiting for keys
+		let mut events = EventStream::new();
+
+		while !self.exit {
+			if self.redraw {
+				terminal
+					.draw(|frame| self.view(frame))
+					.map_err(terminal_error)?;
+				self.redraw = false;
+			}
+
+			let mut msg = self.on_tick().await;
+
+			if msg.is_none() {
+				let event = tokio::select! {
+					event = events.next() => event,
+					_ = tokio::time::sleep(POLL_INTERVAL) => continue,
+				};
+
+				msg = match event.transpose().map_err(terminal_error)? {
+					Some(Event::Key(key)) if key.kind == KeyEventKind::Press => self.on_key(key),
+					Some(Event::Resize(_, _)) => {
+						self.redraw = true;
+						None
+					}
+					Some(_) => None,
+					// The terminal is gone
+					None => {
+						self.exit = true;
+						None
+					}
+				};
+			}
+
+			while let Some(next) = msg {
+				msg = self.update(next).await;
+			}
+		}
+
+		Ok(())
 	}
 
-	fn init_app() -> Application<Id, Msg, NoUserEvent> {
-		// Setup application
-		// NOTE: NoUserEvent is a shorthand to tell tui-realm we're not going to use any custom user event
-		// NOTE: the event listener is configured to use the default crossterm input listener and to raise a Tick event each second
-		// which we will use to update the clock
+	/// Takes in progress events and returns the next loaded track, finished
+	/// scan or download
+	async fn on_tick(&mut self) -> Option<Msg> {
+		while let Ok(event) = self.progress_rx.try_recv() {
+			self.progress_state.apply(&event);
+			self.redraw = true;
+		}
 
-		let mut app: Application<Id, Msg, NoUserEvent> = Application::init(
-			EventListenerCfg::default()
-				.crossterm_input_listener(Duration::from_millis(20), 3)
-				.poll_timeout(Duration::from_millis(10))
-				.tick_interval(Duration::from_secs(1)),
-		);
+		self
+			.downloads_panel
+			.record_downloaded(Instant::now(), self.progress_state.get_downloaded_bytes());
+
+		if let Some(player) = &mut self.player {
+			// Keeps the position moving
+			if !player.is_paused() && !player.is_finished() {
+				self.redraw = true;
+			}
+
+			if let Some(result) = player.poll_loaded() {
+				let loaded = result.map(|track| track.label.clone());
+				return Some(Msg::TrackLoaded(loaded));
+			}
+		}
 
-		// Mount components
-		assert!(app
-			.mount(
-				Id::SearchInput,
-				Box::new(
-					LineInput::default() //text("Waiting for a Msg...")
-					                     // .alignment(Alignment::Left)
-					                     // .background(Color::Reset)
-					                     // .foreground(Color::LightYellow)
-					                     // .modifiers(Modifier::BOLD),
-				),
-				Vec::default(),
-			)
-			.is_ok());
-
-		// Active letter counter
-		assert!(app.active(&Id::SearchInput).is_ok());
-		app
+		if let Ok((url, result)) = self.scan_rx.try_recv() {
+			return Some(Msg::ScanFinished(url, result));
+		}
+
+		let download_manager = self.download_manager.as_mut()?;
+		let event = download_manager.try_next_event(self.context).await?;
+
+		Some(Msg::DownloadFinished(event))
 	}
-}
 
-impl<'a, T> Update<Msg> for Model<'a, T>
-where
-	T: TerminalAdapter,
-{
-	fn update(&mut self, msg: Option<Msg>) -> Option<Msg> {
+	/// Keys that work everywhere come first, the rest go to the focused
+	/// component
+	fn on_key(&mut self, key: KeyEvent) -> Option<Msg> {
 		self.redraw = true;
-		match msg.unwrap_or(Msg::None) {
+
+		let is_control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+		match key.code {
+			KeyCode::Char('c') if is_control => return Some(Msg::Exit),
+			_ if self.focus == Id::AddUrlPopup => {
+				return match key.code {
+					KeyCode::Esc => Some(Msg::Focus(self.focus_before_popup)),
+					KeyCode::Enter => Some(Msg::AddUrl(self.add_url_popup.take_value())),
+					_ => {
+						self.add_url_popup.on(key);
+						None
+					}
+				}
+			}
+			KeyCode::Esc => return Some(Msg::Exit),
+			KeyCode::Char('a') if is_control => return Some(Msg::Focus(Id::AddUrlPopup)),
+			KeyCode::Tab => return Some(Msg::Focus(self.focus.next())),
+			KeyCode::BackTab => return Some(Msg::Focus(self.focus.prev())),
+			_ => {}
+		}
+
+		match self.focus {
+			Id::SearchInput => {
+				if self.search_input.on(key) {
+					return Some(Msg::SearchUpdate(self.search_input.get_value().to_string()));
+				}
+
+				match key.code {
+					KeyCode::Down | KeyCode::Enter => Some(Msg::Focus(Id::ResultsTable)),
+					_ => None,
+				}
+			}
+			Id::ResultsTable => {
+				if self.results_table.on(key) {
+					return Some(Msg::SelectionChanged);
+				}
+
+				match key.code {
+					KeyCode::Enter => Some(Msg::Focus(Id::ItemDetails)),
+					KeyCode::Char('d') => self
+						.results_table
+						.get_selected()
+						.cloned()
+						.map(Msg::Download),
+					KeyCode::Char(' ') => self.results_table.get_selected().cloned().map(Msg::Play),
+					_ => self.on_player_key(key),
+				}
+			}
+			Id::ItemDetails => {
+				if self.item_details.on(key) {
+					return None;
+				}
+
+				self.on_player_key(key)
+			}
+			Id::DownloadsPanel => {
+				if self.downloads_panel.on(key) {
+					return None;
+				}
+
+				let Some(download) = self.downloads_panel.get_selected() else {
+					return self.on_player_key(key);
+				};
+				let url = download.url.clone();
+
+				match (key.code, download.state) {
+					(KeyCode::Char('p'), DownloadState::Paused) => Some(Msg::ResumeDownload(url)),
+					(KeyCode::Char('p'), DownloadState::Running | DownloadState::Queued) => {
+						Some(Msg::PauseDownload(url))
+					}
+					(KeyCode::Char('r'), DownloadState::Failed) => Some(Msg::RetryDownload(url)),
+					(KeyCode::Char('x') | KeyCode::Delete, _) => Some(Msg::CancelDownload(url)),
+					_ => self.on_player_key(key),
+				}
+			}
+			Id::AddUrlPopup => None,
+		}
+	}
+
+	/// Controls the player from anywhere but the text inputs
+	fn on_player_key(&self, key: KeyEvent) -> Option<Msg> {
+		match key.code {
+			KeyCode::Char(' ') => Some(Msg::TogglePause),
+			KeyCode::Left => Some(Msg::SeekBack),
+			KeyCode::Right => Some(Msg::SeekForward),
+			KeyCode::Char('-') => Some(Msg::VolumeDown),
+			KeyCode::Char('+' | '=') => Some(Msg::VolumeUp),
+			_ => None,
+		}
+	}
+
+	async fn update(&mut self, msg: Msg) -> Option<Msg> {
+		self.redraw = true;
+
+		match msg {
 			Msg::Exit => {
 				self.exit = true;
 				None
 			}
+			Msg::Focus(id) => {
+				if id == Id::AddUrlPopup && self.focus != Id::AddUrlPopup {
+					self.focus_before_popup = self.focus;
+				}
+				self.focus = id;
+				None
+			}
 			Msg::SearchUpdate(query) => {
 				log::debug!("Search update: {}", query);
-				let future = self.context.search(&query, None);
+
+				match self
+					.context
+					.search(&get_live_query(&query), &SearchFilters::default())
+					.await
+				{
+					Ok(items) => {
+						self.results_table.set_items(items);
+						Some(Msg::SelectionChanged)
+					}
+					Err(err) => {
+						self.set_error(err);
+						None
+					}
+				}
+			}
+			Msg::SelectionChanged => {
+				self.item_details.reset_scroll();
 				None
 			}
+			Msg::Download(item) => {
+				let label = item.get_label();
+
+				match self.get_download_manager() {
+					Ok(download_manager) => {
+						let result = download_manager.enqueue(item, DOWNLOAD_PRIORITY).await;
+						download_manager.start_queued();
+
+						match result {
+							Ok(()) => self.status = Some(Line::from(format!("Queued {}", label))),
+							Err(err) => self.set_error(err),
+						}
+					}
+					Err(err) => self.set_error(err),
+				}
+
+				self.refresh_downloads();
+				None
+			}
+			Msg::PauseDownload(url) => {
+				if let Some(download_manager) = &mut self.download_manager {
+					download_manager.pause(&url).await;
+				}
+
+				self.refresh_downloads();
+				None
+			}
+			Msg::ResumeDownload(url) => {
+				if let Some(download_manager) = &mut self.download_manager {
+					download_manager.resume(&url);
+				}
+
+				self.refresh_downloads();
+				None
+			}
+			Msg::RetryDownload(url) => {
+				if let Some(download_manager) = &mut self.download_manager {
+					download_manager.retry(&url);
+				}
+
+				self.refresh_downloads();
+				None
+			}
+			Msg::CancelDownload(url) => {
+				if let Some(download_manager) = &mut self.download_manager {
+					if let Err(err) = download_manager.abort(&url).await {
+						self.set_error(err);
+					}
+				}
+
+				self.refresh_downloads();
+				None
+			}
+			Msg::DownloadFinished(event) => {
+				self.status = Some(match event {
+					DownloadEvent::Completed { items, .. } => {
+						Line::from(format!("Downloaded {}", get_items_label(&items)))
+					}
+					DownloadEvent::Failed { items, error, .. } => {
+						Line::from(format!("Failed to download {}: {}", get_items_label(&items), error).red())
+					}
+				});
+
+				self.refresh_downloads();
+
+				// The results show whether an item is downloaded
+				Some(Msg::SearchUpdate(self.search_input.get_value().to_string()))
+			}
 			Msg::AddUrl(url) => {
-				self.context.add_url(url);
+				let url = url.trim().to_string();
+
+				if !url.is_empty() {
+					// Scans take a while and run on a connection of their own,
+					// so the interface keeps responding
+					let mut context = match self.context.try_clone() {
+						Ok(context) => context,
+						Err(err) => {
+							self.set_error(err);
+							return Some(Msg::Focus(self.focus_before_popup));
+						}
+					};
+					let scan_tx = self.scan_tx.clone();
+					self.scans.push(url.clone());
+
+					tokio::spawn(async move {
+						let result = context.add_url(&url).await;
+						let _ = scan_tx.send((url, result));
+					});
+				}
+
+				Some(Msg::Focus(self.focus_before_popup))
+			}
+			Msg::Play(item) => {
+				let LibraryItem::SoundgasmTrack(track) = &item else {
+					self.set_error(Error::InvalidInput(
+						"Only Soundgasm tracks can be played".to_string(),
+					));
+					return None;
+				};
+
+				let id = item.get_url();
+				let source = match PlaybackSource::from_track(track, &self.context.file_store) {
+					Ok(source) => source,
+					Err(err) => {
+						self.set_error(err);
+						return None;
+					}
+				};
+				let player = match self.get_player() {
+					Ok(player) => player,
+					Err(err) => {
+						self.set_error(err);
+						return None;
+					}
+				};
+
+				let is_loaded = player.get_track().is_some_and(|track| track.id == id);
+				let is_loading = player.get_loading().is_some_and(|track| track.id == id);
+				if is_loaded && !is_loading {
+					return Some(Msg::TogglePause);
+				}
+
+				if !is_loading {
+					player.load(id, item.get_label(), source);
+				}
+
 				None
 			}
-			Msg::None => None,
+			Msg::TogglePause => {
+				if let Some(player) = &mut self.player {
+					player.toggle_pause();
+				}
+
+				None
+			}
+			Msg::SeekBack | Msg::SeekForward => {
+				let player = self.player.as_mut()?;
+				let position = match msg {
+					Msg::SeekBack => player.get_position().saturating_sub(SEEK_STEP),
+					_ => player.get_position() + SEEK_STEP,
+				};
+
+				if let Err(err) = player.seek(position) {
+					self.set_error(err);
+				}
+
+				None
+			}
+			Msg::VolumeDown | Msg::VolumeUp => {
+				let player = self.player.as_mut()?;
+				let step = match msg {
+					Msg::VolumeDown => -VOLUME_STEP,
+					_ => VOLUME_STEP,
+				};
+
+				player.set_volume(player.get_volume() + step);
+
+				None
+			}
+			Msg::TrackLoaded(result) => {
+				match result {
+					Ok(label) => self.status = Some(Line::from(format!("Playing {}", label))),
+					Err(err) => self.set_error(err),
+				}
+
+				None
+			}
+			Msg::ScanFinished(url, result) => {
+				self.scans.retain(|scan| *scan != url);
+
+				let query = self.search_input.get_value().to_string();
+				let report = match result {
+					Ok(report) => report,
+					Err(err) => {
+						self.set_error(err);
+						return None;
+					}
+				};
+
+				self.status = Some(match &report.error {
+					Some(err) => Line::from(format!("{}: {}", report.message, err).red()),
+					None => Line::from(report.message.clone()),
+				});
+
+				if build_match_query(&query).is_some() {
+					return Some(Msg::SearchUpdate(query));
+				}
+
+				// Without a search, shows what was just scanned
+				let filters = SearchFilters {
+					author: Some(report.author),
+					..Default::default()
+				};
+				match self.context.search("", &filters).await {
+					Ok(items) => {
+						self.results_table.set_items(items);
+						Some(Msg::SelectionChanged)
+					}
+					Err(err) => {
+						self.set_error(err);
+						None
+					}
+				}
+			}
+		}
+	}
+
+	fn get_download_manager(&mut self) -> Result<&mut DownloadManager, Error> {
+		let download_manager = match self.download_manager.take() {
+			Some(download_manager) => download_manager,
+			None => DownloadManager::new(
+				self.context.file_store.clone(),
+				self.context.http.clone(),
+				self.context.progress.clone(),
+				DOWNLOAD_CONCURRENCY,
+			)?,
+		};
+
+		Ok(self.download_manager.insert(download_manager))
+	}
+
+	fn get_player(&mut self) -> Result<&mut Player, Error> {
+		let player = match self.player.take() {
+			Some(player) => player,
+			None => Player::new(self.audio_output, self.context.http.clone())?,
+		};
+
+		Ok(self.player.insert(player))
+	}
+
+	fn refresh_downloads(&mut self) {
+		if let Some(download_manager) = &self.download_manager {
+			self
+				.downloads_panel
+				.set_downloads(download_manager.get_downloads());
+		}
+	}
+
+	fn set_error(&mut self, err: Error) {
+		self.status = Some(Line::from(err.to_string().red()));
+	}
+
+	fn view(&mut self, frame: &mut Frame) {
+		let [search_area, body_area, downloads_area, player_area, footer_area] = Layout::vertical([
+			Constraint::Length(3),
+			Constraint::Fill(1),
+			Constraint::Length(8),
+			Constraint::Length(1),
+			Constraint::Length(1),
+		])
+		.areas(frame.area());
+
+		let [results_area, details_area] =
+			Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body_area);
+
+		self
+			.search_input
+			.view(frame, search_area, self.focus == Id::SearchInput);
+		self
+			.results_table
+			.view(frame, results_area, self.focus == Id::ResultsTable);
+		self.item_details.view(
+			frame,
+			details_area,
+			self.results_table.get_selected(),
+			self.focus == Id::ItemDetails,
+		);
+
+		self.downloads_panel.view(
+			frame,
+			downloads_area,
+			&self.progress_state,
+			self.focus == Id::DownloadsPanel,
+		);
+
+		view_player(frame, player_area, self.player.as_ref());
+
+		let [help_area, status_area] =
+			Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(footer_area);
+
+		let mut help = match self.focus {
+			Id::AddUrlPopup => vec![
+				" Scan ".into(),
+				"<Enter>".fg(Color::Blue),
+				" Close ".into(),
+				"<Esc>".fg(Color::Blue),
+			],
+			_ => vec![
+				" Quit ".into(),
+				"<Esc>".fg(Color::Blue),
+				" Switch focus ".into(),
+				"<Tab>".fg(Color::Blue),
+				" Add URL ".into(),
+				"<C-a>".fg(Color::Blue),
+			],
+		};
+		match self.focus {
+			Id::ResultsTable => help.extend([
+				" Download ".into(),
+				"<d>".fg(Color::Blue),
+				" Play ".into(),
+				"<Space>".fg(Color::Blue),
+			]),
+			Id::DownloadsPanel => help.extend([
+				" Pause/resume ".into(),
+				"<p>".fg(Color::Blue),
+				" Retry ".into(),
+				"<r>".fg(Color::Blue),
+				" Cancel ".into(),
+				"<x>".fg(Color::Blue),
+			]),
+			_ => {}
+		}
+		frame.render_widget(Line::from(help), help_area);
+
+		if let Some(status) = self.get_scan_status().or_else(|| self.status.clone()) {
+			frame.render_widget(status.right_aligned(), status_area);
+		}
+
+		if self.focus == Id::AddUrlPopup {
+			self.add_url_popup.view(frame, frame.area());
+		}
+	}
+
+	/// How far along the running scans are, if there are any
+	fn get_scan_status(&self) -> Option<Line<'static>> {
+		let first = self.scans.first()?;
+
+		// Only profile scans report progress
+		let progress = self
+			.progress_state
+			.jobs
+			.values()
+			.filter(|job| job.kind == JobKind::Scan)
+			.map(|job| match job.total {
+				Some(total) => format!("{} {}/{}", job.label, job.done, total),
+				None => job.label.clone(),
+			})
+			.collect::<Vec<_>>();
+
+		let status = match (progress.is_empty(), self.scans.len()) {
+			(false, _) => progress.join(", "),
+			(true, 1) => format!("Scanning {}", first),
+			(true, count) => format!("Scanning {} and {} more", first, count - 1),
+		};
+
+		Some(Line::from(status.fg(Color::Cyan)))
+	}
+}
+
+/// The first item waiting for a download, and how many more there are
+fn get_items_label(items: &[LibraryItem]) -> String {
+	match items {
+		[] => "nothing".to_string(),
+		[item] => item.get_label(),
+		[item, rest @ ..] => format!("{} and {} more", item.get_label(), rest.len()),
+	}
+}
+
+/// Matches the word still being typed as a prefix, so results show up
+/// before it is finished
+fn get_live_query(query: &str) -> String {
+	let is_in_phrase = query.matches('"').count() % 2 == 1;
+
+	match query.chars().next_back() {
+		Some(c) if c.is_alphanumeric() && !is_in_phrase => format!("{}*", query),
+		_ => query.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use diesel::RunQueryDsl;
+	use ratatui::{
+		backend::TestBackend,
+		crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+		Terminal,
+	};
+
+	use super::{get_live_query, Id, Model, Msg};
+	use crate::context::{test_context, ScanReport};
+	use crate::error::Error;
+	use crate::player::AudioOutput;
+
+	#[test]
+	fn test_live_query() {
+		assert_eq!(get_live_query("rainy ni"), "rainy ni*");
+		assert_eq!(get_live_query("rainy "), "rainy ");
+		assert_eq!(get_live_query("\"rainy ni"), "\"rainy ni");
+		assert_eq!(get_live_query("\"rainy night\""), "\"rainy night\"");
+	}
+
+	#[tokio::test]
+	async fn test_search_as_you_type() {
+		let mut context = test_context();
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, sound_id, file_extension, created_at, updated_at) \
+			VALUES ('sgdl-test', 'rain', 'Rainy night walk', 'Footsteps on wet gravel', 'abc', 'm4a', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		let mut model = Model::new(AudioOutput::Null, &mut context);
+
+		for c in "rai".chars() {
+			let mut msg = model.on_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+			while let Some(next) = msg {
+				msg = model.update(next).await;
+			}
 		}
+
+		assert_eq!(
+			model.results_table.get_selected().unwrap().get_title(),
+			"Rainy night walk"
+		);
+
+		let msg = model.on_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+		model.update(msg.unwrap()).await;
+		assert_eq!(model.focus, Id::ResultsTable);
+
+		let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+		terminal.draw(|frame| model.view(frame)).unwrap();
+
+		let screen = terminal
+			.backend()
+			.buffer()
+			.content()
+			.chunks(100)
+			.map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+			.collect::<Vec<_>>()
+			.join("\n");
+		assert!(screen.contains("Results (1)"));
+		assert!(screen.contains("Not downloaded"));
+		assert!(screen.contains("Footsteps on wet gravel"));
+	}
+
+	#[tokio::test]
+	async fn test_add_url_popup() {
+		let mut context = test_context();
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, sound_id, file_extension, created_at, updated_at) \
+			VALUES ('sgdl-test', 'rain', 'Rainy night walk', 'Footsteps on wet gravel', 'abc', 'm4a', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		let mut model = Model::new(AudioOutput::Null, &mut context);
+
+		let msg = model.on_key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+		model.update(msg.unwrap()).await;
+		assert_eq!(model.focus, Id::AddUrlPopup);
+
+		// Typing goes to the popup instead of the search
+		model.on_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+		assert_eq!(model.search_input.get_value(), "");
+
+		let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+		terminal.draw(|frame| model.view(frame)).unwrap();
+		let screen = terminal
+			.backend()
+			.buffer()
+			.content()
+			.iter()
+			.map(|cell| cell.symbol())
+			.collect::<String>();
+		assert!(screen.contains("Add URL"));
+
+		// Closing gives the focus back and doesn't exit
+		let msg = model.on_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+		model.update(msg.unwrap()).await;
+		assert_eq!(model.focus, Id::SearchInput);
+		assert!(!model.exit);
+
+		// Without a search, the scanned profile is listed once it's done
+		let url = "https://soundgasm.net/u/sgdl-test".to_string();
+		model.scans.push(url.clone());
+		let report = ScanReport {
+			message: "Added 1 tracks, 0 removed, 0 failed".to_string(),
+			author: "sgdl-test".to_string(),
+			error: None,
+		};
+		let mut msg = model
+			.update(Msg::ScanFinished(url.clone(), Ok(report)))
+			.await;
+		while let Some(next) = msg {
+			msg = model.update(next).await;
+		}
+
+		assert!(model.scans.is_empty());
+		assert_eq!(
+			model.results_table.get_selected().unwrap().get_title(),
+			"Rainy night walk"
+		);
+		assert_eq!(
+			model.status.as_ref().unwrap().to_string(),
+			"Added 1 tracks, 0 removed, 0 failed"
+		);
+
+		let error = Error::NotFound("Soundgasm track was removed".to_string());
+		model.update(Msg::ScanFinished(url, Err(error))).await;
+		assert!(model
+			.status
+			.as_ref()
+			.unwrap()
+			.to_string()
+			.contains("Soundgasm track was removed"));
+	}
+
+	#[tokio::test]
+	async fn test_play_streamed_track() {
+		use httpmock::prelude::*;
+
+		use crate::config::Config;
+		use crate::http_client::HttpClient;
+
+		let server = MockServer::start_async().await;
+		let sound = server.mock(|when, then| {
+			when.method(GET).path("/sounds/abc.m4a");
+			then.status(200).body(crate::player::silent_wav());
+		});
+
+		let mut context = test_context();
+		let mut config = Config::new();
+		config.base_urls.soundgasm_media = server.base_url();
+		context.http = HttpClient::new(&config).unwrap();
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, sound_id, file_extension, created_at, updated_at) \
+			VALUES ('sgdl-test', 'rain', 'Rainy night walk', 'Footsteps on wet gravel', 'abc', 'm4a', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		let mut model = Model::new(AudioOutput::Null, &mut context);
+		let mut msg = Some(Msg::SearchUpdate("rainy".to_string()));
+		while let Some(next) = msg {
+			msg = model.update(next).await;
+		}
+		model.update(Msg::Focus(Id::ResultsTable)).await;
+
+		// Not downloaded, so it's streamed
+		let msg = model.on_key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+		assert!(model.update(msg.unwrap()).await.is_none());
+
+		let loaded = loop {
+			if let Some(msg @ Msg::TrackLoaded(_)) = model.on_tick().await {
+				break msg;
+			}
+			tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+		};
+		model.update(loaded).await;
+		sound.assert();
+		assert_eq!(
+			model.status.as_ref().unwrap().to_string(),
+			"Playing sgdl-test/rain"
+		);
+
+		// Space on the playing track pauses it
+		for (code, modifiers) in [
+			(KeyCode::Char(' '), KeyModifiers::NONE),
+			(KeyCode::Char('-'), KeyModifiers::NONE),
+		] {
+			let mut msg = model.on_key(KeyEvent::new(code, modifiers));
+			while let Some(next) = msg {
+				msg = model.update(next).await;
+			}
+		}
+
+		let player = model.player.as_ref().unwrap();
+		assert!(player.is_paused());
+		assert_eq!(
+			player.get_track().unwrap().id,
+			"https://soundgasm.net/u/sgdl-test/rain"
+		);
+
+		let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+		terminal.draw(|frame| model.view(frame)).unwrap();
+		let screen = terminal
+			.backend()
+			.buffer()
+			.content()
+			.iter()
+			.map(|cell| cell.symbol())
+			.collect::<String>();
+		assert!(screen.contains("⏸ sgdl-test/rain"));
+		assert!(screen.contains("/0:01"));
+		assert!(screen.contains("vol  90%"));
 	}
 }
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::file_store::BlobStatus;
+use crate::media_sources::kemono::KemonoPostAttachment;
+use crate::media_sources::soundgasm::SoundgasmAudioTrack;
+use crate::media_types::LibraryItem;
+use crate::progress::JobKind;
+use crate::Context;
+
+#[derive(Serialize, Default)]
+struct VerifySummary {
+	checked: usize,
+	valid: usize,
+	missing: usize,
+	length_mismatch: usize,
+	hash_mismatch: usize,
+	reset: usize,
+	problems: Vec<VerifyProblem>,
+}
+
+#[derive(Serialize)]
+struct VerifyProblem {
+	source: String,
+	item: String,
+	path: PathBuf,
+	problem: &'static str,
+	expected: Option<String>,
+	actual: Option<String>,
+	reset: bool,
+}
+
+/// Checks every stored blob against its recorded length and hash. Returns a
+/// filesystem error if any problems were found.
+pub async fn verify_command(reset: bool, json: bool, context: &mut Context) -> Result<(), Error> {
+	let mut items = SoundgasmAudioTrack::find_downloaded(context)
+		.await
+		.into_iter()
+		.map(LibraryItem::SoundgasmTrack)
+		.collect::<Vec<_>>();
+
+	items.extend(
+		KemonoPostAttachment::find_downloaded(context)
+			.await
+			.into_iter()
+			.map(LibraryItem::KemonoAttachment),
+	);
+
+	let mut summary = VerifySummary::default();
+	let mut job = context
+		.progress
+		.start_job(JobKind::Batch, "Verifying", Some(items.len() as u64));
+	// Several items can share a blob, so only hash each file once
+	let mut checked_paths: HashMap<PathBuf, BlobStatus> = HashMap::new();
+
+	for mut item in items {
+		let Some(path) = item.get_stored_path() else {
+			continue;
+		};
+
+		let path = context.file_store.resolve_path(path);
+
+		let status = match checked_paths.get(&path) {
+			Some(status) => status.clone(),
+			None => {
+				let status = item
+					.verify_stored(&context.file_store, &context.progress)
+					.await;
+				checked_paths.insert(path.clone(), status.clone());
+				status
+			}
+		};
+
+		summary.checked += 1;
+		job.advance(1);
+
+		let (problem, expected, actual) = match status {
+			BlobStatus::Valid => {
+				summary.valid += 1;
+				continue;
+			}
+			BlobStatus::Missing => {
+				summary.missing += 1;
+				("missing", None, None)
+			}
+			BlobStatus::LengthMismatch { expected, actual } => {
+				summary.length_mismatch += 1;
+				(
+					"length_mismatch",
+					Some(expected.to_string()),
+					Some(actual.to_string()),
+				)
+			}
+			BlobStatus::HashMismatch { expected, actual } => {
+				summary.hash_mismatch += 1;
+				("hash_mismatch", Some(expected), Some(actual))
+			}
+		};
+
+		info!("{} {}: {}", problem, item.get_label(), path.display());
+
+		let was_reset = reset && reset_item(&mut item, &path, context).await;
+		if was_reset {
+			summary.reset += 1;
+		}
+
+		summary.problems.push(VerifyProblem {
+			source: item.get_source().as_ref().to_lowercase(),
+			item: item.get_label(),
+			path,
+			problem,
+			expected,
+			actual,
+			reset: was_reset,
+		});
+	}
+
+	job.finish();
+
+	if json {
+		match serde_json::to_string(&summary) {
+			Ok(output) => println!("{}", output),
+			Err(err) => error!("Unable to serialize verify summary: {}", err),
+		}
+	} else {
+		print_summary(&summary);
+	}
+
+	if summary.problems.is_empty() {
+		Ok(())
+	} else {
+		Err(Error::Filesystem(format!(
+			"{} of {} stored tracks and attachments failed verification",
+			summary.problems.len(),
+			summary.checked
+		)))
+	}
+}
+
+/// Removes a corrupt file and clears the item's hash so it is downloaded again.
+async fn reset_item(item: &mut LibraryItem, path: &Path, context: &mut Context) -> bool {
+	// Leaving a corrupt file in place would make the store treat the
+	// re-download as a duplicate of it and throw the good copy away
+	if path.is_file() {
+		if let Err(err) = tokio::fs::remove_file(path).await {
+			error!("Unable to remove corrupt file {}: {}", path.display(), err);
+			return false;
+		}
+	}
+
+	match item.clear_stored(context).await {
+		Ok(()) => true,
+		Err(err) => {
+			error!("Unable to reset {}: {}", item.get_label(), err);
+			false
+		}
+	}
+}
+
+fn print_summary(summary: &VerifySummary) {
+	for problem in &summary.problems {
+		let detail = match (&problem.expected, &problem.actual) {
+			(Some(expected), Some(actual)) => format!(" (expected {}, found {})", expected, actual),
+			_ => String::new(),
+		};
+
+		println!(
+			"{:<16} {}:{} {}{}{}",
+			problem.problem,
+			problem.source,
+			problem.item,
+			problem.path.display(),
+			detail,
+			if problem.reset { " [reset]" } else { "" }
+		);
+	}
+
+	println!(
+		"Checked {} stored tracks and attachments: {} valid, {} missing, {} length mismatches, {} hash mismatches, {} reset",
+		summary.checked,
+		summary.valid,
+		summary.missing,
+		summary.length_mismatch,
+		summary.hash_mismatch,
+		summary.reset
+	);
+}
@@ -5,19 +5,116 @@ use serde::{Deserialize, Serialize};
 use std::env::current_dir;
 use std::path::PathBuf;
 
-#[cfg(test)]
-mod test;
-#[cfg(test)]
-use lazy_static::lazy_static;
-#[cfg(test)]
-lazy_static! {
-	static ref SERVER_HOST: String = test::init_test_server();
-}
+use reqwest::Url;
+
+use crate::common::USER_AGENT;
+use crate::error::Error;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
 	version: u64,
 	pub data_path: PathBuf,
+	#[serde(default)]
+	pub http: HttpConfig,
+	#[serde(default)]
+	pub base_urls: BaseUrls,
+}
+
+/// Where requests for each provider's pages and media are sent, e.g. a
+/// mirror or a local mock server. Items are still identified by their
+/// canonical URLs, requests to those are redirected to these base URLs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BaseUrls {
+	pub soundgasm: String,
+	pub soundgasm_media: String,
+	pub kemono: String,
+	pub coomer: String,
+}
+
+impl Default for BaseUrls {
+	fn default() -> Self {
+		Self {
+			soundgasm: "https://soundgasm.net".to_string(),
+			soundgasm_media: "https://media.soundgasm.net".to_string(),
+			kemono: "https://kemono.su".to_string(),
+			coomer: "https://coomer.su".to_string(),
+		}
+	}
+}
+
+impl BaseUrls {
+	/// Returns the configured base URL for a canonical host
+	fn get_base_url(&self, host: &str) -> Option<&str> {
+		match host {
+			"soundgasm.net" | "www.soundgasm.net" => Some(&self.soundgasm),
+			"media.soundgasm.net" => Some(&self.soundgasm_media),
+			"kemono.su" => Some(&self.kemono),
+			"coomer.su" => Some(&self.coomer),
+			_ => None,
+		}
+	}
+
+	/// Moves a canonical URL onto its provider's configured base URL, keeping
+	/// the path and query. URLs of other hosts are returned as they are.
+	pub fn resolve(&self, url: &Url) -> Result<Url, Error> {
+		let Some(base_url) = url.host_str().and_then(|host| self.get_base_url(host)) else {
+			return Ok(url.clone());
+		};
+
+		let mut resolved = Url::parse(base_url)
+			.map_err(|err| Error::Config(format!("Invalid base URL {}: {}", base_url, err)))?;
+		resolved.set_path(&format!(
+			"{}{}",
+			resolved.path().trim_end_matches('/'),
+			url.path()
+		));
+		resolved.set_query(url.query());
+
+		Ok(resolved)
+	}
+}
+
+/// Request settings for each provider
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct HttpConfig {
+	pub soundgasm: ProviderHttpConfig,
+	pub kemono: ProviderHttpConfig,
+	pub patreon: ProviderHttpConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ProviderHttpConfig {
+	pub user_agent: String,
+	/// Least time between two requests to the same host
+	pub min_request_interval_ms: u64,
+	/// How long connecting or waiting for more data may take. Whole
+	/// downloads can take longer.
+	pub timeout_secs: u64,
+	/// Requests failing with 429, 5xx or a connection error are retried with
+	/// exponential backoff, starting at `initial_backoff_ms`
+	pub max_attempts: u32,
+	pub initial_backoff_ms: u64,
+	/// e.g. `socks5h://127.0.0.1:9050`
+	pub proxy: Option<String>,
+	/// Sent as the `Cookie` header, e.g. `session=abc; theme=dark`
+	pub cookies: Option<String>,
+}
+
+impl Default for ProviderHttpConfig {
+	fn default() -> Self {
+		Self {
+			user_agent: USER_AGENT.to_string(),
+			min_request_interval_ms: 250,
+			timeout_secs: 30,
+			max_attempts: 4,
+			initial_backoff_ms: 500,
+			proxy: None,
+			cookies: None,
+		}
+	}
 }
 
 impl Config {
@@ -25,6 +122,8 @@ impl Config {
 		Self {
 			version: 0,
 			data_path: Self::get_data_path(),
+			http: HttpConfig::default(),
+			base_urls: BaseUrls::default(),
 		}
 	}
 
@@ -50,15 +149,6 @@ impl Config {
 
 		data_path.clone()
 	}
-
-	#[cfg(not(test))]
-	pub fn get_server_url(&self) -> String {
-		format!("//{}", "soundgasm.net")
-	}
-	#[cfg(test)]
-	pub fn get_server_url(&self) -> String {
-		SERVER_HOST.clone()
-	}
 }
 
 impl std::default::Default for Config {
@@ -66,3 +156,30 @@ impl std::default::Default for Config {
 		Self::new()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use reqwest::Url;
+
+	use super::BaseUrls;
+
+	#[test]
+	fn test_resolve_moves_canonical_urls_to_base_url() {
+		let base_urls = BaseUrls {
+			soundgasm: "http://127.0.0.1:5268/mirror/".to_string(),
+			..Default::default()
+		};
+
+		let track = Url::parse("https://soundgasm.net/u/sgdl-test/rain?x=1").unwrap();
+		assert_eq!(
+			base_urls.resolve(&track).unwrap().as_str(),
+			"http://127.0.0.1:5268/mirror/u/sgdl-test/rain?x=1"
+		);
+
+		let sound = Url::parse("https://media.soundgasm.net/sounds/abc.m4a").unwrap();
+		assert_eq!(base_urls.resolve(&sound).unwrap(), sound);
+
+		let other = Url::parse("https://example.com/u/sgdl-test").unwrap();
+		assert_eq!(base_urls.resolve(&other).unwrap(), other);
+	}
+}
@@ -1,55 +1,495 @@
 use diesel::SqliteConnection;
+use log::info;
 
 use crate::{
 	config::Config,
+	error::Error,
+	establish_connection,
 	file_store::FileStore,
-	media_sources::soundgasm::SoundgasmAudioTrack,
-	media_types::{MediaItem, MediaType},
+	http_client::HttpClient,
+	media_sources::{
+		kemono::KemonoPostAttachment,
+		recognize_pointer_from_string,
+		soundgasm::{ProfilePointer, SoundgasmAudioTrack},
+		PointerType,
+	},
+	media_types::{build_match_query, LibraryItem, SearchFilters},
+	progress::ProgressSender,
 };
 
 pub struct Context {
 	pub config: Config,
 	pub conn: SqliteConnection,
 	pub file_store: FileStore,
+	pub http: HttpClient,
+	pub progress: ProgressSender,
 }
 
 impl Context {
+	/// A context with a connection of its own, for work that runs alongside
+	/// this one
+	pub fn try_clone(&self) -> Result<Context, Error> {
+		Ok(Self {
+			config: self.config.clone(),
+			conn: establish_connection(self.file_store.data_path.as_path())?,
+			file_store: self.file_store.clone(),
+			http: self.http.clone(),
+			progress: self.progress.clone(),
+		})
+	}
+
+	/// Searches the whole library, best match first. See `build_match_query`
+	/// for the query syntax. Without a query, everything that passes the
+	/// filters is listed, as long as there are any.
 	pub async fn search(
 		&mut self,
 		query: &str,
-		filter_media_type: Option<MediaType>,
-		// filter_provider_type: Option<ProviderType>,
-	) -> Vec<impl MediaItem> {
-		match filter_media_type {
-			Some(MediaType::AudioMp3) => SoundgasmAudioTrack::search(self, query).await,
-			_ => SoundgasmAudioTrack::search(self, query).await,
+		filters: &SearchFilters,
+	) -> Result<Vec<LibraryItem>, Error> {
+		let filters = filters.normalized();
+
+		let match_query = build_match_query(query);
+		if match_query.is_none() && !filters.is_narrowing() {
+			return Ok(Vec::new());
 		}
+
+		let mut results = SoundgasmAudioTrack::search_ranked(self, match_query.as_deref(), &filters)
+			.await?
+			.into_iter()
+			.map(|(track, score)| (LibraryItem::SoundgasmTrack(track), score))
+			.collect::<Vec<_>>();
+
+		results.extend(
+			KemonoPostAttachment::search_ranked(self, match_query.as_deref(), &filters)
+				.await?
+				.into_iter()
+				.map(|(attachment, score)| (LibraryItem::KemonoAttachment(attachment), score)),
+		);
+
+		// BM25 scores are negative, with the best matches lowest
+		results.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+		results.truncate(filters.get_limit().max(0) as usize);
+
+		Ok(results.into_iter().map(|(item, _)| item).collect())
 	}
 
-	pub fn add_url(&mut self, url: String) {
-		use crate::media_sources::{recognize_pointer_from_string, PointerType};
-
-		log::debug!("Adding URL: {}", url);
-
-		recognize_pointer_from_string(&url).map(|pointer| {
-			match pointer {
-				PointerType::SoundgasmTrack(track) => {
-					log::debug!("Adding Soundgasm track: {:?}", track);
-					// SoundgasmAudioTrack::add(self, track);
-				}
-				PointerType::SoundgasmProfile(profile) => {
-					log::debug!("Adding Soundgasm profile: {:?}", profile);
-					// SoundgasmAudioTrack::add_profile(self, profile);
-				}
-				PointerType::KemonoPost(post) => {
-					log::debug!("Adding Kemono post: {:?}", post);
-					// Handle Kemono post addition
-				}
-				PointerType::KemonoProfile(profile) => {
-					log::debug!("Adding Kemono profile: {:?}", profile);
-					// Handle Kemono profile addition
-				}
+	/// Scans a track, profile, Kemono post or creator into the library.
+	/// Fails if nothing could be scanned, a profile scan that only partly
+	/// succeeded returns its first error with the report.
+	pub async fn add_url(&mut self, url: &str) -> Result<ScanReport, Error> {
+		let pointer = recognize_pointer_from_string(url)
+			.ok_or_else(|| Error::InvalidInput(format!("Unrecognized media source for: {}", url)))?;
+
+		match pointer {
+			PointerType::SoundgasmTrack(track_pointer) => {
+				info!("Scanning Soundgasm track: {}", url);
+
+				let (metadata, sound_pointer) = match track_pointer.fetch_track_page(&self.http).await {
+					Ok(page) => page,
+					Err(Error::NotFound(_)) => {
+						info!("Soundgasm track was removed: {}", url);
+						track_pointer.mark_deleted(self).await?;
+						return Err(Error::NotFound(format!(
+							"Soundgasm track was removed: {}",
+							url
+						)));
+					}
+					Err(err) => return Err(err),
+				};
+
+				let track = SoundgasmAudioTrack::new(track_pointer, metadata, sound_pointer);
+				track.add_to_library(self).await?;
+				info!("Added Soundgasm track to library: {}", url);
+
+				// The rest of the profile comes along with the track
+				let profile_pointer = ProfilePointer::from(track.pointer);
+				let summary = profile_pointer.scan(self).await?;
+
+				Ok(ScanReport {
+					message: summary.to_string(),
+					author: profile_pointer.slug,
+					error: summary.get_error(),
+				})
+			}
+			PointerType::SoundgasmProfile(pointer) => {
+				info!("Scanning Soundgasm profile: {}", pointer.slug);
+
+				let summary = pointer.scan(self).await?;
+
+				Ok(ScanReport {
+					message: summary.to_string(),
+					author: pointer.slug,
+					error: summary.get_error(),
+				})
+			}
+			PointerType::KemonoPost(post) => {
+				info!("Scanning Kemono post: {}", post.to_url());
+
+				Ok(ScanReport {
+					message: post.scan(self).await?,
+					author: post.creator.creator_id,
+					error: None,
+				})
+			}
+			PointerType::KemonoProfile(profile) => {
+				info!("Scanning Kemono creator: {}", profile.to_url());
+
+				Ok(ScanReport {
+					message: profile.scan(self).await?,
+					author: profile.creator_id,
+					error: None,
+				})
 			}
-		});
+		}
+	}
+}
+
+/// What `Context::add_url` added to the library
+#[derive(Debug)]
+pub struct ScanReport {
+	/// Sums up what was added, removed and failed
+	pub message: String,
+	/// Soundgasm profile slug or Kemono creator id, for
+	/// `SearchFilters::author`
+	pub author: String,
+	/// The first failure of a scan that only partly succeeded
+	pub error: Option<Error>,
+}
+
+/// A context with an empty in-memory library
+#[cfg(test)]
+pub(crate) fn test_context() -> Context {
+	use diesel::Connection;
+	use diesel_migrations::MigrationHarness;
+
+	let mut conn = SqliteConnection::establish(":memory:").unwrap();
+	conn.run_pending_migrations(crate::MIGRATIONS).unwrap();
+
+	let config = Config::new();
+	let file_store = FileStore {
+		data_path: config.data_path.clone(),
+	};
+	let http = HttpClient::new(&config).unwrap();
+
+	Context {
+		config,
+		conn,
+		file_store,
+		http,
+		progress: ProgressSender::disabled(),
+	}
+}
+
+/// An empty data directory, unique to the test process, that is removed
+/// again when the test ends
+#[cfg(test)]
+pub(crate) struct TempDataPath(pub std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDataPath {
+	pub fn new(name: &str) -> Self {
+		let path = std::env::temp_dir().join(format!("sgdl-{}-{}", name, std::process::id()));
+		if path.exists() {
+			std::fs::remove_dir_all(&path).unwrap();
+		}
+
+		Self(path)
+	}
+}
+
+#[cfg(test)]
+impl Drop for TempDataPath {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use diesel::RunQueryDsl;
+
+	use super::{test_context, Context};
+	use crate::media_types::{MediaType, SearchFilters};
+
+	fn video_filters() -> SearchFilters {
+		SearchFilters {
+			media_type: Some(MediaType::VideoMp4),
+			..Default::default()
+		}
+	}
+
+	fn insert_track(context: &mut Context, track_slug: &str, title: &str, description: &str) {
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, sound_id, file_extension, created_at, updated_at) \
+			VALUES ('sgdl-test', ?, ?, ?, 'abc', 'm4a', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.bind::<diesel::sql_types::Text, _>(track_slug)
+		.bind::<diesel::sql_types::Text, _>(title)
+		.bind::<diesel::sql_types::Text, _>(description)
+		.execute(&mut context.conn)
+		.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_search_ranks_and_filters() {
+		let mut context = test_context();
+
+		insert_track(&mut context, "rain", "Rainy night walk", "Footsteps");
+		insert_track(
+			&mut context,
+			"cafe",
+			"Cafe ambience",
+			"A rainy night outside",
+		);
+		insert_track(&mut context, "mall", "Shopping mall", "Half open at night");
+
+		// Matches in the title rank above matches in the description
+		let titles = context
+			.search("rainy night", &SearchFilters::default())
+			.await
+			.unwrap()
+			.iter()
+			.map(|item| item.get_title())
+			.collect::<Vec<_>>();
+		assert_eq!(titles, vec!["Rainy night walk", "Cafe ambience"]);
+
+		// Every term has to match
+		assert!(context
+			.search("rainy mall", &SearchFilters::default())
+			.await
+			.unwrap()
+			.is_empty());
+
+		// Phrases and prefixes
+		assert_eq!(
+			context
+				.search("\"half open\"", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+		assert_eq!(
+			context
+				.search("shop*", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+		assert_eq!(
+			context
+				.search("\"outside night\"", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			0
+		);
+
+		// The index follows updates and deletes
+		diesel::sql_query("UPDATE soundgasm_tracks SET title = 'Quiet mall' WHERE track_slug = 'mall'")
+			.execute(&mut context.conn)
+			.unwrap();
+		assert_eq!(
+			context
+				.search("quiet", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+		assert!(context
+			.search("shopping", &SearchFilters::default())
+			.await
+			.unwrap()
+			.is_empty());
+
+		diesel::sql_query("DELETE FROM soundgasm_tracks WHERE track_slug = 'rain'")
+			.execute(&mut context.conn)
+			.unwrap();
+		assert_eq!(
+			context
+				.search("rainy", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+
+		// Injected FTS5 syntax is searched for as text
+		assert!(context
+			.search("title:cafe OR NEAR(", &SearchFilters::default())
+			.await
+			.unwrap()
+			.is_empty());
+
+		assert!(context
+			.search("cafe", &video_filters())
+			.await
+			.unwrap()
+			.is_empty());
+
+		// Tracks removed from soundgasm stay searchable
+		diesel::sql_query(
+			"UPDATE soundgasm_tracks SET deleted_at = '2026-10-18 00:00:00' WHERE track_slug = 'cafe'",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+		let deleted = SearchFilters {
+			is_deleted: Some(true),
+			..Default::default()
+		};
+		let titles = context
+			.search("", &deleted)
+			.await
+			.unwrap()
+			.iter()
+			.map(|item| item.get_title())
+			.collect::<Vec<_>>();
+		assert_eq!(titles, vec!["Cafe ambience"]);
+	}
+
+	#[tokio::test]
+	async fn test_search_follows_changes() {
+		let mut context = test_context();
+
+		insert_track(&mut context, "rain", "Rainy night walk", "Footsteps");
+		insert_track(&mut context, "mall", "Shopping mall", "Half open at night");
+
+		diesel::sql_query(
+			"UPDATE soundgasm_tracks SET title = 'Thunderstorm' WHERE track_slug = 'rain'",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+		diesel::sql_query("DELETE FROM soundgasm_tracks WHERE track_slug = 'mall'")
+			.execute(&mut context.conn)
+			.unwrap();
+
+		// The index follows renames and deletions
+		assert_eq!(
+			context
+				.search("rainy", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			0
+		);
+		assert_eq!(
+			context
+				.search("thunderstorm", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+		assert_eq!(
+			context
+				.search("mall", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			0
+		);
+	}
+
+	#[tokio::test]
+	async fn test_search_kemono_posts() {
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO kemono_posts VALUES ('coomer.su', 'onlyfans', 'creator', '1', 'Beach day', 'Photos from the beach', NULL, NULL, '2026-10-18 00:00:00', '2026-10-18 00:00:00', NULL)",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		diesel::sql_query(
+			"INSERT INTO kemono_attachments (service, creator_id, post_id, path, name, position, created_at, media_type) VALUES \
+			('onlyfans', 'creator', '1', '/aa/bb/one.jpg', 'one.jpg', 0, '2026-10-18 00:00:00', 'ImageJpg'), \
+			('onlyfans', 'creator', '1', '/aa/bb/two.mp4', 'two.mp4', 1, '2026-10-18 00:00:00', 'VideoMp4'), \
+			('onlyfans', 'creator', '1', '/aa/bb/three.zip', 'three.zip', 2, '2026-10-18 00:00:00', NULL)",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		assert_eq!(
+			context
+				.search("beach", &SearchFilters::default())
+				.await
+				.unwrap()
+				.len(),
+			2
+		);
+		assert_eq!(
+			context
+				.search("beach", &video_filters())
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+	}
+
+	#[tokio::test]
+	async fn test_search_by_tag() {
+		use crate::media_types::TaggedItem;
+
+		let mut context = test_context();
+
+		insert_track(&mut context, "rain", "[F4M] [Whisper] Rainy night", "");
+		insert_track(&mut context, "cafe", "[F4A] Cafe", "[whisper] rainy cafe");
+
+		for (track_slug, title, description) in [
+			("rain", "[F4M] [Whisper] Rainy night", ""),
+			("cafe", "[F4A] Cafe", "[whisper] rainy cafe"),
+		] {
+			let tags = crate::media_types::extract_tags(&format!("{}\n{}", title, description));
+			TaggedItem::soundgasm_track("sgdl-test", track_slug)
+				.set_extracted_tags(&mut context, &tags)
+				.await
+				.unwrap();
+		}
+
+		let whisper = SearchFilters {
+			tags: vec!["WHISPER".to_string()],
+			..Default::default()
+		};
+		assert_eq!(context.search("", &whisper).await.unwrap().len(), 2);
+		assert_eq!(context.search("cafe", &whisper).await.unwrap().len(), 1);
+
+		let both = SearchFilters {
+			tags: vec![
+				"whisper".to_string(),
+				"f4m".to_string(),
+				"Whisper".to_string(),
+			],
+			..Default::default()
+		};
+		let results = context.search("", &both).await.unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].get_title(), "[F4M] [Whisper] Rainy night");
+
+		// Manual tags survive re-extraction, extracted ones are replaced
+		let cafe = TaggedItem::soundgasm_track("sgdl-test", "cafe");
+		cafe
+			.add_tags(&mut context, &["favorite".to_string()])
+			.await
+			.unwrap();
+		cafe
+			.set_extracted_tags(&mut context, &["F4A".to_string()])
+			.await
+			.unwrap();
+		assert_eq!(
+			cafe.get_tags(&mut context).await.unwrap(),
+			vec!["F4A", "favorite"]
+		);
+
+		assert_eq!(
+			cafe
+				.remove_tags(&mut context, &["FAVORITE".to_string()])
+				.await
+				.unwrap(),
+			1
+		);
+		assert_eq!(cafe.get_tags(&mut context).await.unwrap(), vec!["F4A"]);
 	}
 }
@@ -0,0 +1,207 @@
+//! Runs whole commands against a mock soundgasm server, from scanning a
+//! profile to verifying the downloaded files, so regressions in the page
+//! scrapers or the downloader show up as failing tests.
+
+use std::path::Path;
+
+use diesel::prelude::*;
+use httpmock::prelude::*;
+use httpmock::Mock;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::commands::{download_command, scan_command, verify_command};
+use crate::config::Config;
+use crate::context::TempDataPath;
+use crate::error::Error;
+use crate::file_store::{format_content_hash, FileStore};
+use crate::http_client::HttpClient;
+use crate::media_sources::soundgasm::SoundgasmAudioTrackRow;
+use crate::progress::ProgressSender;
+use crate::{establish_connection, Context};
+
+const PROFILE_URL: &str = "https://soundgasm.net/u/sgdl-test";
+const TRACK_SLUG: &str = "shopping-mall-half-open-Netherlands-207-AM-161001_0998";
+const SOUND_ID: &str = "7358137704b4386f24c1b5dad8b44fbdb0cf7731";
+const SOUND_BYTES: &[u8] = include_bytes!(
+	"../test/fixtures/http/soundgasm/sounds/7358137704b4386f24c1b5dad8b44fbdb0cf7731.m4a"
+);
+
+/// A mock soundgasm serving the `sgdl-test` profile from the fixtures
+struct MockSoundgasm {
+	server: MockServer,
+}
+
+impl MockSoundgasm {
+	fn start() -> Self {
+		let this = Self {
+			server: MockServer::start(),
+		};
+
+		this.serve_page(
+			"/u/sgdl-test",
+			include_str!("../test/fixtures/http/soundgasm/profiles/sgdl-test/index.html"),
+		);
+		this.serve_page(
+			&format!("/u/sgdl-test/{}", TRACK_SLUG),
+			include_str!("../test/fixtures/http/soundgasm/profiles/sgdl-test/tracks/shopping-mall-half-open-Netherlands-207-AM-161001_0998.html"),
+		);
+
+		this
+	}
+
+	fn serve_page(&self, path: &str, html: impl AsRef<[u8]>) -> Mock<'_> {
+		self.server.mock(|when, then| {
+			when.method(GET).path(path);
+			then
+				.status(200)
+				.header("content-type", "text/html")
+				.body(html.as_ref());
+		})
+	}
+
+	/// Serves `bytes` from `start` on, the way a server that supports
+	/// `Range` requests answers `Range: bytes=<start>-`
+	fn serve_blob_range(&self, path: &str, bytes: &'static [u8], start: usize) -> Mock<'_> {
+		self.server.mock(|when, then| {
+			when
+				.method(GET)
+				.path(path)
+				.header("range", format!("bytes={}-", start));
+			then
+				.status(206)
+				.header(
+					"content-range",
+					format!("bytes {}-{}/{}", start, bytes.len() - 1, bytes.len()),
+				)
+				.body(&bytes[start..]);
+		})
+	}
+
+	/// A library in `data_path` that sends every soundgasm request here
+	async fn create_context(&self, data_path: &Path) -> Context {
+		let mut config = Config::new();
+		config.data_path = data_path.to_path_buf();
+		config.base_urls.soundgasm = self.server.base_url();
+		config.base_urls.soundgasm_media = self.server.base_url();
+		config.http.soundgasm.min_request_interval_ms = 0;
+
+		let http = HttpClient::new(&config).unwrap();
+
+		Context {
+			conn: establish_connection(data_path).unwrap(),
+			file_store: FileStore::new(data_path).await,
+			http,
+			config,
+			progress: ProgressSender::disabled(),
+		}
+	}
+}
+
+fn load_tracks(context: &mut Context) -> Vec<SoundgasmAudioTrackRow> {
+	use crate::schema::soundgasm_tracks::dsl::*;
+
+	soundgasm_tracks
+		.select(SoundgasmAudioTrackRow::as_select())
+		.load(&mut context.conn)
+		.unwrap()
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+	let mut hasher = Xxh3::new();
+	hasher.update(bytes);
+	format_content_hash(&hasher)
+}
+
+#[tokio::test]
+async fn test_scan_download_verify() {
+	let soundgasm = MockSoundgasm::start();
+	let sound_path = format!("/sounds/{}.m4a", SOUND_ID);
+	let sound = soundgasm.serve_blob_range(&sound_path, SOUND_BYTES, 0);
+
+	let data_path = TempDataPath::new("scan-download-verify");
+	let mut context = soundgasm.create_context(&data_path.0).await;
+
+	scan_command(PROFILE_URL.to_string(), &mut context)
+		.await
+		.unwrap();
+
+	let tracks = load_tracks(&mut context);
+	assert_eq!(tracks.len(), 1);
+	assert_eq!(tracks[0].track_slug, TRACK_SLUG);
+	assert_eq!(
+		tracks[0].title,
+		"shopping mall half open Netherlands 207 AM 161001_0998"
+	);
+	assert_eq!(tracks[0].sound_id.as_deref(), Some(SOUND_ID));
+	assert_eq!(tracks[0].file_extension.as_deref(), Some("m4a"));
+	assert!(tracks[0].content_hash.is_none());
+
+	download_command(None, 2, &mut context).await.unwrap();
+
+	let expected_hash = hash_bytes(SOUND_BYTES);
+	let tracks = load_tracks(&mut context);
+	assert_eq!(
+		tracks[0].content_hash.as_deref(),
+		Some(expected_hash.as_str())
+	);
+	assert_eq!(tracks[0].content_length, Some(SOUND_BYTES.len() as i64));
+	sound.assert_hits(1);
+
+	let blob_path = context
+		.file_store
+		.get_blob_path("soundgasm_audio", &expected_hash, "m4a");
+	assert_eq!(std::fs::read(&blob_path).unwrap(), SOUND_BYTES);
+
+	verify_command(false, false, &mut context).await.unwrap();
+
+	// A corrupted file is caught, reset and downloaded again
+	std::fs::write(&blob_path, vec![0; SOUND_BYTES.len()]).unwrap();
+	assert!(matches!(
+		verify_command(true, false, &mut context).await,
+		Err(Error::Filesystem(_))
+	));
+	assert!(load_tracks(&mut context)[0].content_hash.is_none());
+
+	download_command(None, 2, &mut context).await.unwrap();
+	sound.assert_hits(2);
+	verify_command(false, false, &mut context).await.unwrap();
+	assert_eq!(std::fs::read(&blob_path).unwrap(), SOUND_BYTES);
+}
+
+#[tokio::test]
+async fn test_scan_marks_removed_tracks() {
+	let soundgasm = MockSoundgasm::start();
+	let data_path = TempDataPath::new("scan-removed");
+	let mut context = soundgasm.create_context(&data_path.0).await;
+
+	scan_command(PROFILE_URL.to_string(), &mut context)
+		.await
+		.unwrap();
+	assert!(load_tracks(&mut context)[0].deleted_at.is_none());
+
+	// The profile lists another track instead, and the old track's page is gone
+	let changed_profile = MockSoundgasm {
+		server: MockServer::start(),
+	};
+	changed_profile.serve_page(
+		"/u/sgdl-test",
+		include_str!("../test/fixtures/http/soundgasm/profiles/sgdl-test/index.html")
+			.replace(TRACK_SLUG, "another-track"),
+	);
+	context = changed_profile.create_context(&data_path.0).await;
+
+	scan_command(PROFILE_URL.to_string(), &mut context)
+		.await
+		.unwrap();
+	assert!(load_tracks(&mut context)[0].deleted_at.is_some());
+
+	// Nothing is left to download from a removed track
+	download_command(None, 1, &mut context).await.unwrap();
+	assert!(load_tracks(&mut context)[0].content_hash.is_none());
+
+	// Scanning the removed track directly reports it as gone
+	let track_url = format!("{}/{}", PROFILE_URL, TRACK_SLUG);
+	let err = scan_command(track_url, &mut context).await.unwrap_err();
+	assert!(matches!(err, Error::NotFound(_)));
+	assert_eq!(err.exit_code(), 7);
+}
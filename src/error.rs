@@ -0,0 +1,108 @@
+use std::fmt::Display;
+
+use crate::media_sources::html::ParseError;
+
+/// What went wrong, sorted by what a caller can do about it. Each kind
+/// carries a message for the user and exits the CLI with its own code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// A request couldn't be sent or the server answered with an error, so
+	/// trying again later may help
+	Network(String),
+	/// A page or API response didn't look the way we expect, usually because
+	/// the provider changed its site
+	Parse(String),
+	/// The library database couldn't be opened, read or written
+	Database(String),
+	/// A file in the data directory couldn't be read, written or moved
+	Filesystem(String),
+	/// The track, post or profile is gone from the provider
+	NotFound(String),
+	/// An argument, like a URL, wasn't understood
+	InvalidInput(String),
+	/// The config file has an invalid setting
+	Config(String),
+}
+
+impl Error {
+	/// Distinct for every kind, so scripts can tell them apart. 1 is left
+	/// for failures outside the library, 2 is used by clap for bad usage.
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			Self::InvalidInput(_) => 2,
+			Self::Network(_) => 3,
+			Self::Parse(_) => 4,
+			Self::Database(_) => 5,
+			Self::Filesystem(_) => 6,
+			Self::NotFound(_) => 7,
+			Self::Config(_) => 8,
+		}
+	}
+
+	pub fn get_message(&self) -> &str {
+		match self {
+			Self::Network(message)
+			| Self::Parse(message)
+			| Self::Database(message)
+			| Self::Filesystem(message)
+			| Self::NotFound(message)
+			| Self::InvalidInput(message)
+			| Self::Config(message) => message,
+		}
+	}
+
+	/// The same kind of error with a different message, for summing up a
+	/// batch by its first failure
+	pub fn with_message(&self, message: String) -> Self {
+		match self {
+			Self::Network(_) => Self::Network(message),
+			Self::Parse(_) => Self::Parse(message),
+			Self::Database(_) => Self::Database(message),
+			Self::Filesystem(_) => Self::Filesystem(message),
+			Self::NotFound(_) => Self::NotFound(message),
+			Self::InvalidInput(_) => Self::InvalidInput(message),
+			Self::Config(_) => Self::Config(message),
+		}
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.get_message())
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParseError> for Error {
+	fn from(err: ParseError) -> Self {
+		Self::Parse(err.to_string())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Error;
+
+	#[test]
+	fn test_exit_codes_are_distinct() {
+		let errors = [
+			Error::Network(String::new()),
+			Error::Parse(String::new()),
+			Error::Database(String::new()),
+			Error::Filesystem(String::new()),
+			Error::NotFound(String::new()),
+			Error::InvalidInput(String::new()),
+			Error::Config(String::new()),
+		];
+
+		let mut codes = errors.iter().map(Error::exit_code).collect::<Vec<_>>();
+		codes.sort();
+		codes.dedup();
+		assert_eq!(codes.len(), errors.len());
+		assert!(!codes.contains(&0) && !codes.contains(&1));
+
+		let summary = Error::Network("timed out".to_string()).with_message("2 failed".to_string());
+		assert_eq!(summary, Error::Network("2 failed".to_string()));
+	}
+}
@@ -1,28 +1,108 @@
 use std::collections::VecDeque;
 
-use crate::media_sources::soundgasm::SoundgasmAudioTrack;
+use reqwest::Url;
 
-struct DownloadQueue {
-	queue: VecDeque<SoundgasmAudioTrack>,
+use crate::error::Error;
+use crate::media_sources::ProviderType;
+use crate::media_types::{LibraryItem, MediaBlobPointer, MediaItem};
+
+/// Where a file is downloaded from and where it ends up in the store
+#[derive(Debug, Clone)]
+pub struct BlobTarget {
+	pub provider: ProviderType,
+	pub url: Url,
+	pub namespace: String,
+	pub extension: String,
 }
 
-impl DownloadQueue {
-	pub fn new() -> Self {
-		Self {
-			queue: VecDeque::with_capacity(128),
+impl BlobTarget {
+	pub fn new(item: &LibraryItem) -> Result<Self, Error> {
+		match item {
+			LibraryItem::SoundgasmTrack(track) => Self::from_media_item(track),
+			LibraryItem::KemonoAttachment(attachment) => Self::from_media_item(attachment),
 		}
 	}
 
-	pub fn enqueue(&mut self, item: SoundgasmAudioTrack) {
-		self.queue.push_back(item);
+	fn from_media_item(item: &impl MediaItem) -> Result<Self, Error> {
+		let blob_pointer = item.get_blob_pointer();
+
+		Ok(Self {
+			provider: item.get_source(),
+			url: blob_pointer.get_download_url()?,
+			namespace: blob_pointer.get_namespace(),
+			extension: blob_pointer.get_extension(),
+		})
+	}
+
+	/// The file name in the URL, for progress output
+	pub fn get_label(&self) -> String {
+		self
+			.url
+			.path_segments()
+			.and_then(|mut segments| segments.next_back())
+			.unwrap_or(self.url.as_str())
+			.to_string()
 	}
+}
 
-	pub fn dequeue(&mut self) -> Option<SoundgasmAudioTrack> {
+/// A file waiting to be downloaded. The same file can be attached to several
+/// posts, so one download may complete more than one item.
+#[derive(Debug, Clone)]
+pub struct QueuedDownload {
+	/// The download's row in `file_downloads`
+	pub job_id: i32,
+	pub priority: i32,
+	pub target: BlobTarget,
+	pub items: Vec<LibraryItem>,
+}
+
+/// Downloads by highest priority and then in the order they were first
+/// requested, one entry per URL
+#[derive(Debug, Default)]
+pub struct DownloadQueue {
+	queue: VecDeque<QueuedDownload>,
+}
+
+impl DownloadQueue {
+	/// Puts the download behind every other download of the same or higher
+	/// priority. Job ids grow with every new job, so a job from an earlier
+	/// run goes ahead of new ones with the same priority.
+	pub fn insert(&mut self, download: QueuedDownload) {
+		let index = self
+			.queue
+			.iter()
+			.position(|queued| (queued.priority, -queued.job_id) < (download.priority, -download.job_id))
+			.unwrap_or(self.queue.len());
+
+		self.queue.insert(index, download);
+	}
+
+	pub fn push_front(&mut self, download: QueuedDownload) {
+		self.queue.push_front(download);
+	}
+
+	pub fn pop_front(&mut self) -> Option<QueuedDownload> {
 		self.queue.pop_front()
 	}
 
-	pub fn is_empty(&self) -> bool {
-		self.queue.is_empty()
+	pub fn get_mut(&mut self, url: &Url) -> Option<&mut QueuedDownload> {
+		self
+			.queue
+			.iter_mut()
+			.find(|download| &download.target.url == url)
+	}
+
+	pub fn remove(&mut self, url: &Url) -> Option<QueuedDownload> {
+		let index = self
+			.queue
+			.iter()
+			.position(|download| &download.target.url == url)?;
+
+		self.queue.remove(index)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &QueuedDownload> {
+		self.queue.iter()
 	}
 
 	pub fn len(&self) -> usize {
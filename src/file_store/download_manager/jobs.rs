@@ -0,0 +1,181 @@
+use diesel::prelude::*;
+
+use super::segments::{merge_segments, FileDownloadRow};
+
+/// Where a download job is, as stored in `file_downloads.state`. Ordered the
+/// way the queue is listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DownloadState {
+	Running,
+	Queued,
+	Paused,
+	Failed,
+	Done,
+}
+
+impl DownloadState {
+	pub fn from_str(state: &str) -> Option<Self> {
+		match state {
+			"running" => Some(Self::Running),
+			"queued" => Some(Self::Queued),
+			"paused" => Some(Self::Paused),
+			"failed" => Some(Self::Failed),
+			"done" => Some(Self::Done),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			Self::Running => "running",
+			Self::Queued => "queued",
+			Self::Paused => "paused",
+			Self::Failed => "failed",
+			Self::Done => "done",
+		}
+	}
+}
+
+impl FileDownloadRow {
+	/// Every job, highest priority first and then in the order they were
+	/// first queued
+	pub fn load_jobs(conn: &mut SqliteConnection) -> QueryResult<Vec<Self>> {
+		use crate::schema::file_downloads::dsl::*;
+
+		file_downloads
+			.order((priority.desc(), id.asc()))
+			.select(Self::as_select())
+			.load(conn)
+	}
+
+	/// Jobs left running belong to a process that was killed, so they go
+	/// back into the queue. Returns how many there were.
+	pub fn requeue_interrupted(conn: &mut SqliteConnection) -> QueryResult<usize> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::update(file_downloads.filter(state.eq(DownloadState::Running.as_str())))
+			.set(state.eq(DownloadState::Queued.as_str()))
+			.execute(conn)
+	}
+
+	/// Unknown states are treated as queued, so the job runs again
+	pub fn get_state(&self) -> DownloadState {
+		DownloadState::from_str(&self.state).unwrap_or(DownloadState::Queued)
+	}
+
+	/// Bytes of the partial file that are already on disk
+	pub fn get_downloaded_bytes(&self, conn: &mut SqliteConnection) -> QueryResult<u64> {
+		let segments = merge_segments(self.load_segments(conn)?);
+
+		Ok(
+			segments
+				.iter()
+				.map(|segment| segment.end - segment.start)
+				.sum(),
+		)
+	}
+
+	/// Puts the job in the queue, keeping the higher of its earlier and the
+	/// new priority. A job that was done before, like one reset by `verify`,
+	/// starts over as if it was new.
+	pub fn mark_queued(
+		&mut self,
+		conn: &mut SqliteConnection,
+		new_priority: i32,
+		new_label: &str,
+	) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		if self.get_state() == DownloadState::Done {
+			diesel::update(file_downloads.find(self.id))
+				.set((
+					priority.eq(0),
+					attempts.eq(0),
+					content_length.eq(None::<i64>),
+				))
+				.execute(conn)?;
+
+			self.priority = 0;
+			self.attempts = 0;
+			self.content_length = None;
+		}
+
+		let queued_priority = self.priority.max(new_priority);
+
+		diesel::update(file_downloads.find(self.id))
+			.set((
+				state.eq(DownloadState::Queued.as_str()),
+				priority.eq(queued_priority),
+				label.eq(new_label),
+			))
+			.execute(conn)?;
+
+		self.state = DownloadState::Queued.as_str().to_string();
+		self.priority = queued_priority;
+		self.label = Some(new_label.to_string());
+
+		Ok(())
+	}
+
+	pub fn set_state(
+		conn: &mut SqliteConnection,
+		job_id: i32,
+		new_state: DownloadState,
+	) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::update(file_downloads.find(job_id))
+			.set(state.eq(new_state.as_str()))
+			.execute(conn)?;
+
+		Ok(())
+	}
+
+	pub fn mark_running(conn: &mut SqliteConnection, job_id: i32) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::update(file_downloads.find(job_id))
+			.set((
+				state.eq(DownloadState::Running.as_str()),
+				attempts.eq(attempts + 1),
+			))
+			.execute(conn)?;
+
+		Ok(())
+	}
+
+	pub fn mark_failed(conn: &mut SqliteConnection, job_id: i32, error: &str) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::update(file_downloads.find(job_id))
+			.set((
+				state.eq(DownloadState::Failed.as_str()),
+				last_error.eq(error),
+			))
+			.execute(conn)?;
+
+		Ok(())
+	}
+
+	/// The file is stored, so only the job's history is kept
+	pub fn mark_done(conn: &mut SqliteConnection, job_id: i32) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		conn.transaction(|conn| {
+			diesel::delete(
+				crate::schema::downloaded_segments::table
+					.filter(crate::schema::downloaded_segments::download_id.eq(job_id)),
+			)
+			.execute(conn)?;
+
+			diesel::update(file_downloads.find(job_id))
+				.set((
+					state.eq(DownloadState::Done.as_str()),
+					last_error.eq(None::<String>),
+				))
+				.execute(conn)?;
+
+			Ok(())
+		})
+	}
+}
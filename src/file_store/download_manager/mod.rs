@@ -1,159 +1,1361 @@
-use std::{collections::HashMap, io::SeekFrom, path::PathBuf};
+mod download_queue;
+mod jobs;
+mod segments;
 
+use std::{collections::HashMap, io::SeekFrom, path::Path};
+
+use diesel::SqliteConnection;
 use futures_util::StreamExt;
 use http_content_range::ContentRange;
-use log::error;
-use reqwest::{Response, Url};
+use log::{debug, error};
+use reqwest::{
+	header::{HeaderMap, HeaderValue, CONTENT_RANGE, RANGE},
+	Method, Response, StatusCode, Url,
+};
 use tokio::{
-	fs::File,
+	fs::{File, OpenOptions},
 	io::{AsyncSeekExt, AsyncWriteExt},
-	sync::mpsc::{self, Receiver, Sender},
+	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
 	task::JoinHandle,
 };
+use xxhash_rust::xxh3::Xxh3;
 
-use crate::media_types::{MediaBlobPointer, MediaItem};
+use crate::{
+	error::Error,
+	establish_connection,
+	file_store::{format_content_hash, FileStore, StoredBlob},
+	http_client::{check_status, HttpClient},
+	media_sources::ProviderType,
+	media_types::LibraryItem,
+	progress::{JobId, JobKind, ProgressJob, ProgressSender},
+	Context,
+};
+use download_queue::{BlobTarget, DownloadQueue, QueuedDownload};
+pub use jobs::DownloadState;
+pub use segments::FileDownloadRow;
+use segments::{missing_spans, ByteSpan};
 
-pub struct DownloadManager {
-	progress_tx: Sender<(Url, DownloadProgress)>,
-	progress_rx: Receiver<(Url, DownloadProgress)>,
-	downloads: HashMap<Url, JoinHandle<()>>,
-	progress: HashMap<Url, DownloadProgress>,
+/// How many bytes to write before persisting the current segment's progress.
+const SEGMENT_FLUSH_INTERVAL: u64 = 1024 * 1024;
+
+/// What became of a download. The library has already been updated by the
+/// time it is returned.
+#[derive(Debug)]
+pub enum DownloadEvent {
+	/// The file is stored and every item waiting for it was saved with it
+	Completed {
+		url: Url,
+		items: Vec<LibraryItem>,
+		blob: StoredBlob,
+	},
+	Failed {
+		url: Url,
+		items: Vec<LibraryItem>,
+		error: Error,
+	},
 }
 
-impl Default for DownloadManager {
-	fn default() -> Self {
-		Self::new(32)
-	}
+/// A download as it is shown in a list, in the order of
+/// `DownloadManager::get_downloads`
+#[derive(Debug, Clone)]
+pub struct DownloadInfo {
+	pub url: Url,
+	/// The first item waiting for the file
+	pub label: String,
+	pub state: DownloadState,
+	/// The progress job of a running download
+	pub job: Option<JobId>,
+	/// Why a failed download failed
+	pub error: Option<Error>,
+}
+
+struct RunningDownload {
+	download: QueuedDownload,
+	/// Tells the results of a download apart from those of an earlier run
+	/// that was paused while it was finishing
+	run: u64,
+	job: JobId,
+	handle: JoinHandle<()>,
+}
+
+struct FailedDownload {
+	download: QueuedDownload,
+	error: Error,
+}
+
+/// Downloads queued items, at most `concurrency` at a time. Downloads can be
+/// paused, resumed and aborted by URL, and a paused download continues
+/// where it stopped.
+///
+/// Every download is also a job in `file_downloads`, so a later run
+/// continues the queue in the same order and keeps paused downloads paused.
+pub struct DownloadManager {
+	conn: SqliteConnection,
+	file_store: FileStore,
+	http: HttpClient,
+	progress: ProgressSender,
+	concurrency: usize,
+	queue: DownloadQueue,
+	paused: HashMap<Url, QueuedDownload>,
+	running: HashMap<Url, RunningDownload>,
+	/// Failed downloads in the order they failed, until they are retried
+	failed: Vec<FailedDownload>,
+	next_run: u64,
+	completed_tx: UnboundedSender<(Url, u64, Result<StoredBlob, Error>)>,
+	completed_rx: UnboundedReceiver<(Url, u64, Result<StoredBlob, Error>)>,
 }
 
 impl DownloadManager {
-	pub fn new(initial_concurrency: usize) -> DownloadManager {
-		let (progress_tx, progress_rx) = mpsc::channel::<(Url, DownloadProgress)>(32);
+	pub fn new(
+		file_store: FileStore,
+		http: HttpClient,
+		progress: ProgressSender,
+		concurrency: usize,
+	) -> Result<DownloadManager, Error> {
+		let mut conn = establish_connection(&file_store.data_path)?;
 
-		DownloadManager {
-			progress_tx,
-			progress_rx,
-			downloads: HashMap::with_capacity(initial_concurrency),
-			progress: HashMap::with_capacity(initial_concurrency),
+		let interrupted = FileDownloadRow::requeue_interrupted(&mut conn)
+			.map_err(|err| Error::Database(format!("Unable to requeue downloads: {}", err)))?;
+		if interrupted > 0 {
+			debug!("Requeued {} downloads of an earlier run", interrupted);
 		}
+
+		let (completed_tx, completed_rx) = mpsc::unbounded_channel();
+
+		Ok(DownloadManager {
+			conn,
+			file_store,
+			http,
+			progress,
+			concurrency: concurrency.max(1),
+			queue: DownloadQueue::default(),
+			paused: HashMap::new(),
+			running: HashMap::with_capacity(concurrency),
+			failed: Vec::new(),
+			next_run: 0,
+			completed_tx,
+			completed_rx,
+		})
 	}
 
-	pub fn get_progress(&self) -> &HashMap<Url, DownloadProgress> {
-		&self.progress
+	/// Downloads that are queued, running or paused
+	pub fn get_pending_count(&self) -> usize {
+		self.queue.len() + self.running.len() + self.paused.len()
 	}
 
-	pub async fn start_download(
-		&mut self,
-		item: impl MediaItem,
-		tx: Sender<(Url, DownloadProgress)>,
-	) {
-		let url = item.get_blob_pointer().get_download_url();
-		// Ignore the request if we're already downloading the file
-		if self.progress.contains_key(&url) {
-			return;
-		}
-
-		let user_agent = item.get_source().get_user_agent();
-
-		let handle = tokio::spawn(Self::run_download(
-			user_agent,
-			url.clone(),
-			item.get_blob_pointer().get_path(),
-			self.progress_tx.clone(),
-		));
-
-		self.progress.insert(
-			url.clone(),
-			DownloadProgress {
-				bytes_downloaded: 0,
-				total_size: None,
+	/// Downloads that wait to be resumed, which `next_event` never finishes
+	pub fn get_paused_count(&self) -> usize {
+		self.paused.len()
+	}
+
+	/// Every download that isn't done, running ones first, then the queue
+	/// in order, then paused and failed ones
+	pub fn get_downloads(&self) -> Vec<DownloadInfo> {
+		let get_info = |download: &QueuedDownload, state: DownloadState| DownloadInfo {
+			url: download.target.url.clone(),
+			label: download
+				.items
+				.first()
+				.map(LibraryItem::get_label)
+				.unwrap_or_else(|| download.target.get_label()),
+			state,
+			job: None,
+			error: None,
+		};
+
+		let mut running = self.running.values().collect::<Vec<_>>();
+		running.sort_by_key(|running| running.download.job_id);
+		let mut paused = self.paused.values().collect::<Vec<_>>();
+		paused.sort_by_key(|download| download.job_id);
+
+		running
+			.into_iter()
+			.map(|running| DownloadInfo {
+				job: Some(running.job),
+				..get_info(&running.download, DownloadState::Running)
+			})
+			.chain(
+				self
+					.queue
+					.iter()
+					.map(|download| get_info(download, DownloadState::Queued)),
+			)
+			.chain(
+				paused
+					.into_iter()
+					.map(|download| get_info(download, DownloadState::Paused)),
+			)
+			.chain(self.failed.iter().map(|failed| DownloadInfo {
+				error: Some(failed.error.clone()),
+				..get_info(&failed.download, DownloadState::Failed)
+			}))
+			.collect()
+	}
+
+	/// Queues the item's file, ahead of downloads with a lower priority. If
+	/// it is already being downloaded for another item, this item is saved
+	/// with it too. A download paused in an earlier run stays paused.
+	///
+	/// Nothing starts until `start_queued` or `next_event` is called, so a
+	/// whole batch can be queued in order first.
+	pub async fn enqueue(&mut self, item: LibraryItem, priority: i32) -> Result<(), Error> {
+		let target = BlobTarget::new(&item)?;
+
+		let waiting = match self.running.get_mut(&target.url) {
+			Some(running) => Some(&mut running.download),
+			None => match self.paused.get_mut(&target.url) {
+				Some(paused) => Some(paused),
+				None => self.queue.get_mut(&target.url),
 			},
-		);
+		};
+
+		if let Some(download) = waiting {
+			download.items.push(item);
+			return Ok(());
+		}
+
+		if let Some(index) = self.find_failed(&target.url) {
+			let failed = self.failed.remove(index);
+			let mut download = failed.download;
+			if !download
+				.items
+				.iter()
+				.any(|waiting| waiting.get_label() == item.get_label())
+			{
+				download.items.push(item);
+			}
+			self.requeue(download);
+			return Ok(());
+		}
+
+		let db_error =
+			|err: diesel::result::Error| Error::Database(format!("Unable to queue download: {}", err));
+
+		let temp_path = self.file_store.get_temp_path(&target.url).await?;
+		let mut record = FileDownloadRow::find_or_create(
+			&mut self.conn,
+			target.url.as_str(),
+			&temp_path.to_string_lossy(),
+		)
+		.map_err(db_error)?;
+
+		if record.get_state() == DownloadState::Paused {
+			self.paused.insert(
+				target.url.clone(),
+				QueuedDownload {
+					job_id: record.id,
+					priority: record.priority,
+					target,
+					items: vec![item],
+				},
+			);
+			return Ok(());
+		}
+
+		record
+			.mark_queued(&mut self.conn, priority, &item.get_label())
+			.map_err(db_error)?;
+		self.queue.insert(QueuedDownload {
+			job_id: record.id,
+			priority: record.priority,
+			target,
+			items: vec![item],
+		});
+
+		Ok(())
 	}
 
-	pub async fn abort_download(&mut self) {}
+	/// Stops a queued or running download until it is resumed. Returns false
+	/// if there is no such download.
+	pub async fn pause(&mut self, url: &Url) -> bool {
+		let download = match self.running.remove(url) {
+			Some(running) => {
+				running.handle.abort();
+				let _ = running.handle.await;
+				running.download
+			}
+			None => match self.queue.remove(url) {
+				Some(download) => download,
+				None => return false,
+			},
+		};
+
+		debug!("Paused download of {}", url);
+		self.record_state(&download, DownloadState::Paused);
+		self.paused.insert(url.clone(), download);
+		self.start_queued();
 
-	async fn run_download(
-		user_agent: String,
+		true
+	}
+
+	/// Queues a failed download again. It continues where the failed
+	/// attempt stopped.
+	pub fn retry(&mut self, url: &Url) -> bool {
+		let Some(index) = self.find_failed(url) else {
+			return false;
+		};
+
+		debug!("Retrying download of {}", url);
+		let failed = self.failed.remove(index);
+		self.requeue(failed.download);
+		self.start_queued();
+
+		true
+	}
+
+	/// Puts a paused download at the front of the queue
+	pub fn resume(&mut self, url: &Url) -> bool {
+		let Some(download) = self.paused.remove(url) else {
+			return false;
+		};
+
+		debug!("Resumed download of {}", url);
+		self.record_state(&download, DownloadState::Queued);
+		self.queue.push_front(download);
+		self.start_queued();
+
+		true
+	}
+
+	/// Stops a download wherever it is and throws away what was downloaded
+	/// so far. Returns false if there is no such download.
+	pub async fn abort(&mut self, url: &Url) -> Result<bool, Error> {
+		match self.running.remove(url) {
+			Some(running) => {
+				running.handle.abort();
+				let _ = running.handle.await;
+			}
+			None => {
+				let failed = self.find_failed(url).map(|index| self.failed.remove(index));
+
+				if self.queue.remove(url).is_none() && self.paused.remove(url).is_none() && failed.is_none()
+				{
+					return Ok(false);
+				}
+			}
+		}
+
+		debug!("Aborted download of {}", url);
+		self.start_queued();
+		self.discard_partial(url).await?;
+
+		Ok(true)
+	}
+
+	/// Waits for the next download to finish and saves the result to the
+	/// library. Returns `None` once nothing is queued or running, paused
+	/// downloads are left alone.
+	pub async fn next_event(&mut self, context: &mut Context) -> Option<DownloadEvent> {
+		loop {
+			self.start_queued();
+			if self.running.is_empty() {
+				return None;
+			}
+
+			let (url, run, result) = self.completed_rx.recv().await?;
+
+			if let Some(event) = self.save_result(url, run, result, context).await {
+				return Some(event);
+			}
+		}
+	}
+
+	/// Like `next_event`, but returns `None` right away if no download has
+	/// finished since the last call
+	pub async fn try_next_event(&mut self, context: &mut Context) -> Option<DownloadEvent> {
+		loop {
+			self.start_queued();
+
+			let (url, run, result) = self.completed_rx.try_recv().ok()?;
+
+			if let Some(event) = self.save_result(url, run, result, context).await {
+				return Some(event);
+			}
+		}
+	}
+
+	/// Records how a download ended and saves a stored file to the library.
+	/// Returns `None` for results of runs that were paused or aborted.
+	async fn save_result(
+		&mut self,
 		url: Url,
-		path: PathBuf,
-		tx: mpsc::Sender<(Url, DownloadProgress)>,
-	) {
-		let mut file = match File::open(path).await {
-			Ok(file) => file,
-			Err(err) => {
-				error!("Unable to open file for download: {}", err);
-				return;
+		run: u64,
+		result: Result<StoredBlob, Error>,
+		context: &mut Context,
+	) -> Option<DownloadEvent> {
+		if self
+			.running
+			.get(&url)
+			.is_none_or(|running| running.run != run)
+		{
+			return None;
+		}
+
+		let running = self.running.remove(&url)?;
+		let job_id = running.download.job_id;
+		let recorded = match &result {
+			Ok(_) => FileDownloadRow::mark_done(&mut self.conn, job_id),
+			Err(err) => FileDownloadRow::mark_failed(&mut self.conn, job_id, &err.to_string()),
+		};
+		if let Err(err) = recorded {
+			error!(
+				"Unable to record the result of downloading {}: {}",
+				url, err
+			);
+		}
+
+		let event = match result {
+			Ok(blob) => {
+				let mut items = running.download.items;
+				for item in &mut items {
+					item.set_stored_blob(&blob, context).await;
+				}
+
+				DownloadEvent::Completed { url, items, blob }
+			}
+			Err(error) => {
+				let items = running.download.items.clone();
+				self.failed.push(FailedDownload {
+					download: running.download,
+					error: error.clone(),
+				});
+
+				DownloadEvent::Failed { url, items, error }
 			}
 		};
 
-		let client = reqwest::Client::builder()
-			.user_agent(user_agent)
-			.build()
-			.unwrap();
+		Some(event)
+	}
 
-		let response = match client.get(url.clone()).send().await {
-			Ok(response) => response,
-			Err(err) => {
-				// TODO: send back error progress
+	/// Starts queued downloads while fewer than `concurrency` are running
+	pub fn start_queued(&mut self) {
+		while self.running.len() < self.concurrency {
+			let Some(download) = self.queue.pop_front() else {
 				return;
+			};
+
+			if let Err(err) = FileDownloadRow::mark_running(&mut self.conn, download.job_id) {
+				error!(
+					"Unable to record the start of downloading {}: {}",
+					download.target.url, err
+				);
 			}
-		};
 
-		Self::seek_to_content_range(&response, &mut file);
+			let run = self.next_run;
+			self.next_run += 1;
 
-		let mut stream = response.bytes_stream();
+			let job = self
+				.progress
+				.start_job(JobKind::Download, download.target.get_label(), None);
+			let job_id = job.get_id();
 
-		// TODO: Track downloaded chunks in the database and calculate the progress based on those
+			let handle = self.spawn_download(run, download.target.clone(), job);
+			self.running.insert(
+				download.target.url.clone(),
+				RunningDownload {
+					download,
+					run,
+					job: job_id,
+					handle,
+				},
+			);
+		}
+	}
 
-		while let Some(chunk) = stream.next().await {
-			let mut bytes = match chunk {
+	fn spawn_download(&self, run: u64, target: BlobTarget, mut job: ProgressJob) -> JoinHandle<()> {
+		let file_store = self.file_store.clone();
+		let http = self.http.clone();
+		let progress = self.progress.clone();
+		let completed_tx = self.completed_tx.clone();
+
+		tokio::spawn(async move {
+			let result = Self::store_download(&file_store, &http, &target, &mut job, &progress).await;
+
+			match &result {
+				Ok(_) => job.finish(),
 				Err(err) => {
-					// TODO: Report error back
-					return;
+					error!("Download of {} failed: {}", target.url, err);
+					job.fail(err);
 				}
-				Ok(bytes) => bytes,
-			};
+			}
 
-			file.write_all(&mut bytes).await;
+			let _ = completed_tx.send((target.url, run, result));
+		})
+	}
 
-			if let Err(err) = tx
-				.send((
-					url.clone(),
-					DownloadProgress {
-						bytes_downloaded: bytes.len() as u64,
-						total_size: file.stream_position().await.ok(),
-					},
-				))
-				.await
-			{
-				error!("{:#?}", err)
+	fn find_failed(&self, url: &Url) -> Option<usize> {
+		self
+			.failed
+			.iter()
+			.position(|failed| &failed.download.target.url == url)
+	}
+
+	/// Queues a download that failed before, ahead of new ones with the
+	/// same priority since it was queued first
+	fn requeue(&mut self, download: QueuedDownload) {
+		self.record_state(&download, DownloadState::Queued);
+		self.queue.insert(download);
+	}
+
+	/// Logs instead of failing, the download itself is unaffected
+	fn record_state(&mut self, download: &QueuedDownload, state: DownloadState) {
+		if let Err(err) = FileDownloadRow::set_state(&mut self.conn, download.job_id, state) {
+			error!(
+				"Unable to mark download of {} as {}: {}",
+				download.target.url,
+				state.as_str(),
+				err
+			);
+		}
+	}
+
+	/// Removes the partial file of a download and its job
+	async fn discard_partial(&mut self, url: &Url) -> Result<(), Error> {
+		let temp_path = self.file_store.get_temp_path(url).await?;
+
+		if temp_path.is_file() {
+			tokio::fs::remove_file(&temp_path).await.map_err(|err| {
+				Error::Filesystem(format!("Unable to remove {}: {}", temp_path.display(), err))
+			})?;
+		}
+
+		FileDownloadRow::delete(&mut self.conn, url.as_str(), &temp_path.to_string_lossy())
+			.map_err(|err| Error::Database(format!("Unable to remove download record: {}", err)))
+	}
+
+	/// Downloads the target to its temp path and moves the result into the
+	/// content-addressed store.
+	async fn store_download(
+		file_store: &FileStore,
+		http: &HttpClient,
+		target: &BlobTarget,
+		job: &mut ProgressJob,
+		progress: &ProgressSender,
+	) -> Result<StoredBlob, Error> {
+		let temp_path = file_store.get_temp_path(&target.url).await?;
+		let mut conn = establish_connection(&file_store.data_path)?;
+
+		let streamed_hash = Self::run_download(
+			&mut conn,
+			http,
+			target.provider,
+			target.url.clone(),
+			&temp_path,
+			job,
+		)
+		.await?;
+
+		let blob = file_store
+			.finalize_blob(
+				&temp_path,
+				&target.namespace,
+				&target.extension,
+				streamed_hash,
+				progress,
+			)
+			.await?;
+
+		debug!("Stored {} as {}", target.url, blob.path.display());
+
+		Ok(blob)
+	}
+
+	/// Downloads `url` into `path`, requesting only the byte ranges that
+	/// aren't already recorded in `downloaded_segments`, so an interrupted
+	/// download picks up where it stopped.
+	///
+	/// Returns the content hash if the whole file was streamed front to back
+	/// in this run, otherwise it has to be calculated from the file.
+	async fn run_download(
+		conn: &mut SqliteConnection,
+		http: &HttpClient,
+		provider: ProviderType,
+		url: Url,
+		path: &Path,
+		job: &mut ProgressJob,
+	) -> Result<Option<String>, Error> {
+		let file_path = path.to_string_lossy().to_string();
+		let mut record = FileDownloadRow::find_or_create(conn, url.as_str(), &file_path)
+			.map_err(|err| Error::Database(format!("Unable to record download: {}", err)))?;
+
+		// Segments are meaningless if the partial file has gone missing
+		if !path.is_file() {
+			record
+				.clear_segments(conn)
+				.map_err(|err| Error::Database(format!("Unable to reset download segments: {}", err)))?;
+		}
+
+		let mut file = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(path)
+			.await
+			.map_err(|err| Error::Filesystem(format!("Unable to open file for download: {}", err)))?;
+
+		let mut hasher = Some(StreamHasher::new());
+		let mut previous_downloaded = None;
+
+		loop {
+			let segments = record
+				.merge_segments(conn)
+				.map_err(|err| Error::Database(format!("Unable to merge download segments: {}", err)))?;
+
+			let Some(span) = missing_spans(&segments, record.get_content_length())
+				.first()
+				.copied()
+			else {
+				break;
 			};
+
+			let downloaded = segments
+				.iter()
+				.map(|segment| segment.end - segment.start)
+				.sum();
+
+			// A short response that ends cleanly would otherwise be requested again forever
+			if previous_downloaded == Some(downloaded) {
+				return Err(Error::Network(format!(
+					"Download of {} stopped at byte {} of {}",
+					url,
+					downloaded,
+					record.get_content_length().unwrap_or_default()
+				)));
+			}
+			previous_downloaded = Some(downloaded);
+
+			job.set_done(downloaded, record.get_content_length());
+
+			Self::download_span(
+				conn,
+				http,
+				provider,
+				&mut record,
+				&url,
+				span,
+				&mut file,
+				&mut hasher,
+				downloaded,
+				job,
+			)
+			.await?;
 		}
+
+		debug!("Finished downloading {} to {}", url, path.display());
+
+		let content_length = record.get_content_length().unwrap_or_default();
+
+		Ok(hasher.and_then(|hasher| hasher.finish(content_length)))
 	}
 
-	fn seek_to_content_range(response: &Response, file: &mut File) -> Option<()> {
-		let range_header = response.headers().get("Content-Range")?;
+	/// Requests a single byte span and writes it into `file`, extending the
+	/// span's segment row as data is flushed to disk.
+	#[allow(clippy::too_many_arguments)]
+	async fn download_span(
+		conn: &mut SqliteConnection,
+		http: &HttpClient,
+		provider: ProviderType,
+		record: &mut FileDownloadRow,
+		url: &Url,
+		span: ByteSpan,
+		file: &mut File,
+		hasher: &mut Option<StreamHasher>,
+		mut downloaded: u64,
+		job: &mut ProgressJob,
+	) -> Result<(), Error> {
+		let db_error = |err: diesel::result::Error| {
+			Error::Database(format!("Unable to record download segment: {}", err))
+		};
+
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			RANGE,
+			HeaderValue::from_str(&span.to_range_header())
+				.map_err(|err| Error::InvalidInput(format!("Invalid range header: {}", err)))?,
+		);
 
-		let range = ContentRange::parse_bytes(&range_header.as_bytes())?;
+		let response = http
+			.send(provider, Method::GET, url.as_str(), headers)
+			.await?;
 
-		match range {
-			ContentRange::Bytes(range) => {
-				file.seek(SeekFrom::Start(range.first_byte));
+		let (offset, total_size) = match response.status() {
+			StatusCode::PARTIAL_CONTENT => {
+				let (offset, total_size) = Self::parse_content_range(&response).ok_or(Error::Network(
+					"Missing or invalid Content-Range in partial response".to_string(),
+				))?;
+				if offset != span.start {
+					return Err(Error::Network(format!(
+						"Partial response starts at byte {} instead of {}",
+						offset, span.start
+					)));
+				}
+				(offset, total_size)
 			}
-			ContentRange::UnboundBytes(range) => {
-				file.seek(SeekFrom::Start(range.first_byte));
+			StatusCode::RANGE_NOT_SATISFIABLE => {
+				// We asked for bytes past the end, so the total says where the file ends
+				let total_size = Self::parse_content_range(&response)
+					.and_then(|(_, total_size)| total_size)
+					.ok_or(Error::Network(
+						"416 without Content-Range, the length of the file is unknown".to_string(),
+					))?;
+				if total_size < downloaded {
+					return Err(Error::Network(format!(
+						"Server reports {} bytes, but {} were already downloaded",
+						total_size, downloaded
+					)));
+				}
+				record
+					.set_content_length(conn, total_size)
+					.map_err(db_error)?;
+				return Ok(());
 			}
-			ContentRange::Unsatisfied(_range) => {
-				return None;
+			status if status.is_success() => {
+				// The server ignored the Range header and is sending the whole file
+				if span.start != 0 {
+					record.clear_segments(conn).map_err(db_error)?;
+					downloaded = 0;
+				}
+				(0, response.content_length())
+			}
+			status => {
+				check_status(response)?;
+				return Err(Error::Network(format!(
+					"Unexpected response status: {}",
+					status
+				)));
 			}
 		};
 
-		Some(())
+		if let (Some(total_size), None) = (total_size, record.content_length) {
+			record
+				.set_content_length(conn, total_size)
+				.map_err(db_error)?;
+		}
+
+		file
+			.seek(SeekFrom::Start(offset))
+			.await
+			.map_err(|err| Error::Filesystem(format!("Unable to seek in download file: {}", err)))?;
+
+		let segment_id = record.start_segment(conn, offset).map_err(db_error)?;
+		let mut position = offset;
+		let mut flushed = offset;
+
+		let mut stream = response.bytes_stream();
+
+		while let Some(chunk) = stream.next().await {
+			let bytes = match chunk {
+				Ok(bytes) => bytes,
+				Err(err) => {
+					Self::flush_segment(conn, record, segment_id, file, position).await?;
+					return Err(Error::Network(format!(
+						"Download interrupted at byte {}: {}",
+						position, err
+					)));
+				}
+			};
+
+			file
+				.write_all(&bytes)
+				.await
+				.map_err(|err| Error::Filesystem(format!("Unable to write download file: {}", err)))?;
+
+			if let Some(stream_hasher) = hasher {
+				if !stream_hasher.update(position, &bytes) {
+					*hasher = None;
+				}
+			}
+
+			position += bytes.len() as u64;
+			downloaded += bytes.len() as u64;
+
+			if position - flushed >= SEGMENT_FLUSH_INTERVAL {
+				Self::flush_segment(conn, record, segment_id, file, position).await?;
+				flushed = position;
+			}
+
+			job.set_done(downloaded, record.get_content_length());
+		}
+
+		Self::flush_segment(conn, record, segment_id, file, position).await?;
+
+		// An open-ended request that ran to completion tells us the real size
+		if span.end.is_none() && record.content_length.is_none() {
+			record
+				.set_content_length(conn, position)
+				.map_err(db_error)?;
+		}
+
+		Ok(())
+	}
+
+	/// Only records bytes as downloaded once they've been handed to the OS.
+	async fn flush_segment(
+		conn: &mut SqliteConnection,
+		record: &FileDownloadRow,
+		segment_id: i32,
+		file: &mut File,
+		position: u64,
+	) -> Result<(), Error> {
+		file
+			.flush()
+			.await
+			.map_err(|err| Error::Filesystem(format!("Unable to flush download file: {}", err)))?;
+
+		record
+			.extend_segment(conn, segment_id, position)
+			.map_err(|err| Error::Database(format!("Unable to record download segment: {}", err)))
+	}
+
+	/// Returns the first byte of the response body and the complete file size, if known.
+	fn parse_content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+		let range_header = response.headers().get(CONTENT_RANGE)?;
+
+		match ContentRange::parse_bytes(range_header.as_bytes())? {
+			ContentRange::Bytes(range) => Some((range.first_byte, Some(range.complete_length))),
+			ContentRange::UnboundBytes(range) => Some((range.first_byte, None)),
+			ContentRange::Unsatisfied(range) => Some((0, Some(range.complete_length))),
+		}
+	}
+}
+
+impl Drop for DownloadManager {
+	/// Stops the running downloads like a killed process would, so they are
+	/// still marked as running and continue in the next run
+	fn drop(&mut self) {
+		for running in self.running.values() {
+			running.handle.abort();
+		}
+	}
+}
+
+/// Hashes a download as it is written, as long as the bytes arrive in order
+/// starting from the beginning of the file.
+struct StreamHasher {
+	hasher: Xxh3,
+	position: u64,
+}
+
+impl StreamHasher {
+	fn new() -> Self {
+		Self {
+			hasher: Xxh3::new(),
+			position: 0,
+		}
+	}
+
+	/// Returns false once a chunk arrives out of order and the hash can't be trusted.
+	fn update(&mut self, offset: u64, bytes: &[u8]) -> bool {
+		if offset != self.position {
+			return false;
+		}
+
+		self.hasher.update(bytes);
+		self.position += bytes.len() as u64;
+
+		true
+	}
+
+	fn finish(self, content_length: u64) -> Option<String> {
+		if self.position != content_length {
+			return None;
+		}
+
+		Some(format_content_hash(&self.hasher))
 	}
 }
 
-pub struct DownloadProgress {
-	bytes_downloaded: u64,
-	total_size: Option<u64>,
+#[cfg(test)]
+mod tests {
+	use diesel::{Connection, SqliteConnection};
+	use diesel_migrations::MigrationHarness;
+	use httpmock::prelude::*;
+	use reqwest::Url;
+
+	use super::{segments::FileDownloadRow, DownloadEvent, DownloadManager, DownloadState};
+	use crate::{
+		config::Config,
+		context::TempDataPath,
+		error::Error,
+		establish_connection,
+		file_store::FileStore,
+		http_client::HttpClient,
+		media_sources::{
+			soundgasm::{SoundgasmAudioTrack, TrackMetadata, TrackPointer, TrackSoundPointer},
+			ProviderType,
+		},
+		media_types::{LibraryItem, MediaBlobPointer},
+		progress::{JobKind, ProgressEvent, ProgressSender},
+		Context, MIGRATIONS,
+	};
+
+	fn test_connection() -> SqliteConnection {
+		let mut conn = SqliteConnection::establish(":memory:").unwrap();
+		conn.run_pending_migrations(MIGRATIONS).unwrap();
+		conn
+	}
+
+	#[tokio::test]
+	async fn test_resume_download_requests_missing_range() {
+		let body = b"0123456789";
+
+		let server = MockServer::start_async().await;
+		let mock = server.mock(|when, then| {
+			when
+				.method(GET)
+				.path("/sounds/resume.m4a")
+				.header("range", "bytes=4-");
+			then
+				.status(206)
+				.header("content-range", "bytes 4-9/10")
+				.body(&body[4..]);
+		});
+
+		let data_path = TempDataPath::new("download-resume");
+		std::fs::create_dir_all(&data_path.0).unwrap();
+		let path = data_path.0.join("resume.m4a");
+		std::fs::write(&path, &body[..4]).unwrap();
+
+		let url = Url::parse("https://media.soundgasm.net/sounds/resume.m4a").unwrap();
+		let mut conn = test_connection();
+
+		let record =
+			FileDownloadRow::find_or_create(&mut conn, url.as_str(), &path.to_string_lossy()).unwrap();
+		let segment_id = record.start_segment(&mut conn, 0).unwrap();
+		record.extend_segment(&mut conn, segment_id, 4).unwrap();
+
+		let (progress, mut progress_rx) = ProgressSender::channel();
+		let mut job = progress.start_job(JobKind::Download, "resume.m4a", None);
+		let mut config = Config::new();
+		config.base_urls.soundgasm_media = server.base_url();
+		let http = HttpClient::new(&config).unwrap();
+		let streamed_hash = DownloadManager::run_download(
+			&mut conn,
+			&http,
+			ProviderType::Soundgasm,
+			url,
+			&path,
+			&mut job,
+		)
+		.await
+		.unwrap();
+		job.finish();
+
+		// Only the tail was streamed, so the hash has to come from the file
+		assert!(streamed_hash.is_none());
+
+		// Progress counts the bytes that were already there, and the total
+		// comes from the Content-Range
+		let mut last_advance = None;
+		while let Ok(event) = progress_rx.try_recv() {
+			if let ProgressEvent::Advanced { done, total, .. } = event {
+				last_advance = Some((done, total));
+			}
+		}
+		assert_eq!(last_advance, Some((10, Some(10))));
+
+		mock.assert();
+		assert_eq!(std::fs::read(&path).unwrap(), body);
+		assert_eq!(record.load_segments(&mut conn).unwrap().len(), 1);
+		assert_eq!(record.load_segments(&mut conn).unwrap()[0].end, 10);
+	}
+
+	#[tokio::test]
+	async fn test_download_without_progress_fails() {
+		let server = MockServer::start_async().await;
+		// Ends cleanly without any of the missing bytes
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/empty.m4a");
+			then
+				.status(206)
+				.header("content-range", "bytes 4-9/10")
+				.body("");
+		});
+		// Ignores the range and sends less than the recorded length
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/short.m4a");
+			then.status(200).body("0123");
+		});
+		// Sends a different range than the one asked for
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/shifted.m4a");
+			then
+				.status(206)
+				.header("content-range", "bytes 0-9/10")
+				.body("0123456789");
+		});
+
+		let mut config = Config::new();
+		config.base_urls.soundgasm_media = server.base_url();
+		let http = HttpClient::new(&config).unwrap();
+
+		// Nothing says how long the file is
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/unsatisfiable.m4a");
+			then.status(416);
+		});
+
+		// Says the file is shorter than what is already downloaded
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/shrunk.m4a");
+			then.status(416).header("content-range", "bytes */2");
+		});
+
+		let data_path = TempDataPath::new("download-stalled");
+		std::fs::create_dir_all(&data_path.0).unwrap();
+
+		for sound_id in ["empty", "short", "shifted", "unsatisfiable", "shrunk"] {
+			let path = data_path.0.join(format!("{}.m4a", sound_id));
+			std::fs::write(&path, b"0123").unwrap();
+
+			let url = Url::parse(&format!(
+				"https://media.soundgasm.net/sounds/{}.m4a",
+				sound_id
+			))
+			.unwrap();
+			let mut conn = test_connection();
+
+			let mut record =
+				FileDownloadRow::find_or_create(&mut conn, url.as_str(), &path.to_string_lossy()).unwrap();
+			record.set_content_length(&mut conn, 10).unwrap();
+			let segment_id = record.start_segment(&mut conn, 0).unwrap();
+			record.extend_segment(&mut conn, segment_id, 4).unwrap();
+
+			let mut job = ProgressSender::disabled().start_job(JobKind::Download, sound_id, None);
+			let result = DownloadManager::run_download(
+				&mut conn,
+				&http,
+				ProviderType::Soundgasm,
+				url,
+				&path,
+				&mut job,
+			)
+			.await;
+
+			assert!(
+				matches!(result, Err(Error::Network(_))),
+				"{}: {:?}",
+				sound_id,
+				result
+			);
+		}
+	}
+
+	fn test_track(sound_id: &str) -> SoundgasmAudioTrack {
+		SoundgasmAudioTrack::new(
+			TrackPointer {
+				profile_slug: "sgdl-test".to_string(),
+				track_slug: sound_id.to_string(),
+			},
+			TrackMetadata {
+				title: sound_id.to_string(),
+				description: String::new(),
+			},
+			TrackSoundPointer {
+				sound_id: sound_id.to_string(),
+				file_extension: "m4a".to_string(),
+			},
+		)
+	}
+
+	/// A library in a data directory of its own, downloading from `server`.
+	/// The directory is removed once the returned path is dropped.
+	async fn test_data_context(name: &str, server: &MockServer) -> (TempDataPath, Context) {
+		let data_path = TempDataPath::new(name);
+
+		let mut config = Config::new();
+		config.data_path = data_path.0.clone();
+		config.base_urls.soundgasm_media = server.base_url();
+		let context = Context {
+			conn: establish_connection(&data_path.0).unwrap(),
+			file_store: FileStore::new(&data_path.0).await,
+			http: HttpClient::new(&config).unwrap(),
+			progress: ProgressSender::disabled(),
+			config,
+		};
+
+		(data_path, context)
+	}
+
+	fn get_download_url(track: &SoundgasmAudioTrack) -> Url {
+		track.sound_pointer.get_download_url().unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_pause_resume_and_abort() {
+		let server = MockServer::start_async().await;
+		let mocks = ["first", "second", "third"].map(|sound_id| {
+			server.mock(|when, then| {
+				when.method(GET).path(format!("/sounds/{}.m4a", sound_id));
+				then.status(200).body(sound_id);
+			})
+		});
+
+		let (_data_path, mut context) = test_data_context("download-manager", &server).await;
+
+		let [first, second, third] = ["first", "second", "third"].map(test_track);
+		let mut download_manager = DownloadManager::new(
+			context.file_store.clone(),
+			context.http.clone(),
+			ProgressSender::disabled(),
+			1,
+		)
+		.unwrap();
+
+		// The first starts right away, the others wait for the free slot
+		for track in [&first, &second, &third] {
+			track.add_to_library(&mut context).await.unwrap();
+			download_manager
+				.enqueue(LibraryItem::SoundgasmTrack(track.clone()), 0)
+				.await
+				.unwrap();
+		}
+		download_manager.start_queued();
+		assert_eq!(download_manager.get_pending_count(), 3);
+
+		assert!(download_manager.pause(&get_download_url(&first)).await);
+		assert!(download_manager.pause(&get_download_url(&second)).await);
+		assert!(download_manager
+			.abort(&get_download_url(&third))
+			.await
+			.unwrap());
+		assert!(!download_manager
+			.abort(&get_download_url(&third))
+			.await
+			.unwrap());
+
+		// Only paused downloads are left
+		assert!(download_manager.next_event(&mut context).await.is_none());
+		assert_eq!(download_manager.get_pending_count(), 2);
+		assert_eq!(download_manager.get_paused_count(), 2);
+
+		assert!(download_manager.resume(&get_download_url(&second)));
+		assert!(download_manager.resume(&get_download_url(&first)));
+		assert!(!download_manager.resume(&get_download_url(&first)));
+
+		let mut completed = Vec::new();
+		while let Some(event) = download_manager.next_event(&mut context).await {
+			let DownloadEvent::Completed { items, blob, .. } = event else {
+				panic!("Download failed: {:?}", event);
+			};
+			assert_eq!(
+				std::fs::read(&blob.path).unwrap(),
+				items[0].get_title().as_bytes()
+			);
+			completed.push(items[0].get_title());
+		}
+
+		// The download resumed first took the free slot
+		assert_eq!(completed, vec!["second", "first"]);
+		assert_eq!(download_manager.get_pending_count(), 0);
+		mocks[2].assert_hits(0);
+
+		let downloaded = SoundgasmAudioTrack::find_downloaded(&mut context).await;
+		assert_eq!(downloaded.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_jobs_survive_restart() {
+		let server = MockServer::start_async().await;
+		for sound_id in ["interrupted", "paused", "urgent", "new"] {
+			server.mock(|when, then| {
+				when.method(GET).path(format!("/sounds/{}.m4a", sound_id));
+				then.status(200).body(sound_id);
+			});
+		}
+
+		let (_data_path, mut context) = test_data_context("download-jobs", &server).await;
+
+		let [interrupted, paused, urgent, new] =
+			["interrupted", "paused", "urgent", "new"].map(test_track);
+		let new_manager = |context: &Context| {
+			DownloadManager::new(
+				context.file_store.clone(),
+				context.http.clone(),
+				ProgressSender::disabled(),
+				1,
+			)
+			.unwrap()
+		};
+
+		// A run that was killed while downloading the urgent file, which went
+		// ahead of the others
+		let mut download_manager = new_manager(&context);
+		for track in [&interrupted, &paused] {
+			download_manager
+				.enqueue(LibraryItem::SoundgasmTrack(track.clone()), 0)
+				.await
+				.unwrap();
+		}
+		download_manager
+			.enqueue(LibraryItem::SoundgasmTrack(urgent.clone()), 5)
+			.await
+			.unwrap();
+		assert!(download_manager.pause(&get_download_url(&paused)).await);
+		download_manager.start_queued();
+		drop(download_manager);
+
+		let jobs = FileDownloadRow::load_jobs(&mut context.conn).unwrap();
+		let states = jobs
+			.iter()
+			.map(|job| (job.label.clone().unwrap(), job.get_state(), job.attempts))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			states,
+			vec![
+				("sgdl-test/urgent".to_string(), DownloadState::Running, 1),
+				(
+					"sgdl-test/interrupted".to_string(),
+					DownloadState::Queued,
+					0
+				),
+				("sgdl-test/paused".to_string(), DownloadState::Paused, 0),
+			]
+		);
+
+		// The next run queues everything again, in no particular order
+		let mut download_manager = new_manager(&context);
+		for track in [&new, &paused, &interrupted, &urgent] {
+			download_manager
+				.enqueue(LibraryItem::SoundgasmTrack(track.clone()), 0)
+				.await
+				.unwrap();
+		}
+
+		let mut completed = Vec::new();
+		while let Some(event) = download_manager.next_event(&mut context).await {
+			let DownloadEvent::Completed { items, .. } = event else {
+				panic!("Download failed: {:?}", event);
+			};
+			completed.push(items[0].get_title());
+		}
+
+		// Queued jobs keep their place and the paused one waits
+		assert_eq!(completed, vec!["urgent", "interrupted", "new"]);
+		assert_eq!(download_manager.get_pending_count(), 1);
+
+		let jobs = FileDownloadRow::load_jobs(&mut context.conn).unwrap();
+		let states = jobs
+			.iter()
+			.map(|job| (job.get_state(), job.attempts))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			states,
+			vec![
+				(DownloadState::Done, 2),
+				(DownloadState::Done, 1),
+				(DownloadState::Paused, 0),
+				(DownloadState::Done, 1),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_retry_failed_download() {
+		let server = MockServer::start_async().await;
+		let gone = server.mock(|when, then| {
+			when.method(GET).path("/sounds/flaky.m4a");
+			then.status(404);
+		});
+
+		let (_data_path, mut context) = test_data_context("download-retry", &server).await;
+
+		let track = test_track("flaky");
+		let url = get_download_url(&track);
+		let mut download_manager = DownloadManager::new(
+			context.file_store.clone(),
+			context.http.clone(),
+			ProgressSender::disabled(),
+			1,
+		)
+		.unwrap();
+		download_manager
+			.enqueue(LibraryItem::SoundgasmTrack(track), 0)
+			.await
+			.unwrap();
+
+		let event = download_manager.next_event(&mut context).await.unwrap();
+		assert!(matches!(event, DownloadEvent::Failed { .. }));
+
+		// Failed downloads are listed with their error until retried
+		let downloads = download_manager.get_downloads();
+		assert_eq!(downloads.len(), 1);
+		assert_eq!(downloads[0].state, DownloadState::Failed);
+		assert!(matches!(downloads[0].error, Some(Error::NotFound(_))));
+		assert!(download_manager.next_event(&mut context).await.is_none());
+
+		gone.delete_async().await;
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/flaky.m4a");
+			then.status(200).body("flaky");
+		});
+
+		assert!(download_manager.retry(&url));
+		assert!(!download_manager.retry(&url));
+		assert_eq!(
+			download_manager.get_downloads()[0].state,
+			DownloadState::Running
+		);
+
+		let event = download_manager.next_event(&mut context).await.unwrap();
+		assert!(matches!(event, DownloadEvent::Completed { .. }));
+		assert!(download_manager.get_downloads().is_empty());
+
+		let jobs = FileDownloadRow::load_jobs(&mut context.conn).unwrap();
+		assert_eq!(
+			(jobs[0].get_state(), jobs[0].attempts),
+			(DownloadState::Done, 2)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_download_keeps_newer_metadata() {
+		use diesel::RunQueryDsl;
+
+		let server = MockServer::start_async().await;
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/renamed.m4a");
+			then.status(200).body("renamed");
+		});
+
+		let (_data_path, mut context) = test_data_context("download-metadata", &server).await;
+
+		let track = test_track("renamed");
+		track.add_to_library(&mut context).await.unwrap();
+
+		let mut download_manager = DownloadManager::new(
+			context.file_store.clone(),
+			context.http.clone(),
+			ProgressSender::disabled(),
+			1,
+		)
+		.unwrap();
+		download_manager
+			.enqueue(LibraryItem::SoundgasmTrack(track), 0)
+			.await
+			.unwrap();
+
+		// A scan while the download runs finds a new title
+		diesel::sql_query(
+			"UPDATE soundgasm_tracks SET title = 'New title' WHERE track_slug = 'renamed'",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		let event = download_manager.next_event(&mut context).await.unwrap();
+		assert!(matches!(event, DownloadEvent::Completed { .. }));
+
+		let (title, content_length) = {
+			use crate::schema::soundgasm_tracks::dsl::*;
+			use diesel::prelude::*;
+
+			soundgasm_tracks
+				.filter(track_slug.eq("renamed"))
+				.select((title, content_length))
+				.first::<(String, Option<i64>)>(&mut context.conn)
+				.unwrap()
+		};
+		assert_eq!(title, "New title");
+		assert_eq!(content_length, Some(7));
+	}
 }
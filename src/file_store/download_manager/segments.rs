@@ -0,0 +1,334 @@
+use diesel::prelude::*;
+
+/// A half-open byte range `[start, end)` of a file that has been written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+	pub start: u64,
+	pub end: u64,
+}
+
+/// A byte range that still has to be requested. An `end` of `None` means
+/// "until the end of the file", used while the total size is still unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSpan {
+	pub start: u64,
+	pub end: Option<u64>,
+}
+
+impl ByteSpan {
+	pub fn to_range_header(self) -> String {
+		match self.end {
+			Some(end) => format!("bytes={}-{}", self.start, end - 1),
+			None => format!("bytes={}-", self.start),
+		}
+	}
+}
+
+/// Sorts the segments and joins any that overlap or touch.
+pub fn merge_segments(mut segments: Vec<Segment>) -> Vec<Segment> {
+	segments.retain(|segment| segment.end > segment.start);
+	segments.sort_by_key(|segment| segment.start);
+
+	let mut merged: Vec<Segment> = Vec::with_capacity(segments.len());
+
+	for segment in segments {
+		match merged.last_mut() {
+			Some(last) if segment.start <= last.end => {
+				last.end = last.end.max(segment.end);
+			}
+			_ => merged.push(segment),
+		}
+	}
+
+	merged
+}
+
+/// Lists the spans not covered by `segments`, which must already be merged.
+pub fn missing_spans(segments: &[Segment], total_size: Option<u64>) -> Vec<ByteSpan> {
+	let mut spans = Vec::new();
+	let mut position = 0;
+
+	for segment in segments {
+		if segment.start > position {
+			spans.push(ByteSpan {
+				start: position,
+				end: Some(segment.start),
+			});
+		}
+		position = segment.end;
+	}
+
+	match total_size {
+		Some(total_size) if position < total_size => spans.push(ByteSpan {
+			start: position,
+			end: Some(total_size),
+		}),
+		Some(_) => {}
+		None => spans.push(ByteSpan {
+			start: position,
+			end: None,
+		}),
+	}
+
+	spans
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::file_downloads)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FileDownloadRow {
+	pub id: i32,
+	pub url: String,
+	pub content_length: Option<i64>,
+	pub state: String,
+	pub priority: i32,
+	pub attempts: i32,
+	pub last_error: Option<String>,
+	pub label: Option<String>,
+}
+
+impl FileDownloadRow {
+	pub fn find_or_create(
+		conn: &mut SqliteConnection,
+		download_url: &str,
+		download_path: &str,
+	) -> QueryResult<Self> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::insert_into(file_downloads)
+			.values((
+				url.eq(download_url),
+				file_path.eq(download_path),
+				created_at.eq(chrono::Utc::now().naive_utc()),
+			))
+			.on_conflict((url, file_path))
+			.do_nothing()
+			.execute(conn)?;
+
+		file_downloads
+			.filter(url.eq(download_url))
+			.filter(file_path.eq(download_path))
+			.select(Self::as_select())
+			.first(conn)
+	}
+
+	pub fn delete(
+		conn: &mut SqliteConnection,
+		download_url: &str,
+		download_path: &str,
+	) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		conn.transaction(|conn| {
+			let download_ids = file_downloads
+				.filter(url.eq(download_url))
+				.filter(file_path.eq(download_path))
+				.select(id);
+
+			diesel::delete(
+				crate::schema::downloaded_segments::table
+					.filter(crate::schema::downloaded_segments::download_id.eq_any(download_ids)),
+			)
+			.execute(conn)?;
+
+			diesel::delete(
+				file_downloads
+					.filter(url.eq(download_url))
+					.filter(file_path.eq(download_path)),
+			)
+			.execute(conn)?;
+
+			Ok(())
+		})
+	}
+
+	pub fn get_content_length(&self) -> Option<u64> {
+		self.content_length.map(|length| length as u64)
+	}
+
+	pub fn set_content_length(
+		&mut self,
+		conn: &mut SqliteConnection,
+		length: u64,
+	) -> QueryResult<()> {
+		use crate::schema::file_downloads::dsl::*;
+
+		diesel::update(file_downloads.find(self.id))
+			.set(content_length.eq(length as i64))
+			.execute(conn)?;
+
+		self.content_length = Some(length as i64);
+
+		Ok(())
+	}
+
+	pub fn load_segments(&self, conn: &mut SqliteConnection) -> QueryResult<Vec<Segment>> {
+		use crate::schema::downloaded_segments::dsl::*;
+
+		let rows = downloaded_segments
+			.filter(download_id.eq(self.id))
+			.select((start_index, end_index))
+			.load::<(i64, i64)>(conn)?;
+
+		Ok(
+			rows
+				.into_iter()
+				.map(|(start, end)| Segment {
+					start: start as u64,
+					end: end as u64,
+				})
+				.collect(),
+		)
+	}
+
+	/// Replaces every recorded segment with the merged set, so the table
+	/// holds one row per contiguous range on disk.
+	pub fn merge_segments(&self, conn: &mut SqliteConnection) -> QueryResult<Vec<Segment>> {
+		use crate::schema::downloaded_segments::dsl::*;
+
+		conn.transaction(|conn| {
+			let merged = merge_segments(self.load_segments(conn)?);
+
+			diesel::delete(downloaded_segments.filter(download_id.eq(self.id))).execute(conn)?;
+
+			let rows = merged
+				.iter()
+				.map(|segment| {
+					(
+						download_id.eq(self.id),
+						start_index.eq(segment.start as i64),
+						end_index.eq(segment.end as i64),
+					)
+				})
+				.collect::<Vec<_>>();
+
+			diesel::insert_into(downloaded_segments)
+				.values(rows)
+				.execute(conn)?;
+
+			Ok(merged)
+		})
+	}
+
+	pub fn clear_segments(&self, conn: &mut SqliteConnection) -> QueryResult<()> {
+		use crate::schema::downloaded_segments::dsl::*;
+
+		diesel::delete(downloaded_segments.filter(download_id.eq(self.id))).execute(conn)?;
+
+		Ok(())
+	}
+
+	/// Starts recording a new, empty segment at `start` and returns its id.
+	pub fn start_segment(&self, conn: &mut SqliteConnection, start: u64) -> QueryResult<i32> {
+		use crate::schema::downloaded_segments::dsl::*;
+
+		diesel::insert_into(downloaded_segments)
+			.values((
+				download_id.eq(self.id),
+				start_index.eq(start as i64),
+				end_index.eq(start as i64),
+			))
+			.returning(id)
+			.get_result(conn)
+	}
+
+	pub fn extend_segment(
+		&self,
+		conn: &mut SqliteConnection,
+		segment_id: i32,
+		end: u64,
+	) -> QueryResult<()> {
+		use crate::schema::downloaded_segments::dsl::*;
+
+		diesel::update(downloaded_segments.find(segment_id))
+			.set(end_index.eq(end as i64))
+			.execute(conn)?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{merge_segments, missing_spans, ByteSpan, Segment};
+
+	fn segment(start: u64, end: u64) -> Segment {
+		Segment { start, end }
+	}
+
+	#[test]
+	fn test_merge_segments() {
+		let merged = merge_segments(vec![
+			segment(20, 30),
+			segment(0, 10),
+			segment(10, 15),
+			segment(25, 40),
+			segment(50, 50),
+			segment(60, 70),
+		]);
+
+		assert_eq!(
+			merged,
+			vec![segment(0, 15), segment(20, 40), segment(60, 70)]
+		);
+	}
+
+	#[test]
+	fn test_missing_spans_with_known_size() {
+		let spans = missing_spans(&[segment(10, 20), segment(30, 40)], Some(50));
+
+		assert_eq!(
+			spans,
+			vec![
+				ByteSpan {
+					start: 0,
+					end: Some(10)
+				},
+				ByteSpan {
+					start: 20,
+					end: Some(30)
+				},
+				ByteSpan {
+					start: 40,
+					end: Some(50)
+				},
+			]
+		);
+
+		assert!(missing_spans(&[segment(0, 50)], Some(50)).is_empty());
+	}
+
+	#[test]
+	fn test_missing_spans_with_unknown_size() {
+		assert_eq!(
+			missing_spans(&[], None),
+			vec![ByteSpan {
+				start: 0,
+				end: None
+			}]
+		);
+
+		assert_eq!(
+			missing_spans(&[segment(0, 10)], None),
+			vec![ByteSpan {
+				start: 10,
+				end: None
+			}]
+		);
+	}
+
+	#[test]
+	fn test_range_header() {
+		let bounded = ByteSpan {
+			start: 10,
+			end: Some(20),
+		};
+		assert_eq!(bounded.to_range_header(), "bytes=10-19");
+
+		let unbounded = ByteSpan {
+			start: 10,
+			end: None,
+		};
+		assert_eq!(unbounded.to_range_header(), "bytes=10-");
+	}
+}
@@ -1,70 +1,128 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use tokio::{fs::File, io::AsyncReadExt};
 use xxhash_rust::xxh3::Xxh3;
 
+use super::FileStore;
+use crate::progress::{JobKind, ProgressSender};
+
+/// Read size while hashing, which is also how often progress is reported
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Outcome of checking a stored blob against its recorded hash and length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlobStatus {
+	Valid,
+	Missing,
+	LengthMismatch { expected: i64, actual: i64 },
+	HashMismatch { expected: String, actual: String },
+}
+
+/// A stored file. `get_path` is relative to the `FileStore` data path.
 pub trait MediaBlob {
 	fn get_path(&self) -> PathBuf;
 	fn get_content_length(&self) -> i64;
 	fn get_content_hash(&self) -> String;
 
-	async fn verify(&self) -> bool {
-		if !self.verify_content_length().await {
-			return false;
+	/// Checks the cheap length first and only hashes the file if that matches.
+	async fn verify(&self, file_store: &FileStore, progress: &ProgressSender) -> BlobStatus {
+		if let Err(status) = self.verify_content_length(file_store).await {
+			return status;
 		}
 
-		self.verify_content_hash().await
+		if let Err(status) = self.verify_content_hash(file_store, progress).await {
+			return status;
+		}
+
+		BlobStatus::Valid
 	}
 
-	async fn verify_content_hash(&self) -> bool {
-		let Some(content_hash) = self.calculate_content_hash().await else {
-			return false;
+	async fn verify_content_length(&self, file_store: &FileStore) -> Result<(), BlobStatus> {
+		let Some(content_length) = self.calculate_content_length(file_store).await else {
+			return Err(BlobStatus::Missing);
 		};
 
-		self.get_content_hash() == content_hash
+		if content_length != self.get_content_length() {
+			return Err(BlobStatus::LengthMismatch {
+				expected: self.get_content_length(),
+				actual: content_length,
+			});
+		}
+
+		Ok(())
 	}
 
-	async fn verify_content_length(&self) -> bool {
-		let Some(content_length) = self.calculate_content_length().await else {
-			return false;
+	async fn verify_content_hash(
+		&self,
+		file_store: &FileStore,
+		progress: &ProgressSender,
+	) -> Result<(), BlobStatus> {
+		let Some(content_hash) = self.calculate_content_hash(file_store, progress).await else {
+			return Err(BlobStatus::Missing);
 		};
 
-		self.get_content_length() == content_length
+		if content_hash != self.get_content_hash() {
+			return Err(BlobStatus::HashMismatch {
+				expected: self.get_content_hash(),
+				actual: content_hash,
+			});
+		}
+
+		Ok(())
 	}
 
-	async fn calculate_content_hash(&self) -> Option<String> {
-		let file_path = self.get_path();
-		if !file_path.is_file() {
+	async fn calculate_content_hash(
+		&self,
+		file_store: &FileStore,
+		progress: &ProgressSender,
+	) -> Option<String> {
+		calculate_file_hash(&file_store.resolve_path(self.get_path()), progress).await
+	}
+
+	async fn calculate_content_length(&self, file_store: &FileStore) -> Option<i64> {
+		let path = file_store.resolve_path(self.get_path());
+		if !path.is_file() {
 			return None;
 		}
 
-		let mut file = File::open(&file_path).await.ok()?;
+		tokio::fs::metadata(path)
+			.await
+			.ok()
+			.map(|metadata| metadata.len() as i64)
+	}
+}
+
+pub fn format_content_hash(hasher: &Xxh3) -> String {
+	format!("{:x}", hasher.digest())
+}
+
+pub async fn calculate_file_hash(file_path: &Path, progress: &ProgressSender) -> Option<String> {
+	if !file_path.is_file() {
+		return None;
+	}
 
-		let mut buffer = vec![0; 8192];
-		let mut hasher = Xxh3::new();
+	let mut file = File::open(file_path).await.ok()?;
+	let content_length = file.metadata().await.ok().map(|metadata| metadata.len());
 
-		loop {
-			let Ok(bytes_read) = file.read(&mut buffer).await else {
-				break;
-			};
+	let label = file_path
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_default();
+	let mut job = progress.start_job(JobKind::Hash, label, content_length);
 
-			if bytes_read == 0 {
-				break;
-			}
+	let mut buffer = vec![0; HASH_BUFFER_SIZE];
+	let mut hasher = Xxh3::new();
 
-			hasher.update(&buffer[..bytes_read]);
-			// TODO: Report progress with indicitif
+	while let Ok(bytes_read) = file.read(&mut buffer).await {
+		if bytes_read == 0 {
+			break;
 		}
 
-		let hash = hasher.digest();
-		Some(format!("{:x}", hash))
+		hasher.update(&buffer[..bytes_read]);
+		job.advance(bytes_read as u64);
 	}
 
-	async fn calculate_content_length(&self) -> Option<i64> {
-		let path = self.get_path();
-		tokio::fs::metadata(path)
-			.await
-			.ok()
-			.map(|metadata| metadata.len() as i64)
-	}
+	job.finish();
+
+	Some(format_content_hash(&hasher))
 }
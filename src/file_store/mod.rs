@@ -2,15 +2,28 @@ pub mod download_manager;
 mod media_blob;
 
 use diesel::prelude::*;
+use reqwest::Url;
 use std::path::{Path, PathBuf};
-use tokio::fs::create_dir_all;
+use tokio::fs::{create_dir_all, remove_file, rename};
+use xxhash_rust::xxh3::xxh3_64;
 
-pub use media_blob::MediaBlob;
+use crate::error::Error;
+use crate::progress::ProgressSender;
+
+pub use media_blob::{calculate_file_hash, format_content_hash, BlobStatus, MediaBlob};
 
 define_sql_function! {
 	fn current_timestamp() -> Timestamp;
 }
 
+/// A blob that has been moved into its content-addressed location.
+#[derive(Clone, Debug)]
+pub struct StoredBlob {
+	pub content_hash: String,
+	pub content_length: i64,
+	pub path: PathBuf,
+}
+
 #[derive(Clone, Debug)]
 pub struct FileStore {
 	pub data_path: PathBuf,
@@ -37,68 +50,137 @@ impl FileStore {
 		}
 	}
 
-	// async fn get_namespace_path(&self, namespace: &str) -> PathBuf {
-	// 	let namespace_path = self.data_path.join(namespace);
-	// 	if !namespace_path.exists() {
-	// 		match create_dir_all(&namespace_path).await {
-	// 			Err(err) => {
-	// 				log::error!("Failed to create namespace directory: {}", err);
-	// 				return PathBuf::new(); // Return an empty path if creation fails
-	// 			}
-	// 			_ => {}
-	// 		};
-	// 	}
-
-	// 	namespace_path
-	// }
-
-	// TODO: Convert the print messages and failures to some kind of stream or progress reporting
-
-	// pub async fn stream_response_to_track(
-	// 	&self,
-	// 	track: Track,
-	// 	mut res: reqwest::Response,
-	// ) -> Option<Track> {
-	// 	let id = track.id.clone();
-
-	// 	let file_path = self.get_track_path(&track);
-	// 	if file_path.exists() {
-	// 		println!("File already exists: {}", file_path.display());
-	// 		return None;
-	// 	}
-
-	// 	let mut file = match File::create(file_path).await {
-	// 		Ok(file) => file,
-	// 		Err(err) => {
-	// 			println!("Error creating file: {}", err);
-	// 			return None;
-	// 		}
-	// 	};
-
-	// 	let mut hasher = Xxh3::new();
-
-	// 	loop {
-	// 		match res.chunk().await {
-	// 			Err(err) => {
-	// 				println!("Error reading response: {:?}", err);
-	// 				return None;
-	// 			}
-	// 			Ok(Some(data)) => {
-	// 				hasher.update(&data);
-	// 				file.write(data.as_ref()).await.unwrap();
-	// 			}
-	// 			Ok(None) => {
-	// 				break;
-	// 			}
-	// 		}
-	// 	}
-
-	// 	let hash = hasher.digest();
-	// 	let hash_hex = format!("{:x}", hash);
-	// 	let content_length = file.metadata().await.unwrap().len();
-
-	// 	// TODO: save track to database
-
-	// 	Some(track)
-	// }
+	async fn get_namespace_path(&self, namespace: &str) -> Result<PathBuf, Error> {
+		let namespace_path = self.data_path.join("data").join(namespace);
+		if !namespace_path.exists() {
+			create_dir_all(&namespace_path).await.map_err(|err| {
+				Error::Filesystem(format!("Failed to create namespace directory: {}", err))
+			})?;
+		}
+
+		Ok(namespace_path)
+	}
+
+	pub fn resolve_path<P: AsRef<Path>>(&self, relative_path: P) -> PathBuf {
+		self.data_path.join(relative_path)
+	}
+
+	/// Path of a blob relative to the data directory, e.g. `data/soundgasm_audio/<hash>.m4a`.
+	pub fn get_relative_blob_path(namespace: &str, content_hash: &str, extension: &str) -> PathBuf {
+		PathBuf::from("data")
+			.join(namespace)
+			.join(format!("{}.{}", content_hash, extension))
+	}
+
+	pub fn get_blob_path(&self, namespace: &str, content_hash: &str, extension: &str) -> PathBuf {
+		self.resolve_path(Self::get_relative_blob_path(
+			namespace,
+			content_hash,
+			extension,
+		))
+	}
+
+	/// Where an in-progress download of `url` is written before it is finalized.
+	pub async fn get_temp_path(&self, url: &Url) -> Result<PathBuf, Error> {
+		let temp_path = self.get_namespace_path("tmp").await?;
+
+		Ok(temp_path.join(format!("{:x}.part", xxh3_64(url.as_str().as_bytes()))))
+	}
+
+	/// Moves a finished download into `data/<namespace>/<hash>.<extension>`.
+	/// The hash is calculated from the file unless it was already computed
+	/// while streaming. If a blob with the same hash is already stored the
+	/// temp file is discarded, so identical content is only kept once.
+	pub async fn finalize_blob(
+		&self,
+		temp_path: &Path,
+		namespace: &str,
+		extension: &str,
+		content_hash: Option<String>,
+		progress: &ProgressSender,
+	) -> Result<StoredBlob, Error> {
+		let content_hash = match content_hash {
+			Some(content_hash) => content_hash,
+			None => calculate_file_hash(temp_path, progress)
+				.await
+				.ok_or_else(|| Error::Filesystem(format!("Unable to hash {}", temp_path.display())))?,
+		};
+
+		let content_length = tokio::fs::metadata(temp_path)
+			.await
+			.map_err(|err| Error::Filesystem(format!("Unable to read {}: {}", temp_path.display(), err)))?
+			.len() as i64;
+
+		self.get_namespace_path(namespace).await?;
+		let path = self.get_blob_path(namespace, &content_hash, extension);
+
+		if path.is_file() {
+			log::debug!(
+				"Blob already stored, discarding duplicate: {}",
+				path.display()
+			);
+			remove_file(temp_path).await.map_err(|err| {
+				Error::Filesystem(format!("Unable to remove {}: {}", temp_path.display(), err))
+			})?;
+		} else {
+			// Both paths are under data/, so this is an atomic move on the same filesystem
+			rename(temp_path, &path)
+				.await
+				.map_err(|err| Error::Filesystem(format!("Unable to move blob into place: {}", err)))?;
+		}
+
+		Ok(StoredBlob {
+			content_hash,
+			content_length,
+			path,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::FileStore;
+	use crate::progress::ProgressSender;
+
+	#[tokio::test]
+	async fn test_finalize_blob_deduplicates() {
+		let data_path = std::env::temp_dir().join("sgdl-finalize-test");
+		let _ = std::fs::remove_dir_all(&data_path);
+		let file_store = FileStore::new(&data_path).await;
+
+		let first = data_path.join("first.part");
+		let second = data_path.join("second.part");
+		std::fs::write(&first, b"same audio").unwrap();
+		std::fs::write(&second, b"same audio").unwrap();
+
+		let first_blob = file_store
+			.finalize_blob(
+				&first,
+				"test_audio",
+				"m4a",
+				None,
+				&ProgressSender::disabled(),
+			)
+			.await
+			.unwrap();
+		let second_blob = file_store
+			.finalize_blob(
+				&second,
+				"test_audio",
+				"m4a",
+				None,
+				&ProgressSender::disabled(),
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(first_blob.content_hash, second_blob.content_hash);
+		assert_eq!(first_blob.path, second_blob.path);
+		assert_eq!(first_blob.content_length, 10);
+		assert_eq!(std::fs::read(&first_blob.path).unwrap(), b"same audio");
+		assert!(!first.exists());
+		assert!(!second.exists());
+
+		std::fs::remove_dir_all(&data_path).unwrap();
+	}
 }
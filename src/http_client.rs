@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, COOKIE};
+use reqwest::{Client, Method, Proxy, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+
+use crate::config::{BaseUrls, Config, ProviderHttpConfig};
+use crate::error::Error;
+use crate::media_sources::ProviderType;
+use crate::throttle::{send_with_retry, HostRateLimiter, RetryPolicy};
+
+/// Makes every request of the app, following the user agent, rate limit,
+/// timeout, retry policy, proxy and cookies configured for each provider,
+/// and sending it to the provider's configured base URL.
+/// Cheap to clone, clones share their rate limits.
+#[derive(Clone)]
+pub struct HttpClient {
+	base_urls: Arc<BaseUrls>,
+	soundgasm: Arc<ProviderClient>,
+	kemono: Arc<ProviderClient>,
+	patreon: Arc<ProviderClient>,
+}
+
+struct ProviderClient {
+	client: Client,
+	rate_limiter: HostRateLimiter,
+	retry_policy: RetryPolicy,
+}
+
+impl ProviderClient {
+	fn new(config: &ProviderHttpConfig) -> Result<Self, Error> {
+		let mut default_headers = HeaderMap::new();
+		if let Some(cookies) = &config.cookies {
+			let cookies = HeaderValue::from_str(cookies)
+				.map_err(|err| Error::Config(format!("Invalid cookies: {}", err)))?;
+			default_headers.insert(COOKIE, cookies);
+		}
+
+		let timeout = Duration::from_secs(config.timeout_secs);
+		let mut builder = Client::builder()
+			.user_agent(&config.user_agent)
+			.default_headers(default_headers)
+			.connect_timeout(timeout)
+			.read_timeout(timeout);
+
+		if let Some(proxy) = &config.proxy {
+			builder = builder.proxy(
+				Proxy::all(proxy)
+					.map_err(|err| Error::Config(format!("Invalid proxy {}: {}", proxy, err)))?,
+			);
+		}
+
+		let client = builder
+			.build()
+			.map_err(|err| Error::Config(format!("Unable to build HTTP client: {}", err)))?;
+
+		Ok(Self {
+			client,
+			rate_limiter: HostRateLimiter::new(Duration::from_millis(config.min_request_interval_ms)),
+			retry_policy: RetryPolicy {
+				max_attempts: config.max_attempts.max(1),
+				initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+				..Default::default()
+			},
+		})
+	}
+}
+
+impl HttpClient {
+	pub fn new(config: &Config) -> Result<Self, Error> {
+		Ok(Self {
+			base_urls: Arc::new(config.base_urls.clone()),
+			soundgasm: Arc::new(ProviderClient::new(&config.http.soundgasm)?),
+			kemono: Arc::new(ProviderClient::new(&config.http.kemono)?),
+			patreon: Arc::new(ProviderClient::new(&config.http.patreon)?),
+		})
+	}
+
+	fn get_provider_client(&self, provider: ProviderType) -> &ProviderClient {
+		match provider {
+			ProviderType::Soundgasm => &self.soundgasm,
+			ProviderType::Kemono => &self.kemono,
+			ProviderType::Patreon => &self.patreon,
+		}
+	}
+
+	/// Sends a request once the provider's rate limit allows it, retrying
+	/// while the server is overloaded. The response can still have an error
+	/// status.
+	pub async fn send(
+		&self,
+		provider: ProviderType,
+		method: Method,
+		url: &str,
+		headers: HeaderMap,
+	) -> Result<Response, Error> {
+		let provider_client = self.get_provider_client(provider);
+		let url = Url::parse(url)
+			.map_err(|err| Error::InvalidInput(format!("Invalid URL {}: {}", url, err)))?;
+		let url = self.base_urls.resolve(&url)?;
+
+		send_with_retry(
+			&provider_client.client,
+			&provider_client.rate_limiter,
+			&provider_client.retry_policy,
+			method,
+			url.as_str(),
+			headers,
+		)
+		.await
+	}
+
+	pub async fn fetch_text(&self, provider: ProviderType, url: &str) -> Result<String, Error> {
+		let response = self
+			.send(provider, Method::GET, url, HeaderMap::new())
+			.await?;
+
+		let text = check_status(response)?
+			.text()
+			.await
+			.map_err(|err| Error::Network(format!("Failed to read {}: {}", url, err)))?;
+
+		Ok(text)
+	}
+
+	pub async fn fetch_json<T: DeserializeOwned>(
+		&self,
+		provider: ProviderType,
+		url: &str,
+	) -> Result<T, Error> {
+		let response = self
+			.send(provider, Method::GET, url, HeaderMap::new())
+			.await?;
+
+		let body = check_status(response)?
+			.bytes()
+			.await
+			.map_err(|err| Error::Network(format!("Failed to read {}: {}", url, err)))?;
+		let value = serde_json::from_slice::<T>(&body)
+			.map_err(|err| Error::Parse(format!("Unexpected response from {}: {}", url, err)))?;
+
+		Ok(value)
+	}
+
+	/// Asks the server for a file's `Content-Type` without downloading it.
+	pub async fn fetch_content_type(
+		&self,
+		provider: ProviderType,
+		url: &str,
+	) -> Result<Option<String>, Error> {
+		let response = self
+			.send(provider, Method::HEAD, url, HeaderMap::new())
+			.await?;
+		let response = check_status(response)?;
+
+		let content_type = response
+			.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|value| value.to_str().ok())
+			.map(|value| value.to_string());
+
+		Ok(content_type)
+	}
+}
+
+/// Turns error statuses into errors, telling things that are gone from the
+/// provider apart from requests that could work another time
+pub fn check_status(response: Response) -> Result<Response, Error> {
+	let status = response.status();
+
+	if matches!(status, StatusCode::NOT_FOUND | StatusCode::GONE) {
+		return Err(Error::NotFound(format!(
+			"{} is gone ({})",
+			response.url(),
+			status
+		)));
+	}
+
+	response
+		.error_for_status()
+		.map_err(|err| Error::Network(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use httpmock::prelude::*;
+
+	use super::HttpClient;
+	use crate::config::Config;
+	use crate::error::Error;
+	use crate::media_sources::ProviderType;
+
+	#[tokio::test]
+	async fn test_requests_follow_provider_config() {
+		let server = MockServer::start();
+		let mock = server.mock(|when, then| {
+			when
+				.method(GET)
+				.path("/api")
+				.header("user-agent", "kemono-agent")
+				.header("cookie", "session=abc");
+			then.status(200).body("ok");
+		});
+
+		let mut config = Config::new();
+		config.http.kemono.user_agent = "kemono-agent".to_string();
+		config.http.kemono.cookies = Some("session=abc".to_string());
+		config.base_urls.kemono = server.base_url();
+		let http = HttpClient::new(&config).unwrap();
+
+		let text = http
+			.fetch_text(ProviderType::Kemono, "https://kemono.su/api")
+			.await
+			.unwrap();
+		assert_eq!(text, "ok");
+		mock.assert();
+
+		// Other providers don't get Kemono's cookies, so nothing matches
+		let result = http
+			.fetch_text(ProviderType::Soundgasm, &server.url("/api"))
+			.await;
+		assert!(matches!(result, Err(Error::NotFound(_))));
+	}
+
+	#[test]
+	fn test_invalid_proxy_is_an_error() {
+		let mut config = Config::new();
+		config.http.soundgasm.proxy = Some("not a proxy".to_string());
+
+		assert!(HttpClient::new(&config).is_err());
+	}
+}
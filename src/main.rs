@@ -2,23 +2,35 @@ mod commands;
 mod common;
 mod config;
 mod context;
+#[cfg(test)]
+mod end_to_end;
+mod error;
 mod file_store;
+mod http_client;
 mod macros;
 mod media_sources;
 mod media_types;
+mod player;
+mod progress;
 mod schema;
+mod throttle;
 
 use clap::{Parser, Subcommand};
 use config::Config;
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
 use diesel::SqliteConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use log::error;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use tokio::fs::create_dir_all;
 use Commands::*;
 
+use error::Error;
 use file_store::FileStore;
+use http_client::HttpClient;
+use progress::ProgressSender;
 
 pub use context::Context;
 
@@ -42,16 +54,64 @@ enum Commands {
 		/// URL or other indicator of media to scan
 		media_string: String,
 	},
+	/// download cataloged media that hasn't been stored yet
+	Download {
+		/// only download from this soundgasm profile or Kemono creator id
+		#[arg(short, long, value_name = "SLUG")]
+		profile: Option<String>,
+
+		/// number of files to download at once
+		#[arg(short, long, default_value_t = 4, value_name = "COUNT")]
+		concurrency: usize,
+	},
+	/// show queued, running, paused and failed downloads
+	Status,
+	/// check stored files against their recorded length and hash
+	Verify {
+		/// clear broken items so the next download fetches them again
+		#[arg(long)]
+		reset: bool,
+
+		/// print the summary as JSON
+		#[arg(long)]
+		json: bool,
+	},
+	/// check a profile or creator for new items on every sync, or list
+	/// subscriptions if no URL is given
+	Subscribe {
+		/// URL of the profile, or of a track or post on it
+		url: Option<String>,
+	},
+	/// catalog new items from every subscribed profile and creator
+	Sync,
+	/// search the library by text, tags, type, author and date
+	Search(commands::SearchArgs),
+	/// show how the title and description of a track or post changed
+	#[command(arg_required_else_help = true)]
+	History {
+		/// URL of the track or post
+		url: String,
+	},
+	/// add, remove and list tags
+	#[command(arg_required_else_help = true)]
+	Tag {
+		#[command(subcommand)]
+		action: commands::TagAction,
+	},
+	/// browse, search and play the library in the terminal
+	Tui {
+		/// where played tracks are heard
+		#[arg(long, value_enum, default_value_t)]
+		audio_output: player::AudioOutput,
+	},
 	Gui,
 }
 
 impl Clone for Context {
+	/// Panics if the library can't be opened again, use `try_clone` where
+	/// that can be handled
 	fn clone(&self) -> Self {
-		Self {
-			config: self.config.clone(),
-			conn: establish_connection(self.file_store.data_path.as_path()),
-			file_store: self.file_store.clone(),
-		}
+		self.try_clone().unwrap_or_else(|err| panic!("{}", err))
 	}
 }
 
@@ -63,9 +123,9 @@ async fn main() {
 		return;
 	}
 
-	let Ok(config) = confy::load::<Config>("sgdl", None) else {
-		eprintln!("Error loading config");
-		return;
+	let config = match confy::load::<Config>("sgdl", None) {
+		Ok(config) => config,
+		Err(err) => exit_with(Error::Config(format!("Error loading config: {}", err))),
 	};
 
 	let cli = Cli::parse();
@@ -85,55 +145,98 @@ async fn main() {
 
 	let file_store = FileStore::new(&data_path).await;
 
+	let http = match HttpClient::new(&config) {
+		Ok(http) => http,
+		Err(err) => exit_with(Error::Config(format!("Error in HTTP config: {}", err))),
+	};
+
+	let conn = match establish_connection(&data_path) {
+		Ok(conn) => conn,
+		Err(err) => exit_with(err),
+	};
+
+	let (progress, progress_rx) = ProgressSender::channel();
+	let progress_bars = tokio::spawn(commands::show_progress(progress_rx));
+
 	let mut context = Context {
 		config,
 		file_store,
-		conn: establish_connection(&data_path),
+		http,
+		conn,
+		progress,
 	};
 
-	// TODO: Tagging system
 	// TODO: gwasi support
 	// TODO: live tag search and create newsfeed
 
-	match cmd {
-		Scan { media_string } => {
-			commands::scan_command(media_string, &mut context).await;
-		}
+	let result = match cmd {
+		Scan { media_string } => commands::scan_command(media_string, &mut context).await,
+		Download {
+			profile,
+			concurrency,
+		} => commands::download_command(profile, concurrency, &mut context).await,
+		Status => commands::status_command(&mut context).await,
+		Verify { reset, json } => commands::verify_command(reset, json, &mut context).await,
+		Subscribe { url } => commands::subscribe_command(url, &mut context).await,
+		Sync => commands::sync_command(&mut context).await,
+		Search(args) => commands::search_command(args, &mut context).await,
+		History { url } => commands::history_command(url, &mut context).await,
+		Tag { action } => commands::tag_command(action, &mut context).await,
+		Tui { audio_output } => commands::tui_command(audio_output, &mut context).await,
 		Gui => {
 			commands::start_gui(&mut context);
+			Ok(())
 		}
 	};
+
+	// The bars are done once the last sender is gone
+	drop(context);
+	let _ = progress_bars.await;
+
+	if let Err(err) = result {
+		exit_with(err);
+	}
+}
+
+/// Reports an error that ended the command and exits with its code
+fn exit_with(err: Error) -> ! {
+	error!("{}", err);
+	eprintln!("{}", err);
+	std::process::exit(err.exit_code());
 }
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
-fn establish_connection(data_path: &Path) -> SqliteConnection {
-	let audio_path = data_path.join("audio");
-	if !audio_path.exists() {
-		std::fs::create_dir_all(&audio_path).unwrap();
+fn establish_connection(data_path: &Path) -> Result<SqliteConnection, Error> {
+	for path in [data_path.join("audio"), data_path.join("data")] {
+		if !path.exists() {
+			std::fs::create_dir_all(&path).map_err(|err| {
+				Error::Filesystem(format!("Unable to create {}: {}", path.display(), err))
+			})?;
+		}
 	}
 
-	let database_path = data_path.join("data");
-	if !database_path.exists() {
-		std::fs::create_dir_all(&database_path).unwrap();
-	}
+	let database_path = data_path.join("data").join("meta.sqlite3");
 
-	let database_path = database_path.join("meta.sqlite3");
+	let mut conn = SqliteConnection::establish(&database_path.to_string_lossy()).map_err(|err| {
+		Error::Database(format!(
+			"Unable to open {}: {}",
+			database_path.display(),
+			err
+		))
+	})?;
 
-	let mut conn =
-		SqliteConnection::establish(database_path.to_str().unwrap()).unwrap_or_else(|err| {
-			println!("Error connecting to {err:?}");
-			panic!("Error connecting to {}", database_path.display());
-		});
+	// Downloads write their progress from connections of their own, so wait
+	// for a moment instead of failing when another one holds the lock
+	conn
+		.batch_execute("PRAGMA busy_timeout = 5000;")
+		.map_err(|err| Error::Database(format!("Unable to configure database: {}", err)))?;
 
 	conn
 		.run_pending_migrations(MIGRATIONS)
-		.unwrap_or_else(|err| {
-			println!("Error running migrations: {err:?}");
-			panic!("Error running migrations");
-		});
+		.map_err(|err| Error::Database(format!("Unable to run migrations: {}", err)))?;
 
-	conn
+	Ok(conn)
 }
 
 async fn setup_logger() -> Result<(), fern::InitError> {
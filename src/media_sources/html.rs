@@ -0,0 +1,610 @@
+//! A small, forgiving HTML parser for scraping pages. It builds a tree of
+//! elements and text that can be searched with simple selectors like
+//! `div.sound-details` or `p`, and decodes entities in text and attributes.
+//! Malformed markup, like stray end tags, is skipped rather than rejected.
+
+use std::fmt::Display;
+
+/// A required part of a page that couldn't be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	pub page: &'static str,
+	pub field: &'static str,
+}
+
+impl ParseError {
+	pub fn missing(page: &'static str, field: &'static str) -> Self {
+		Self { page, field }
+	}
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Unable to find the {} on the {} page",
+			self.field, self.page
+		)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+const VOID_ELEMENTS: &[&str] = &[
+	"area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+	"track", "wbr",
+];
+
+/// Elements whose content is text up to their end tag, never markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements that close an open `<p>`
+const BLOCK_ELEMENTS: &[&str] = &[
+	"address",
+	"article",
+	"aside",
+	"blockquote",
+	"div",
+	"dl",
+	"fieldset",
+	"footer",
+	"form",
+	"h1",
+	"h2",
+	"h3",
+	"h4",
+	"h5",
+	"h6",
+	"header",
+	"hr",
+	"main",
+	"nav",
+	"ol",
+	"p",
+	"pre",
+	"section",
+	"table",
+	"ul",
+];
+
+#[derive(Debug)]
+enum NodeData {
+	Element {
+		name: String,
+		attributes: Vec<(String, String)>,
+	},
+	Text(String),
+}
+
+#[derive(Debug)]
+struct Node {
+	data: NodeData,
+	children: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct Document {
+	/// The root is an element without a name at index 0
+	nodes: Vec<Node>,
+}
+
+impl Document {
+	pub fn parse(html: &str) -> Self {
+		let mut builder = TreeBuilder::new();
+		let mut rest = html;
+
+		while !rest.is_empty() {
+			let Some(tag_start) = rest.find('<') else {
+				builder.push_text(decode_entities(rest));
+				break;
+			};
+
+			if tag_start > 0 {
+				builder.push_text(decode_entities(&rest[..tag_start]));
+				rest = &rest[tag_start..];
+			}
+
+			if let Some(comment) = rest.strip_prefix("<!--") {
+				rest = comment
+					.find("-->")
+					.map_or("", |comment_end| &comment[comment_end + 3..]);
+			} else if rest.starts_with("<!") || rest.starts_with("<?") {
+				rest = rest.find('>').map_or("", |tag_end| &rest[tag_end + 1..]);
+			} else if let Some(end_tag) = rest.strip_prefix("</") {
+				let tag_end = end_tag.find('>').unwrap_or(end_tag.len());
+				let name = end_tag[..tag_end].trim().to_ascii_lowercase();
+				builder.close(&name);
+				rest = end_tag.get(tag_end + 1..).unwrap_or("");
+			} else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+				let (tag, after_tag) = parse_start_tag(&rest[1..]);
+				rest = after_tag;
+
+				if RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+					let (text, after_text) = split_raw_text(rest, &tag.name);
+					let text = match tag.name.as_str() {
+						"script" | "style" => text.to_string(),
+						_ => decode_entities(text),
+					};
+
+					builder.open(tag.name, tag.attributes);
+					builder.push_text(text);
+					builder.pop();
+					rest = after_text;
+				} else {
+					let is_void = tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str());
+					builder.open(tag.name, tag.attributes);
+					if is_void {
+						builder.pop();
+					}
+				}
+			} else {
+				// A lone `<` is just text
+				builder.push_text("<".to_string());
+				rest = &rest[1..];
+			}
+		}
+
+		Self {
+			nodes: builder.nodes,
+		}
+	}
+
+	pub fn root(&self) -> ElementRef<'_> {
+		ElementRef {
+			document: self,
+			id: 0,
+		}
+	}
+
+	/// Every element matching `selector`, in document order
+	pub fn select(&self, selector: &str) -> Vec<ElementRef<'_>> {
+		self.root().select(selector)
+	}
+
+	/// The first element matching `selector`
+	pub fn select_first(&self, selector: &str) -> Option<ElementRef<'_>> {
+		self.root().select_first(selector)
+	}
+}
+
+#[derive(Clone, Copy)]
+pub struct ElementRef<'a> {
+	document: &'a Document,
+	id: usize,
+}
+
+impl<'a> ElementRef<'a> {
+	fn node(&self) -> &'a Node {
+		&self.document.nodes[self.id]
+	}
+
+	pub fn name(&self) -> &'a str {
+		match &self.node().data {
+			NodeData::Element { name, .. } => name,
+			NodeData::Text(_) => "",
+		}
+	}
+
+	pub fn attr(&self, attribute: &str) -> Option<&'a str> {
+		let NodeData::Element { attributes, .. } = &self.node().data else {
+			return None;
+		};
+
+		attributes
+			.iter()
+			.find(|(name, _)| name == attribute)
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub fn has_class(&self, class: &str) -> bool {
+		self
+			.attr("class")
+			.is_some_and(|classes| classes.split_ascii_whitespace().any(|name| name == class))
+	}
+
+	fn descendants(&self) -> Vec<ElementRef<'a>> {
+		let mut descendants = Vec::new();
+		let mut stack = self
+			.node()
+			.children
+			.iter()
+			.rev()
+			.copied()
+			.collect::<Vec<_>>();
+
+		while let Some(id) = stack.pop() {
+			let node = &self.document.nodes[id];
+			if let NodeData::Element { .. } = node.data {
+				descendants.push(ElementRef {
+					document: self.document,
+					id,
+				});
+				stack.extend(node.children.iter().rev().copied());
+			}
+		}
+
+		descendants
+	}
+
+	/// Descendant elements matching a selector made of an optional tag name
+	/// followed by any number of `.class` and `#id` parts
+	pub fn select(&self, selector: &str) -> Vec<ElementRef<'a>> {
+		let selector = Selector::parse(selector);
+
+		self
+			.descendants()
+			.into_iter()
+			.filter(|element| selector.matches(element))
+			.collect()
+	}
+
+	pub fn select_first(&self, selector: &str) -> Option<ElementRef<'a>> {
+		self.select(selector).into_iter().next()
+	}
+
+	/// All text inside the element with `<br>` turned into line breaks
+	pub fn text(&self) -> String {
+		let mut text = String::new();
+		self.collect_text(self.id, &mut text);
+		text
+	}
+
+	fn collect_text(&self, id: usize, text: &mut String) {
+		let node = &self.document.nodes[id];
+
+		match &node.data {
+			NodeData::Text(content) => text.push_str(content),
+			NodeData::Element { name, .. } if name == "br" => text.push('\n'),
+			NodeData::Element { .. } => {
+				for child in &node.children {
+					self.collect_text(*child, text);
+				}
+			}
+		}
+	}
+}
+
+struct Selector<'s> {
+	name: Option<&'s str>,
+	classes: Vec<&'s str>,
+	id: Option<&'s str>,
+}
+
+impl<'s> Selector<'s> {
+	fn parse(selector: &'s str) -> Self {
+		let mut parsed = Self {
+			name: None,
+			classes: Vec::new(),
+			id: None,
+		};
+
+		let name_end = selector.find(['.', '#']).unwrap_or(selector.len());
+		if name_end > 0 {
+			parsed.name = Some(&selector[..name_end]);
+		}
+
+		let mut rest = &selector[name_end..];
+		while let Some(kind) = rest.chars().next() {
+			let part_end = rest[1..].find(['.', '#']).map_or(rest.len(), |end| end + 1);
+			let part = &rest[1..part_end];
+
+			match kind {
+				'.' => parsed.classes.push(part),
+				_ => parsed.id = Some(part),
+			}
+
+			rest = &rest[part_end..];
+		}
+
+		parsed
+	}
+
+	fn matches(&self, element: &ElementRef) -> bool {
+		self
+			.name
+			.is_none_or(|name| element.name().eq_ignore_ascii_case(name))
+			&& self.classes.iter().all(|class| element.has_class(class))
+			&& self.id.is_none_or(|id| element.attr("id") == Some(id))
+	}
+}
+
+struct TreeBuilder {
+	nodes: Vec<Node>,
+	open_elements: Vec<usize>,
+}
+
+impl TreeBuilder {
+	fn new() -> Self {
+		Self {
+			nodes: vec![Node {
+				data: NodeData::Element {
+					name: String::new(),
+					attributes: Vec::new(),
+				},
+				children: Vec::new(),
+			}],
+			open_elements: vec![0],
+		}
+	}
+
+	fn current(&self) -> usize {
+		*self.open_elements.last().unwrap()
+	}
+
+	fn current_name(&self) -> &str {
+		match &self.nodes[self.current()].data {
+			NodeData::Element { name, .. } => name,
+			NodeData::Text(_) => "",
+		}
+	}
+
+	fn append(&mut self, data: NodeData) -> usize {
+		let id = self.nodes.len();
+		self.nodes.push(Node {
+			data,
+			children: Vec::new(),
+		});
+
+		let parent = self.current();
+		self.nodes[parent].children.push(id);
+
+		id
+	}
+
+	fn push_text(&mut self, text: String) {
+		if !text.is_empty() {
+			self.append(NodeData::Text(text));
+		}
+	}
+
+	fn open(&mut self, name: String, attributes: Vec<(String, String)>) {
+		// Paragraphs and list items can't contain blocks or each other, so
+		// their end tags are optional
+		if (self.current_name() == "p" && BLOCK_ELEMENTS.contains(&name.as_str()))
+			|| (self.current_name() == "li" && name == "li")
+		{
+			self.pop();
+		}
+
+		let id = self.append(NodeData::Element { name, attributes });
+		self.open_elements.push(id);
+	}
+
+	fn pop(&mut self) {
+		if self.open_elements.len() > 1 {
+			self.open_elements.pop();
+		}
+	}
+
+	/// Closes the innermost open element called `name`, and any left open
+	/// inside it. End tags without an open element are ignored.
+	fn close(&mut self, name: &str) {
+		// Browsers read `</br>` as `<br>`
+		if name == "br" {
+			self.open(name.to_string(), Vec::new());
+			self.pop();
+			return;
+		}
+
+		let position = self.open_elements.iter().skip(1).rposition(|id| {
+			matches!(&self.nodes[*id].data, NodeData::Element { name: open_name, .. } if open_name == name)
+		});
+
+		if let Some(position) = position {
+			self.open_elements.truncate(position + 1);
+		}
+	}
+}
+
+struct StartTag {
+	name: String,
+	attributes: Vec<(String, String)>,
+	self_closing: bool,
+}
+
+/// Parses a start tag from just after its `<`, returning the rest of the
+/// input after its `>`
+fn parse_start_tag(input: &str) -> (StartTag, &str) {
+	let name_end = input
+		.find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+		.unwrap_or(input.len());
+
+	let mut tag = StartTag {
+		name: input[..name_end].to_ascii_lowercase(),
+		attributes: Vec::new(),
+		self_closing: false,
+	};
+
+	let mut rest = &input[name_end..];
+
+	loop {
+		rest = rest.trim_start();
+
+		if let Some(after_tag) = rest.strip_prefix('>') {
+			return (tag, after_tag);
+		}
+		if let Some(after_slash) = rest.strip_prefix('/') {
+			tag.self_closing = after_slash.starts_with('>');
+			rest = after_slash;
+			continue;
+		}
+		if rest.is_empty() {
+			return (tag, rest);
+		}
+
+		let attribute_end = rest
+			.find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+			.unwrap_or(rest.len())
+			.max(1);
+		let attribute = rest[..attribute_end].to_ascii_lowercase();
+		rest = rest[attribute_end..].trim_start();
+
+		let mut value = String::new();
+		if let Some(after_equals) = rest.strip_prefix('=') {
+			rest = after_equals.trim_start();
+
+			let (raw_value, after_value) = match rest.chars().next() {
+				Some(quote @ ('"' | '\'')) => {
+					let quoted = &rest[1..];
+					match quoted.find(quote) {
+						Some(value_end) => (&quoted[..value_end], &quoted[value_end + 1..]),
+						None => (quoted, ""),
+					}
+				}
+				_ => {
+					let value_end = rest
+						.find(|c: char| c.is_ascii_whitespace() || c == '>')
+						.unwrap_or(rest.len());
+					(&rest[..value_end], &rest[value_end..])
+				}
+			};
+
+			value = decode_entities(raw_value);
+			rest = after_value;
+		}
+
+		tag.attributes.push((attribute, value));
+	}
+}
+
+/// Splits raw text from the end tag `</name>` that closes it
+fn split_raw_text<'i>(input: &'i str, name: &str) -> (&'i str, &'i str) {
+	let end_tag = format!("</{}", name);
+	let lowercase = input.to_ascii_lowercase();
+
+	match lowercase.find(&end_tag) {
+		Some(text_end) => {
+			let after_end_tag = input[text_end..]
+				.find('>')
+				.map_or("", |tag_end| &input[text_end + tag_end + 1..]);
+			(&input[..text_end], after_end_tag)
+		}
+		None => (input, ""),
+	}
+}
+
+/// Replaces character references like `&amp;`, `&#39;` and `&#x1F600;`.
+/// Unknown references are left as they are.
+pub fn decode_entities(text: &str) -> String {
+	let mut decoded = String::with_capacity(text.len());
+	let mut rest = text;
+
+	while let Some(start) = rest.find('&') {
+		decoded.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let reference = rest[1..]
+			.find(';')
+			.filter(|end| *end <= 32)
+			.and_then(|end| Some((decode_reference(&rest[1..end + 1])?, end + 2)));
+
+		match reference {
+			Some((character, length)) => {
+				decoded.push(character);
+				rest = &rest[length..];
+			}
+			None => {
+				decoded.push('&');
+				rest = &rest[1..];
+			}
+		}
+	}
+
+	decoded.push_str(rest);
+	decoded
+}
+
+fn decode_reference(reference: &str) -> Option<char> {
+	if let Some(number) = reference.strip_prefix('#') {
+		let code_point = match number.strip_prefix(['x', 'X']) {
+			Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+			None => number.parse().ok()?,
+		};
+
+		return char::from_u32(code_point);
+	}
+
+	let character = match reference {
+		"amp" => '&',
+		"lt" => '<',
+		"gt" => '>',
+		"quot" => '"',
+		"apos" => '\'',
+		"nbsp" => '\u{a0}',
+		"hellip" => '…',
+		"mdash" => '—',
+		"ndash" => '–',
+		"lsquo" => '‘',
+		"rsquo" => '’',
+		"ldquo" => '“',
+		"rdquo" => '”',
+		"bull" => '•',
+		"middot" => '·',
+		"copy" => '©',
+		"reg" => '®',
+		"trade" => '™',
+		"deg" => '°',
+		"times" => '×',
+		"hearts" => '♥',
+		"eacute" => 'é',
+		"egrave" => 'è',
+		"aacute" => 'á',
+		"agrave" => 'à',
+		"iacute" => 'í',
+		"oacute" => 'ó',
+		"uacute" => 'ú',
+		"ntilde" => 'ñ',
+		"uuml" => 'ü',
+		"ouml" => 'ö',
+		"auml" => 'ä',
+		"szlig" => 'ß',
+		_ => return None,
+	};
+
+	Some(character)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode_entities, Document};
+
+	#[test]
+	fn test_decode_entities() {
+		assert_eq!(
+			decode_entities("Tom &amp; Jerry&#39;s &lt;3 &#x1F600; &unknown; & more"),
+			"Tom & Jerry's <3 😀 &unknown; & more"
+		);
+	}
+
+	#[test]
+	fn test_select_and_text() {
+		let document = Document::parse(
+			"<!DOCTYPE html><html><body>\
+			<!-- <div class=\"item\">commented out</div> -->\
+			<div class=\"item first\" id=\"a\"><a href='/one?a=1&amp;b=2'>One</a></br><span>Line 1\nLine 2</span></div>\
+			<div class=item><p>Unclosed<p>Second &quot;paragraph&quot;</div></span>\
+			<script>if (a < b) { document.write(\"<div class='item'>\") }</script>\
+			</body></html>",
+		);
+
+		let items = document.select("div.item");
+		assert_eq!(items.len(), 2);
+		assert!(items[0].has_class("first"));
+		assert_eq!(document.select_first("#a").unwrap().name(), "div");
+		assert_eq!(
+			items[0].select_first("a").unwrap().attr("href"),
+			Some("/one?a=1&b=2")
+		);
+		assert_eq!(items[0].text(), "One\nLine 1\nLine 2");
+
+		let paragraphs = items[1].select("p");
+		assert_eq!(paragraphs.len(), 2);
+		assert_eq!(paragraphs[1].text(), "Second \"paragraph\"");
+
+		assert!(document
+			.select_first("script")
+			.unwrap()
+			.text()
+			.contains("<div class='item'>"));
+	}
+}
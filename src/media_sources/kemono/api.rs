@@ -0,0 +1,78 @@
+//! Response shapes of the kemono.su / coomer.su JSON API.
+
+use serde::Deserialize;
+
+/// Number of posts the creator feed returns per page.
+pub const POSTS_PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiPost {
+	pub id: String,
+	pub user: String,
+	pub service: String,
+	pub title: Option<String>,
+	pub content: Option<String>,
+	pub published: Option<chrono::NaiveDateTime>,
+	pub edited: Option<chrono::NaiveDateTime>,
+	/// Posts without a main file have an empty object here
+	#[serde(default)]
+	pub file: ApiFile,
+	#[serde(default)]
+	pub attachments: Vec<ApiFile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiFile {
+	pub name: Option<String>,
+	pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiPostResponse {
+	pub post: ApiPost,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiCreatorProfile {
+	pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ApiPost, ApiPostResponse};
+
+	#[test]
+	fn test_parse_creator_posts() {
+		let json = include_str!("../../../test/fixtures/http/coomer/onlyfans/heidiv/index.json");
+
+		let posts: Vec<ApiPost> = serde_json::from_str(json).unwrap();
+		assert_eq!(posts.len(), 50);
+
+		assert_eq!(posts[0].id, "1725305245");
+		assert_eq!(posts[0].user, "heidiv");
+		assert_eq!(posts[0].service, "onlyfans");
+		assert_eq!(
+			posts[0].file.path.as_deref(),
+			Some("/d7/d1/d7d102695d89c53ecd2c14b22712b061967ca24ea8299bee2e3102d654deadb0.mp4")
+		);
+		assert!(posts[0].published.is_some());
+	}
+
+	#[test]
+	fn test_parse_post() {
+		let json =
+			include_str!("../../../test/fixtures/http/coomer/onlyfans/heidiv/posts/29526377.json");
+
+		let response: ApiPostResponse = serde_json::from_str(json).unwrap();
+		assert_eq!(response.post.id, "29526377");
+		assert_eq!(
+			response.post.title.as_deref(),
+			Some("preggy lady outfits  #pregnant #sfw")
+		);
+		assert_eq!(response.post.attachments.len(), 2);
+		assert_eq!(
+			response.post.file.name.as_deref(),
+			Some("592x800_a1e17321e02d4f69692a0232f261274f.jpg")
+		);
+	}
+}
@@ -0,0 +1,714 @@
+use std::{path::PathBuf, str::FromStr};
+
+use diesel::prelude::*;
+use log::{debug, error, warn};
+use reqwest::Url;
+
+use super::{
+	pointer::{PostPointer, ProfilePointer, ProviderDomain},
+	post::{KemonoAttachment, KemonoAttachmentRow, KemonoPostRow},
+};
+use crate::{
+	error::Error,
+	file_store::{FileStore, MediaBlob, StoredBlob},
+	media_sources::ProviderType,
+	media_types::{
+		build_match_query, tagged_items_subquery, MediaBlobPointer, MediaItem, MediaType,
+		SearchFilters, TaggedItem,
+	},
+	Context,
+};
+
+/// `/data` redirects to whichever file server holds the path.
+fn get_data_url(provider_domain: &ProviderDomain, path: &str) -> String {
+	format!("https://{}/data{}", provider_domain.as_str(), path)
+}
+
+/// Where a post's file lives on the Kemono/Coomer file servers.
+#[derive(Debug, Clone)]
+pub struct AttachmentPointer {
+	pub provider_domain: ProviderDomain,
+	pub path: String,
+	pub media_type: MediaType,
+}
+
+impl AttachmentPointer {
+	pub fn new(provider_domain: &ProviderDomain, attachment: &KemonoAttachment) -> Option<Self> {
+		let media_type =
+			MediaType::from_path(&attachment.path).or_else(|| MediaType::from_path(&attachment.name))?;
+
+		Some(Self {
+			provider_domain: provider_domain.clone(),
+			path: attachment.path.clone(),
+			media_type,
+		})
+	}
+}
+
+impl MediaBlobPointer for AttachmentPointer {
+	fn get_namespace(&self) -> String {
+		format!("kemono_{}", self.media_type.get_category())
+	}
+
+	fn get_extension(&self) -> String {
+		self.media_type.get_extension().to_string()
+	}
+
+	/// The path comes from the API, so it is checked before it's trusted
+	fn get_download_url(&self) -> Result<Url, Error> {
+		if !self.path.starts_with('/') {
+			return Err(Error::Parse(format!(
+				"Kemono attachment path {} isn't absolute",
+				self.path
+			)));
+		}
+
+		let url = get_data_url(&self.provider_domain, &self.path);
+		Url::parse(&url)
+			.map_err(|err| Error::Parse(format!("Invalid Kemono download URL {}: {}", url, err)))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredAttachment {
+	pub attachment_pointer: AttachmentPointer,
+	pub content_hash: String,
+	pub content_length: i64,
+}
+
+impl MediaBlob for StoredAttachment {
+	fn get_path(&self) -> PathBuf {
+		FileStore::get_relative_blob_path(
+			&self.attachment_pointer.get_namespace(),
+			&self.content_hash,
+			&self.attachment_pointer.get_extension(),
+		)
+	}
+
+	fn get_content_length(&self) -> i64 {
+		self.content_length
+	}
+
+	fn get_content_hash(&self) -> String {
+		self.content_hash.clone()
+	}
+}
+
+/// A downloadable file of a cataloged post, along with the post it belongs to.
+#[derive(Debug, Clone)]
+pub struct KemonoPostAttachment {
+	pub post: PostPointer,
+	pub post_title: String,
+	pub post_content: String,
+	pub name: String,
+	pub attachment_pointer: AttachmentPointer,
+	pub stored_attachment: Option<StoredAttachment>,
+}
+
+impl TryFrom<(&KemonoAttachmentRow, &KemonoPostRow)> for KemonoPostAttachment {
+	type Error = Error;
+
+	fn try_from(
+		(attachment_row, post_row): (&KemonoAttachmentRow, &KemonoPostRow),
+	) -> Result<Self, Self::Error> {
+		let Some(provider_domain) = ProviderDomain::from_str(&post_row.provider_domain) else {
+			return Err(Error::Database(format!(
+				"Unknown provider {}",
+				post_row.provider_domain
+			)));
+		};
+
+		let Some(media_type) = attachment_row.media_type.as_deref() else {
+			return Err(Error::Database(format!(
+				"Kemono attachment {} has no media type",
+				attachment_row.path
+			)));
+		};
+
+		let media_type = MediaType::from_str(media_type).map_err(Error::Database)?;
+
+		let attachment_pointer = AttachmentPointer {
+			provider_domain: provider_domain.clone(),
+			path: attachment_row.path.clone(),
+			media_type,
+		};
+
+		let stored_attachment = match (&attachment_row.content_hash, attachment_row.content_length) {
+			(Some(content_hash), Some(content_length)) => Some(StoredAttachment {
+				attachment_pointer: attachment_pointer.clone(),
+				content_hash: content_hash.clone(),
+				content_length,
+			}),
+			_ => None,
+		};
+
+		Ok(Self {
+			post: PostPointer {
+				creator: ProfilePointer {
+					provider_domain,
+					service_slug: post_row.service.clone(),
+					creator_id: post_row.creator_id.clone(),
+				},
+				post_id: post_row.post_id.clone(),
+			},
+			post_title: post_row.title.clone(),
+			post_content: post_row.content.clone(),
+			name: attachment_row.name.clone(),
+			attachment_pointer,
+			stored_attachment,
+		})
+	}
+}
+
+impl MediaItem for KemonoPostAttachment {
+	fn get_source(&self) -> ProviderType {
+		ProviderType::Kemono
+	}
+
+	fn get_type(&self) -> MediaType {
+		self.attachment_pointer.media_type
+	}
+
+	fn get_title(&self) -> String {
+		self.post_title.clone()
+	}
+
+	fn get_description(&self) -> String {
+		self.post_content.clone()
+	}
+
+	fn get_author(&self) -> String {
+		self.post.creator.creator_id.clone()
+	}
+
+	fn get_blob_pointer(&self) -> impl MediaBlobPointer {
+		self.attachment_pointer.clone()
+	}
+
+	async fn search(context: &mut Context, query: &str) -> Result<Vec<KemonoPostAttachment>, Error> {
+		let Some(match_query) = build_match_query(query) else {
+			return Ok(Vec::new());
+		};
+
+		let results = Self::search_ranked(context, Some(&match_query), &SearchFilters::default())
+			.await?
+			.into_iter()
+			.map(|(attachment, _)| attachment)
+			.collect();
+
+		Ok(results)
+	}
+}
+
+#[derive(QueryableByName)]
+struct RankedPostRow {
+	#[diesel(embed)]
+	post_row: KemonoPostRow,
+	#[diesel(sql_type = diesel::sql_types::Double)]
+	score: f64,
+}
+
+impl KemonoPostAttachment {
+	/// Runs an FTS5 `match_query` (see `build_match_query`) over post titles
+	/// and contents. Each downloadable file of a matching post is returned
+	/// with the post's BM25 score, best match first. Without a query every
+	/// post passing the filters matches with a score of 0.
+	pub async fn search_ranked(
+		context: &mut Context,
+		match_query: Option<&str>,
+		filters: &SearchFilters,
+	) -> Result<Vec<(KemonoPostAttachment, f64)>, Error> {
+		use crate::schema::kemono_attachments;
+		use diesel::sql_types::{BigInt, Text, Timestamp};
+
+		let mut conditions = Vec::new();
+
+		let select = match match_query {
+			Some(_) => {
+				conditions.push("`kemono_posts_search` MATCH ?".to_string());
+
+				"SELECT `kemono_posts`.*, \
+					bm25(`kemono_posts_search`, 0.0, 0.0, 0.0, 10.0, 1.0) AS `score` \
+				FROM `kemono_posts_search` \
+				JOIN `kemono_posts` ON `kemono_posts`.`rowid` = `kemono_posts_search`.`rowid`"
+			}
+			None => "SELECT `kemono_posts`.*, 0.0 AS `score` FROM `kemono_posts`",
+		};
+
+		if !filters.tags.is_empty() {
+			// Same format as `TaggedItem::kemono_post`
+			conditions.push(format!(
+				"(`kemono_posts`.`service` || '/' || `kemono_posts`.`creator_id` || '/' || `kemono_posts`.`post_id`) IN ({})",
+				tagged_items_subquery(filters.tags.len())
+			));
+		}
+		if filters.author.is_some() {
+			conditions.push("`kemono_posts`.`creator_id` = ?".to_string());
+		}
+		match filters.is_deleted {
+			Some(true) => conditions.push("`kemono_posts`.`deleted_at` IS NOT NULL".to_string()),
+			Some(false) => conditions.push("`kemono_posts`.`deleted_at` IS NULL".to_string()),
+			None => {}
+		}
+		if filters.since.is_some() {
+			conditions.push(
+				"COALESCE(`kemono_posts`.`published_at`, `kemono_posts`.`created_at`) >= ?".to_string(),
+			);
+		}
+		if filters.until.is_some() {
+			conditions.push(
+				"COALESCE(`kemono_posts`.`published_at`, `kemono_posts`.`created_at`) < ?".to_string(),
+			);
+		}
+
+		// Skip posts without any files that pass the filters, so they don't
+		// count against the limit
+		let mut attachment_conditions = vec!["`media_type` IS NOT NULL"];
+		if filters.media_type.is_some() {
+			attachment_conditions.push("`media_type` = ?");
+		}
+		match filters.is_downloaded {
+			Some(true) => attachment_conditions.push("`content_hash` IS NOT NULL"),
+			Some(false) => attachment_conditions.push("`content_hash` IS NULL"),
+			None => {}
+		}
+		conditions.push(format!(
+			"EXISTS (SELECT 1 FROM `kemono_attachments` \
+				WHERE `kemono_attachments`.`service` = `kemono_posts`.`service` \
+				AND `kemono_attachments`.`creator_id` = `kemono_posts`.`creator_id` \
+				AND `kemono_attachments`.`post_id` = `kemono_posts`.`post_id` \
+				AND {})",
+			attachment_conditions.join(" AND ")
+		));
+
+		let sql = format!(
+			"{} WHERE {} \
+			ORDER BY `score`, `kemono_posts`.`service`, `kemono_posts`.`creator_id`, `kemono_posts`.`post_id` \
+			LIMIT ?",
+			select,
+			conditions.join(" AND ")
+		);
+
+		// Bound in the same order as the conditions above
+		let mut query = diesel::sql_query(sql).into_boxed();
+
+		if let Some(match_query) = match_query {
+			query = query.bind::<Text, _>(match_query.to_string());
+		}
+		if !filters.tags.is_empty() {
+			query = query.bind::<Text, _>(TaggedItem::KEMONO_SOURCE);
+			for tag in &filters.tags {
+				query = query.bind::<Text, _>(tag.clone());
+			}
+		}
+		if let Some(author) = &filters.author {
+			query = query.bind::<Text, _>(author.clone());
+		}
+		if let Some(since) = filters.since {
+			query = query.bind::<Timestamp, _>(since);
+		}
+		if let Some(until) = filters.until {
+			query = query.bind::<Timestamp, _>(until);
+		}
+		if let Some(media_type) = filters.media_type {
+			query = query.bind::<Text, _>(media_type.to_string());
+		}
+
+		let ranked_posts = query
+			.bind::<BigInt, _>(filters.get_limit())
+			.load::<RankedPostRow>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to search Kemono posts: {}", err)))?;
+
+		let post_ids = ranked_posts
+			.iter()
+			.map(|ranked| ranked.post_row.post_id.clone())
+			.collect::<Vec<_>>();
+
+		let mut attachment_query = kemono_attachments::table
+			.filter(kemono_attachments::post_id.eq_any(post_ids))
+			.filter(kemono_attachments::media_type.is_not_null())
+			.into_boxed();
+
+		if let Some(media_type) = filters.media_type {
+			attachment_query =
+				attachment_query.filter(kemono_attachments::media_type.eq(media_type.to_string()));
+		}
+		match filters.is_downloaded {
+			Some(true) => {
+				attachment_query = attachment_query.filter(kemono_attachments::content_hash.is_not_null())
+			}
+			Some(false) => {
+				attachment_query = attachment_query.filter(kemono_attachments::content_hash.is_null())
+			}
+			None => {}
+		}
+
+		let attachment_rows = attachment_query
+			.order(kemono_attachments::position)
+			.select(KemonoAttachmentRow::as_select())
+			.load::<KemonoAttachmentRow>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load Kemono attachments: {}", err)))?;
+
+		let results = ranked_posts
+			.iter()
+			.flat_map(|ranked| {
+				attachment_rows
+					.iter()
+					.filter(|attachment_row| {
+						attachment_row.service == ranked.post_row.service
+							&& attachment_row.creator_id == ranked.post_row.creator_id
+							&& attachment_row.post_id == ranked.post_row.post_id
+					})
+					.filter_map(|attachment_row| {
+						KemonoPostAttachment::try_from((attachment_row, &ranked.post_row))
+							.inspect_err(|err| warn!("Skipping attachment in search results: {}", err))
+							.ok()
+							.map(|attachment| (attachment, ranked.score))
+					})
+			})
+			.collect::<Vec<_>>();
+
+		Ok(results)
+	}
+
+	/// `service/creator/post/name`, for log output and reports.
+	pub fn get_label(&self) -> String {
+		format!(
+			"{}/{}/{}/{}",
+			self.post.creator.service_slug, self.post.creator.creator_id, self.post.post_id, self.name
+		)
+	}
+
+	/// Points the attachment at its content-addressed file and saves the
+	/// hash and length. The post's metadata is left as it is.
+	pub async fn set_stored_attachment(
+		&mut self,
+		blob: &StoredBlob,
+		context: &mut Context,
+	) -> Result<(), Error> {
+		self
+			.update_row(
+				context,
+				Some(blob.content_hash.clone()),
+				Some(blob.content_length),
+			)
+			.map_err(|err| Error::Database(format!("Failed to update Kemono attachment: {}", err)))?;
+
+		self.stored_attachment = Some(StoredAttachment {
+			attachment_pointer: self.attachment_pointer.clone(),
+			content_hash: blob.content_hash.clone(),
+			content_length: blob.content_length,
+		});
+		debug!("Kemono attachment updated: {}", self.get_label());
+
+		Ok(())
+	}
+
+	/// Forgets the stored file so the next download run fetches it again.
+	pub async fn clear_stored_attachment(&mut self, context: &mut Context) -> Result<(), Error> {
+		self
+			.update_row(context, None, None)
+			.map_err(|err| Error::Database(format!("Failed to reset Kemono attachment: {}", err)))?;
+
+		self.stored_attachment = None;
+
+		Ok(())
+	}
+
+	fn update_row(
+		&self,
+		context: &mut Context,
+		new_content_hash: Option<String>,
+		new_content_length: Option<i64>,
+	) -> QueryResult<usize> {
+		use crate::schema::kemono_attachments::dsl::*;
+
+		diesel::update(
+			kemono_attachments
+				.filter(service.eq(&self.post.creator.service_slug))
+				.filter(creator_id.eq(&self.post.creator.creator_id))
+				.filter(post_id.eq(&self.post.post_id))
+				.filter(path.eq(&self.attachment_pointer.path)),
+		)
+		.set((
+			media_type.eq(self.attachment_pointer.media_type.to_string()),
+			content_hash.eq(new_content_hash),
+			content_length.eq(new_content_length),
+		))
+		.execute(&mut context.conn)
+	}
+
+	/// Attachments of known type that have been cataloged but aren't stored
+	/// yet, optionally limited to one creator.
+	pub async fn find_undownloaded(
+		context: &mut Context,
+		filter_creator_id: Option<&str>,
+	) -> Result<Vec<KemonoPostAttachment>, Error> {
+		use crate::schema::{kemono_attachments, kemono_posts};
+
+		let mut query = kemono_attachments::table
+			.inner_join(
+				kemono_posts::table.on(
+					kemono_posts::service
+						.eq(kemono_attachments::service)
+						.and(kemono_posts::creator_id.eq(kemono_attachments::creator_id))
+						.and(kemono_posts::post_id.eq(kemono_attachments::post_id)),
+				),
+			)
+			.filter(kemono_attachments::content_hash.is_null())
+			.filter(kemono_attachments::media_type.is_not_null())
+			.into_boxed();
+
+		if let Some(filter_creator_id) = filter_creator_id {
+			query = query.filter(kemono_attachments::creator_id.eq(filter_creator_id.to_string()));
+		}
+
+		let rows = query
+			.order((
+				kemono_attachments::service,
+				kemono_attachments::creator_id,
+				kemono_attachments::post_id,
+				kemono_attachments::position,
+			))
+			.select((KemonoAttachmentRow::as_select(), KemonoPostRow::as_select()))
+			.load::<(KemonoAttachmentRow, KemonoPostRow)>(&mut context.conn)
+			.map_err(|err| {
+				Error::Database(format!(
+					"Failed to load undownloaded Kemono attachments: {}",
+					err
+				))
+			})?;
+
+		Ok(Self::from_rows(rows))
+	}
+
+	/// Attachments that have a file recorded in the store.
+	pub async fn find_downloaded(context: &mut Context) -> Vec<KemonoPostAttachment> {
+		use crate::schema::{kemono_attachments, kemono_posts};
+
+		let query = kemono_attachments::table
+			.inner_join(
+				kemono_posts::table.on(
+					kemono_posts::service
+						.eq(kemono_attachments::service)
+						.and(kemono_posts::creator_id.eq(kemono_attachments::creator_id))
+						.and(kemono_posts::post_id.eq(kemono_attachments::post_id)),
+				),
+			)
+			.filter(kemono_attachments::content_hash.is_not_null())
+			.into_boxed();
+
+		let rows = query
+			.order((
+				kemono_attachments::service,
+				kemono_attachments::creator_id,
+				kemono_attachments::post_id,
+				kemono_attachments::position,
+			))
+			.select((KemonoAttachmentRow::as_select(), KemonoPostRow::as_select()))
+			.load::<(KemonoAttachmentRow, KemonoPostRow)>(&mut context.conn)
+			.unwrap_or_else(|err| {
+				error!("Failed to load downloaded Kemono attachments: {}", err);
+				Vec::new()
+			});
+
+		Self::from_rows(rows)
+	}
+
+	/// Fills in the media type of attachments whose name didn't give it
+	/// away by asking the server for the `Content-Type`. Attachments of
+	/// unsupported types are left untyped and never downloaded, and aren't
+	/// asked about again. Returns the number of attachments that were
+	/// detected.
+	pub async fn detect_media_types(context: &mut Context) -> usize {
+		use crate::schema::{kemono_attachments, kemono_posts};
+
+		let rows = kemono_attachments::table
+			.inner_join(
+				kemono_posts::table.on(
+					kemono_posts::service
+						.eq(kemono_attachments::service)
+						.and(kemono_posts::creator_id.eq(kemono_attachments::creator_id))
+						.and(kemono_posts::post_id.eq(kemono_attachments::post_id)),
+				),
+			)
+			.filter(kemono_attachments::media_type.is_null())
+			.filter(kemono_attachments::media_type_checked_at.is_null())
+			.filter(kemono_attachments::content_hash.is_null())
+			.select((
+				KemonoAttachmentRow::as_select(),
+				kemono_posts::provider_domain,
+			))
+			.load::<(KemonoAttachmentRow, String)>(&mut context.conn)
+			.unwrap_or_else(|err| {
+				error!("Failed to load untyped Kemono attachments: {}", err);
+				Vec::new()
+			});
+
+		let mut detected = 0;
+
+		for (row, row_provider_domain) in rows {
+			let Some(provider_domain) = ProviderDomain::from_str(&row_provider_domain) else {
+				continue;
+			};
+
+			let attachment = KemonoAttachment {
+				name: row.name.clone(),
+				path: row.path.clone(),
+			};
+
+			let media_type = match AttachmentPointer::new(&provider_domain, &attachment) {
+				Some(pointer) => Some(pointer.media_type),
+				None => match context
+					.http
+					.fetch_content_type(
+						ProviderType::Kemono,
+						&get_data_url(&provider_domain, &row.path),
+					)
+					.await
+				{
+					Ok(content_type) => content_type
+						.as_deref()
+						.and_then(MediaType::from_content_type),
+					// Asked again next time, the server might answer then
+					Err(err) => {
+						error!("Failed to detect type of {}: {}", row.path, err);
+						continue;
+					}
+				},
+			};
+
+			if media_type.is_none() {
+				debug!(
+					"Skipping Kemono attachment of unsupported type: {}",
+					row.name
+				);
+			}
+
+			let result = {
+				use crate::schema::kemono_attachments::dsl;
+
+				diesel::update(
+					dsl::kemono_attachments
+						.filter(dsl::service.eq(&row.service))
+						.filter(dsl::creator_id.eq(&row.creator_id))
+						.filter(dsl::post_id.eq(&row.post_id))
+						.filter(dsl::path.eq(&row.path)),
+				)
+				.set((
+					dsl::media_type.eq(media_type.map(|media_type| media_type.to_string())),
+					dsl::media_type_checked_at.eq(chrono::Utc::now().naive_utc()),
+				))
+				.execute(&mut context.conn)
+			};
+
+			match result {
+				Ok(_) if media_type.is_some() => detected += 1,
+				Ok(_) => {}
+				Err(err) => error!("Failed to save type of {}: {}", row.path, err),
+			}
+		}
+
+		detected
+	}
+
+	fn from_rows(rows: Vec<(KemonoAttachmentRow, KemonoPostRow)>) -> Vec<KemonoPostAttachment> {
+		rows
+			.iter()
+			.filter_map(|(attachment_row, post_row)| {
+				KemonoPostAttachment::try_from((attachment_row, post_row))
+					.inspect_err(|err| warn!("Skipping Kemono attachment: {}", err))
+					.ok()
+			})
+			.collect::<Vec<_>>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use diesel::RunQueryDsl;
+	use httpmock::{Method::HEAD, MockServer};
+
+	use super::{AttachmentPointer, KemonoPostAttachment};
+	use crate::config::Config;
+	use crate::context::test_context;
+	use crate::error::Error;
+	use crate::http_client::HttpClient;
+	use crate::media_sources::kemono::{pointer::ProviderDomain, post::KemonoAttachment};
+	use crate::media_types::{MediaBlobPointer, MediaType};
+
+	#[test]
+	fn test_attachment_pointer() {
+		let attachment = KemonoAttachment {
+			name: "clip.MP4".to_string(),
+			path: "/d7/d1/d7d1a2f3.mp4".to_string(),
+		};
+
+		let pointer = AttachmentPointer::new(&ProviderDomain::Coomer, &attachment).unwrap();
+		assert_eq!(pointer.media_type, MediaType::VideoMp4);
+		assert_eq!(pointer.get_namespace(), "kemono_video");
+		assert_eq!(pointer.get_extension(), "mp4");
+		assert_eq!(
+			pointer.get_download_url().unwrap().as_str(),
+			"https://coomer.su/data/d7/d1/d7d1a2f3.mp4"
+		);
+
+		// A path without the leading slash would run into `/data`
+		let relative = KemonoAttachment {
+			name: "clip.mp4".to_string(),
+			path: "d7/d1/d7d1a2f3.mp4".to_string(),
+		};
+		let pointer = AttachmentPointer::new(&ProviderDomain::Coomer, &relative).unwrap();
+		assert!(matches!(pointer.get_download_url(), Err(Error::Parse(_))));
+
+		let archive = KemonoAttachment {
+			name: "pack.zip".to_string(),
+			path: "/aa/bb/aabbcc.zip".to_string(),
+		};
+		assert!(AttachmentPointer::new(&ProviderDomain::Kemono, &archive).is_none());
+	}
+
+	#[tokio::test]
+	async fn test_detect_media_types_once() {
+		let server = MockServer::start_async().await;
+		let video = server.mock(|when, then| {
+			when.method(HEAD).path("/data/aa/bb/aabbcc");
+			then.status(200).header("content-type", "video/mp4");
+		});
+		let archive = server.mock(|when, then| {
+			when.method(HEAD).path("/data/dd/ee/ddeeff");
+			then.status(200).header("content-type", "application/zip");
+		});
+
+		let mut context = test_context();
+		let mut config = Config::new();
+		config.base_urls.kemono = server.base_url();
+		context.http = HttpClient::new(&config).unwrap();
+
+		diesel::sql_query(
+			"INSERT INTO kemono_posts (provider_domain, service, creator_id, post_id, title, content, created_at, updated_at) \
+			VALUES ('kemono.su', 'patreon', '1', '2', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+		diesel::sql_query(
+			"INSERT INTO kemono_attachments (service, creator_id, post_id, path, name, position, created_at) VALUES \
+			('patreon', '1', '2', '/aa/bb/aabbcc', 'clip', 0, '2026-10-18 00:00:00'), \
+			('patreon', '1', '2', '/dd/ee/ddeeff', 'pack', 1, '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		assert_eq!(
+			KemonoPostAttachment::detect_media_types(&mut context).await,
+			1
+		);
+		// The unsupported attachment isn't asked about again
+		assert_eq!(
+			KemonoPostAttachment::detect_media_types(&mut context).await,
+			0
+		);
+		video.assert_hits(1);
+		archive.assert_hits(1);
+	}
+}
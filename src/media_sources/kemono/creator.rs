@@ -0,0 +1,67 @@
+use diesel::prelude::*;
+use log::debug;
+
+use super::pointer::ProfilePointer;
+use crate::{error::Error, Context};
+
+#[derive(Debug, Clone, Selectable, Insertable, Queryable)]
+#[diesel(table_name = crate::schema::kemono_creators)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct KemonoCreatorRow {
+	pub provider_domain: String,
+	pub service: String,
+	pub creator_id: String,
+	pub name: String,
+	pub created_at: chrono::NaiveDateTime,
+	pub updated_at: chrono::NaiveDateTime,
+	pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+impl KemonoCreatorRow {
+	pub fn new(pointer: &ProfilePointer, name: Option<String>) -> Self {
+		Self {
+			provider_domain: pointer.provider_domain.as_str().to_string(),
+			service: pointer.service_slug.clone(),
+			creator_id: pointer.creator_id.clone(),
+			name: name.unwrap_or_else(|| pointer.creator_id.clone()),
+			created_at: chrono::Utc::now().naive_utc(),
+			updated_at: chrono::Utc::now().naive_utc(),
+			deleted_at: None,
+		}
+	}
+
+	/// Inserts the creator, or refreshes its name if it is already known.
+	pub async fn add_to_library(&self, context: &mut Context) -> Result<(), Error> {
+		use crate::schema::kemono_creators::dsl::*;
+		use diesel::upsert::excluded;
+
+		diesel::insert_into(kemono_creators)
+			.values(self)
+			.on_conflict((service, creator_id))
+			.do_update()
+			.set((name.eq(excluded(name)), updated_at.eq(excluded(updated_at))))
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to upsert Kemono creator: {}", err)))?;
+
+		debug!(
+			"Kemono creator upserted: {}/{}",
+			self.service, self.creator_id
+		);
+
+		Ok(())
+	}
+
+	/// Makes sure a row exists for the creator without overwriting a known name.
+	pub async fn ensure_in_library(&self, context: &mut Context) -> Result<(), Error> {
+		use crate::schema::kemono_creators::dsl::*;
+
+		diesel::insert_into(kemono_creators)
+			.values(self)
+			.on_conflict((service, creator_id))
+			.do_nothing()
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to add Kemono creator: {}", err)))?;
+
+		Ok(())
+	}
+}
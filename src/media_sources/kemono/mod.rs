@@ -1,3 +1,8 @@
+mod api;
+mod attachment;
+mod creator;
 mod pointer;
+mod post;
 
+pub use attachment::KemonoPostAttachment;
 pub use pointer::{PostPointer, ProfilePointer};
@@ -1,5 +1,22 @@
+use std::collections::HashSet;
+
+use log::{debug, info};
+
+use super::{
+	api::{ApiCreatorProfile, ApiPost, ApiPostResponse, POSTS_PAGE_SIZE},
+	attachment::AttachmentPointer,
+	creator::KemonoCreatorRow,
+	post::KemonoPost,
+};
+use crate::{
+	error::Error,
+	http_client::HttpClient,
+	media_sources::ProviderType,
+	media_types::{MediaBlobPointer, MediaMetadata, MediaPointer, MetadataVersion, TaggedItem},
+};
+
 #[derive(Debug, Clone)]
-enum ProviderDomain {
+pub enum ProviderDomain {
 	Kemono,
 	Coomer,
 }
@@ -52,6 +69,135 @@ impl PostPointer {
 	pub fn to_url(&self) -> String {
 		format!("{}/post/{}", self.creator.to_url(), self.post_id).to_string()
 	}
+
+	pub fn get_api_url(&self) -> String {
+		format!("{}/post/{}", self.creator.get_api_url(), self.post_id)
+	}
+
+	pub fn get_tagged_item(&self) -> TaggedItem {
+		TaggedItem::kemono_post(
+			&self.creator.service_slug,
+			&self.creator.creator_id,
+			&self.post_id,
+		)
+	}
+
+	pub async fn is_in_library(&self, context: &mut crate::Context) -> bool {
+		use crate::schema::kemono_posts::dsl::*;
+		use diesel::prelude::*;
+
+		let count = kemono_posts
+			.filter(service.eq(&self.creator.service_slug))
+			.filter(creator_id.eq(&self.creator.creator_id))
+			.filter(post_id.eq(&self.post_id))
+			.count()
+			.get_result::<i64>(&mut context.conn)
+			.unwrap_or_default();
+
+		count > 0
+	}
+
+	/// Every version of the post's title and content, oldest first. Empty if
+	/// the post isn't in the library.
+	pub async fn get_history(
+		&self,
+		context: &mut crate::Context,
+	) -> Result<Vec<MetadataVersion>, Error> {
+		use crate::schema::{kemono_post_revisions, kemono_posts};
+		use diesel::prelude::*;
+
+		let current = kemono_posts::table
+			.find((
+				&self.creator.service_slug,
+				&self.creator.creator_id,
+				&self.post_id,
+			))
+			.select((
+				kemono_posts::created_at,
+				kemono_posts::title,
+				kemono_posts::content,
+			))
+			.first::<(chrono::NaiveDateTime, String, String)>(&mut context.conn)
+			.optional()
+			.map_err(|err| Error::Database(format!("Failed to load Kemono post: {}", err)))?;
+
+		let Some((created_at, title, content)) = current else {
+			return Ok(Vec::new());
+		};
+
+		let revisions = kemono_post_revisions::table
+			.filter(kemono_post_revisions::service.eq(&self.creator.service_slug))
+			.filter(kemono_post_revisions::creator_id.eq(&self.creator.creator_id))
+			.filter(kemono_post_revisions::post_id.eq(&self.post_id))
+			.order(kemono_post_revisions::id)
+			.select((
+				kemono_post_revisions::title,
+				kemono_post_revisions::content,
+				kemono_post_revisions::replaced_at,
+			))
+			.load(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load Kemono post revisions: {}", err)))?;
+
+		Ok(MetadataVersion::build_history(
+			created_at,
+			revisions,
+			(title, content),
+		))
+	}
+
+	pub async fn fetch_post(&self, http: &HttpClient) -> Result<KemonoPost, Error> {
+		let response: ApiPostResponse = http
+			.fetch_json(ProviderType::Kemono, &self.get_api_url())
+			.await
+			.map_err(|err| {
+				err.with_message(format!(
+					"Failed to fetch Kemono post {}: {}",
+					self.to_url(),
+					err
+				))
+			})?;
+
+		Ok(KemonoPost::from_api(&self.creator, response.post))
+	}
+
+	pub async fn scan(&self, context: &mut crate::Context) -> Result<String, Error> {
+		let post = self.fetch_post(&context.http).await?;
+
+		// Keep the creator's name if a profile scan already found it
+		KemonoCreatorRow::new(&self.creator, None)
+			.ensure_in_library(context)
+			.await?;
+
+		post.add_to_library(context).await?;
+
+		Ok(format!(
+			"Added Kemono post {} with {} attachments to library",
+			self.post_id,
+			post.attachments.len()
+		))
+	}
+}
+
+impl MediaPointer for PostPointer {
+	async fn fetch_metadata(&self, http: &HttpClient) -> Vec<impl MediaMetadata> {
+		match self.fetch_post(http).await {
+			Ok(post) => vec![post],
+			Err(err) => {
+				log::error!("{}", err);
+				vec![]
+			}
+		}
+	}
+
+	/// The first file of the post that has a supported type
+	async fn fetch_blob_pointer(&self, http: &HttpClient) -> Option<impl MediaBlobPointer> {
+		let post = self.fetch_post(http).await.ok()?;
+
+		post
+			.attachments
+			.iter()
+			.find_map(|attachment| AttachmentPointer::new(&self.creator.provider_domain, attachment))
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -87,11 +233,194 @@ impl ProfilePointer {
 			self.creator_id
 		)
 	}
+
+	pub fn get_api_url(&self) -> String {
+		format!(
+			"https://{}/api/v1/{}/user/{}",
+			self.provider_domain.as_str(),
+			self.service_slug,
+			self.creator_id
+		)
+	}
+
+	pub fn get_posts_api_url(&self, offset: usize) -> String {
+		format!("{}/posts?o={}", self.get_api_url(), offset)
+	}
+
+	/// The creator's display name, falling back to their id if the profile can't be fetched.
+	pub async fn fetch_name(&self, http: &HttpClient) -> Option<String> {
+		let url = format!("{}/profile", self.get_api_url());
+
+		match http
+			.fetch_json::<ApiCreatorProfile>(ProviderType::Kemono, &url)
+			.await
+		{
+			Ok(profile) => Some(profile.name),
+			Err(err) => {
+				debug!("Failed to fetch Kemono creator profile: {}", err);
+				None
+			}
+		}
+	}
+
+	pub async fn fetch_posts_page(
+		&self,
+		http: &HttpClient,
+		offset: usize,
+	) -> Result<Vec<ApiPost>, Error> {
+		http
+			.fetch_json(ProviderType::Kemono, &self.get_posts_api_url(offset))
+			.await
+			.map_err(|err| {
+				err.with_message(format!(
+					"Failed to fetch Kemono posts of creator {}: {}",
+					self.creator_id, err
+				))
+			})
+	}
+
+	/// Pages through the creator's feed and adds every post to the library.
+	pub async fn scan(&self, context: &mut crate::Context) -> Result<String, Error> {
+		let name = self.fetch_name(&context.http).await;
+		KemonoCreatorRow::new(self, name)
+			.add_to_library(context)
+			.await?;
+
+		let mut offset = 0;
+		let mut post_count = 0;
+
+		loop {
+			let page = self.fetch_posts_page(&context.http, offset).await?;
+			let page_len = page.len();
+
+			debug!(
+				"Fetched {} Kemono posts at offset {} for {}",
+				page_len, offset, self.creator_id
+			);
+
+			for api_post in page {
+				let post = KemonoPost::from_api(self, api_post);
+				post.add_to_library(context).await?;
+				post_count += 1;
+			}
+
+			if page_len < POSTS_PAGE_SIZE {
+				break;
+			}
+
+			offset += page_len;
+		}
+
+		info!(
+			"Scanned {} Kemono posts for {}",
+			post_count, self.creator_id
+		);
+
+		Ok(format!(
+			"Added {} posts from Kemono creator {} to library",
+			post_count, self.creator_id
+		))
+	}
+
+	/// Adds the posts that aren't in the library yet. The feed is newest
+	/// first, so paging stops at the first page without anything new.
+	pub async fn sync(&self, context: &mut crate::Context) -> Result<Vec<KemonoPost>, Error> {
+		use crate::schema::kemono_posts::dsl::*;
+		use diesel::prelude::*;
+
+		let name = self.fetch_name(&context.http).await;
+		KemonoCreatorRow::new(self, name)
+			.add_to_library(context)
+			.await?;
+
+		let known_post_ids = kemono_posts
+			.filter(service.eq(&self.service_slug))
+			.filter(creator_id.eq(&self.creator_id))
+			.select(post_id)
+			.load::<String>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load known Kemono posts: {}", err)))?
+			.into_iter()
+			.collect::<HashSet<_>>();
+
+		let mut new_posts = Vec::new();
+		let mut offset = 0;
+
+		loop {
+			let page = self.fetch_posts_page(&context.http, offset).await?;
+			let page_len = page.len();
+			let mut has_new_posts = false;
+
+			for api_post in page {
+				if known_post_ids.contains(&api_post.id) {
+					continue;
+				}
+
+				let post = KemonoPost::from_api(self, api_post);
+				post.add_to_library(context).await?;
+				new_posts.push(post);
+				has_new_posts = true;
+			}
+
+			if !has_new_posts || page_len < POSTS_PAGE_SIZE {
+				break;
+			}
+
+			offset += page_len;
+		}
+
+		debug!(
+			"Found {} new Kemono posts for {}",
+			new_posts.len(),
+			self.creator_id
+		);
+
+		Ok(new_posts)
+	}
 }
 
 lazy_static::lazy_static! {
 	static ref PROFILE_URL_RE: regex::Regex =
-		regex::Regex::new(r"https://(kemono\.su|coomer\.su)/([^/]+?)/user/([^/]+?)/?").unwrap();
+		regex::Regex::new(r"https://(kemono\.su|coomer\.su)/([^/]+)/user/([^/?#]+)/?").unwrap();
 		static ref POST_URL_RE: regex::Regex =
-		regex::Regex::new(r"https://(kemono\.su|coomer\.su)/([^/]+?)/user/([^/]+?)/post/([^/]+?)/?").unwrap();
+		regex::Regex::new(r"https://(kemono\.su|coomer\.su)/([^/]+)/user/([^/]+)/post/([^/?#]+)/?").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PostPointer, ProfilePointer};
+
+	#[test]
+	fn test_parse_profile_pointer_from_url() {
+		let pointer = ProfilePointer::from_url("https://coomer.su/onlyfans/user/heidiv").unwrap();
+		assert_eq!(pointer.provider_domain.as_str(), "coomer.su");
+		assert_eq!(pointer.service_slug, "onlyfans");
+		assert_eq!(pointer.creator_id, "heidiv");
+		assert_eq!(
+			pointer.get_posts_api_url(50),
+			"https://coomer.su/api/v1/onlyfans/user/heidiv/posts?o=50"
+		);
+
+		// With trailing slash
+		let pointer = ProfilePointer::from_url("https://kemono.su/patreon/user/12345/").unwrap();
+		assert_eq!(pointer.provider_domain.as_str(), "kemono.su");
+		assert_eq!(pointer.creator_id, "12345");
+	}
+
+	#[test]
+	fn test_parse_post_pointer_from_url() {
+		let pointer =
+			PostPointer::from_url("https://coomer.su/onlyfans/user/heidiv/post/29526377").unwrap();
+		assert_eq!(pointer.creator.creator_id, "heidiv");
+		assert_eq!(pointer.post_id, "29526377");
+		assert_eq!(
+			pointer.get_api_url(),
+			"https://coomer.su/api/v1/onlyfans/user/heidiv/post/29526377"
+		);
+	}
+
+	#[test]
+	fn test_parse_invalid_kemono_pointer() {
+		assert!(ProfilePointer::from_url("https://example.com/onlyfans/user/heidiv").is_none());
+		assert!(PostPointer::from_url("https://coomer.su/onlyfans/user/heidiv").is_none());
+	}
 }
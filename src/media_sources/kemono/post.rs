@@ -0,0 +1,221 @@
+use diesel::prelude::*;
+use log::debug;
+
+use super::{api::ApiPost, pointer::ProfilePointer};
+use crate::{
+	error::Error,
+	media_types::{MediaMetadata, MediaType},
+	Context,
+};
+
+#[derive(Debug, Clone)]
+pub struct KemonoPost {
+	pub creator: ProfilePointer,
+	pub post_id: String,
+	pub title: String,
+	pub content: String,
+	pub published_at: Option<chrono::NaiveDateTime>,
+	pub edited_at: Option<chrono::NaiveDateTime>,
+	pub attachments: Vec<KemonoAttachment>,
+}
+
+/// A file attached to a post. `path` is the server path, e.g. `/d7/d1/<sha256>.mp4`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KemonoAttachment {
+	pub name: String,
+	pub path: String,
+}
+
+impl KemonoPost {
+	/// The main `file` is listed first, followed by the other attachments.
+	/// Kemono often repeats the main file as an attachment, so duplicate
+	/// paths are dropped.
+	pub fn from_api(creator: &ProfilePointer, post: ApiPost) -> Self {
+		let mut attachments: Vec<KemonoAttachment> = Vec::with_capacity(post.attachments.len() + 1);
+
+		for file in std::iter::once(post.file).chain(post.attachments) {
+			let Some(path) = file.path else {
+				continue;
+			};
+
+			if attachments.iter().any(|attachment| attachment.path == path) {
+				continue;
+			}
+
+			let name = file
+				.name
+				.unwrap_or_else(|| path.rsplit('/').next().unwrap_or_default().to_string());
+
+			attachments.push(KemonoAttachment { name, path });
+		}
+
+		Self {
+			creator: creator.clone(),
+			post_id: post.id,
+			title: post.title.unwrap_or_default(),
+			content: post.content.unwrap_or_default(),
+			published_at: post.published,
+			edited_at: post.edited,
+			attachments,
+		}
+	}
+
+	pub async fn add_to_library(&self, context: &mut Context) -> Result<(), Error> {
+		let row = KemonoPostRow::from(self);
+		row.add_to_library(context).await?;
+
+		let attachment_rows = self
+			.attachments
+			.iter()
+			.enumerate()
+			.map(|(position, attachment)| KemonoAttachmentRow {
+				service: self.creator.service_slug.clone(),
+				creator_id: self.creator.creator_id.clone(),
+				post_id: self.post_id.clone(),
+				path: attachment.path.clone(),
+				name: attachment.name.clone(),
+				position: position as i32,
+				created_at: chrono::Utc::now().naive_utc(),
+				media_type: MediaType::from_path(&attachment.path)
+					.or_else(|| MediaType::from_path(&attachment.name))
+					.map(|media_type| media_type.to_string()),
+				content_hash: None,
+				content_length: None,
+			})
+			.collect::<Vec<_>>();
+
+		KemonoAttachmentRow::add_to_library(&attachment_rows, context).await
+	}
+}
+
+impl MediaMetadata for KemonoPost {
+	fn get_title(&self) -> String {
+		self.title.clone()
+	}
+
+	fn get_description(&self) -> String {
+		self.content.clone()
+	}
+}
+
+#[derive(Debug, Clone, Selectable, Insertable, Queryable, QueryableByName)]
+#[diesel(table_name = crate::schema::kemono_posts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct KemonoPostRow {
+	pub provider_domain: String,
+	pub service: String,
+	pub creator_id: String,
+	pub post_id: String,
+	pub title: String,
+	pub content: String,
+	pub published_at: Option<chrono::NaiveDateTime>,
+	pub edited_at: Option<chrono::NaiveDateTime>,
+	pub created_at: chrono::NaiveDateTime,
+	pub updated_at: chrono::NaiveDateTime,
+	pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+impl KemonoPostRow {
+	pub async fn add_to_library(&self, context: &mut Context) -> Result<(), Error> {
+		use crate::schema::kemono_posts::dsl::*;
+		use diesel::upsert::excluded;
+
+		diesel::insert_into(kemono_posts)
+			.values(self)
+			.on_conflict((service, creator_id, post_id))
+			.do_update()
+			.set((
+				title.eq(excluded(title)),
+				content.eq(excluded(content)),
+				published_at.eq(excluded(published_at)),
+				edited_at.eq(excluded(edited_at)),
+				updated_at.eq(excluded(updated_at)),
+			))
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to upsert Kemono post: {}", err)))?;
+
+		debug!(
+			"Kemono post upserted: {}/{}/{}",
+			self.service, self.creator_id, self.post_id
+		);
+
+		Ok(())
+	}
+}
+
+impl From<&KemonoPost> for KemonoPostRow {
+	fn from(post: &KemonoPost) -> Self {
+		Self {
+			provider_domain: post.creator.provider_domain.as_str().to_string(),
+			service: post.creator.service_slug.clone(),
+			creator_id: post.creator.creator_id.clone(),
+			post_id: post.post_id.clone(),
+			title: post.title.clone(),
+			content: post.content.clone(),
+			published_at: post.published_at,
+			edited_at: post.edited_at,
+			created_at: chrono::Utc::now().naive_utc(),
+			updated_at: chrono::Utc::now().naive_utc(),
+			deleted_at: None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Selectable, Insertable, Queryable)]
+#[diesel(table_name = crate::schema::kemono_attachments)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct KemonoAttachmentRow {
+	pub service: String,
+	pub creator_id: String,
+	pub post_id: String,
+	pub path: String,
+	pub name: String,
+	pub position: i32,
+	pub created_at: chrono::NaiveDateTime,
+	/// `None` until the type has been detected from the extension or the
+	/// server's `Content-Type`
+	pub media_type: Option<String>,
+	pub content_hash: Option<String>,
+	pub content_length: Option<i64>,
+}
+
+impl KemonoAttachmentRow {
+	pub async fn add_to_library(rows: &[Self], context: &mut Context) -> Result<(), Error> {
+		use crate::schema::kemono_attachments::dsl::*;
+		use diesel::upsert::excluded;
+
+		for row in rows {
+			diesel::insert_into(kemono_attachments)
+				.values(row)
+				.on_conflict((service, creator_id, post_id, path))
+				.do_update()
+				.set((name.eq(excluded(name)), position.eq(excluded(position))))
+				.execute(&mut context.conn)
+				.map_err(|err| Error::Database(format!("Failed to upsert Kemono attachment: {}", err)))?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::KemonoPost;
+	use crate::media_sources::kemono::{api::ApiPostResponse, ProfilePointer};
+
+	#[test]
+	fn test_post_from_api() {
+		let json =
+			include_str!("../../../test/fixtures/http/coomer/onlyfans/heidiv/posts/29526377.json");
+		let response: ApiPostResponse = serde_json::from_str(json).unwrap();
+		let creator = ProfilePointer::from_url("https://coomer.su/onlyfans/user/heidiv").unwrap();
+
+		let post = KemonoPost::from_api(&creator, response.post);
+		assert_eq!(post.post_id, "29526377");
+		assert_eq!(post.attachments.len(), 3);
+		assert_eq!(
+			post.attachments[0].name,
+			"592x800_a1e17321e02d4f69692a0232f261274f.jpg"
+		);
+	}
+}
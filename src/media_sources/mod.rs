@@ -1,30 +1,21 @@
-use crate::common::USER_AGENT;
-
-mod kemono;
+pub mod html;
+pub mod kemono;
 pub mod soundgasm;
+mod subscription;
+
+pub use subscription::{Subscription, SubscriptionTarget};
 
 // use crate::media_types::MediaItem;
 
-#[derive(strum_macros::Display, strum_macros::AsRefStr)]
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Hash, strum_macros::Display, strum_macros::AsRefStr,
+)]
 pub enum ProviderType {
 	Soundgasm,
 	Kemono,
 	Patreon,
 }
 
-impl ProviderType {
-	pub fn get_user_agent(&self) -> String {
-		use ProviderType::*;
-
-		match self {
-			Soundgasm => USER_AGENT,
-			Kemono => USER_AGENT,
-			Patreon => USER_AGENT,
-		}
-		.to_string()
-	}
-}
-
 pub enum PointerType {
 	SoundgasmTrack(soundgasm::TrackPointer),
 	SoundgasmProfile(soundgasm::ProfilePointer),
@@ -2,6 +2,7 @@ mod profile;
 mod track;
 
 pub use profile::ProfilePointer;
-pub use track::{
-	SoundgasmAudioTrack, SoundgasmAudioTrackRow, SoundgasmTrackAudio, TrackPointer, TrackSoundPointer,
-};
+pub use track::{SoundgasmAudioTrack, SoundgasmAudioTrackRow, TrackPointer, TrackSoundPointer};
+// Tests elsewhere build tracks from scratch
+#[cfg(test)]
+pub use track::TrackMetadata;
@@ -1,82 +1,305 @@
 mod pointer;
 
-use lazy_static::lazy_static;
-use log::debug;
-use regex::Regex;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use futures_util::{stream, StreamExt};
+use log::{debug, error, info};
 
 use super::track::{SoundgasmAudioTrack, TrackMetadata, TrackPointer};
+use crate::error::Error;
+use crate::media_sources::html::{Document, ParseError};
+use crate::progress::JobKind;
 
 pub use pointer::{ProfilePointer, PROFILE_SLUG_PATTERN};
 
+/// Most track pages fetched at once while scanning a profile. Requests are
+/// also spaced out by the per-host rate limit.
+const SCAN_CONCURRENCY: usize = 4;
+
 pub struct Profile {
 	pub slug: String,
 	pub tracks: Vec<ProfileTrackListing>,
+	/// Listings that couldn't be read. Their tracks may still be on the
+	/// profile, so nothing is marked as deleted while there are any.
+	pub skipped_listings: usize,
 }
 
 impl Profile {
-	pub fn from_html(profile_page_html: &str) -> Result<Self, String> {
-		let sections_iter = TRACK_SECTION_RE.captures_iter(profile_page_html);
-
+	/// Reads the track listings from a profile page. Listings without a
+	/// track link are skipped, a missing description is left empty. A
+	/// profile without any tracks lists none, but only on a page with the
+	/// site's header, so an error page isn't read as an empty profile.
+	pub fn from_html(slug: &str, profile_page_html: &str) -> Result<Self, ParseError> {
+		let page = Document::parse(profile_page_html);
 		let mut tracks = Vec::new();
 
-		for section_capture in sections_iter {
-			let (_, [section_html]) = section_capture.extract();
-
-			let Some(track_url_and_title) = TRACK_URL_AND_TITLE_RE.captures(section_html) else {
-				debug!("Failed to capture track URL and title");
-				continue;
-			};
+		let has_header = page
+			.select("header")
+			.iter()
+			.any(|header| header.select_first("a.logo").is_some());
+		if !has_header {
+			return Err(ParseError::missing("profile", "site header"));
+		}
 
-			let (_, [url, title]) = track_url_and_title.extract();
+		let mut skipped_listings = 0;
 
-			let Ok(pointer) = TrackPointer::from_url(url) else {
-				continue; // Skip if track URL is invalid
+		for section in page.select("div.sound-details") {
+			let Some(link) = section.select_first("a") else {
+				debug!("Skipping track listing without a link");
+				skipped_listings += 1;
+				continue;
 			};
 
-			let Some(description_capture) = TRACK_DESCRIPTION_RE.captures(section_html) else {
-				debug!("Failed to capture track description");
+			let Some(Ok(pointer)) = link.attr("href").map(TrackPointer::from_url) else {
+				debug!("Skipping track listing with an invalid link");
+				skipped_listings += 1;
 				continue;
 			};
 
-			let (_, [description]) = description_capture.extract();
+			let description = section
+				.select_first("span.soundDescription")
+				.map(|description| description.text().trim().to_string())
+				.unwrap_or_default();
 
 			let metadata = TrackMetadata {
-				title: title.to_string(),
-				description: description.to_string(),
+				title: link.text().trim().to_string(),
+				description,
 			};
 
 			tracks.push(ProfileTrackListing { pointer, metadata });
 		}
 
-		if tracks.is_empty() {
-			return Err(format!(
-				"No valid track listings found in profile page HTML"
-			));
-		}
-
 		Ok(Self {
-			slug: tracks.first().unwrap().pointer.profile_slug.clone(),
+			slug: slug.to_string(),
 			tracks,
+			skipped_listings,
 		})
 	}
 
-	pub async fn add_to_library(&self, context: &mut crate::Context) -> Result<(), String> {
-		for track in &self.tracks {
-			let track_pointer = &track.pointer;
-			debug!(
-				"Adding track {} to profile {}",
-				track_pointer.track_slug, self.slug
+	pub async fn add_to_library(&self, context: &mut crate::Context) -> ProfileScanSummary {
+		self.add_tracks(&self.tracks, context).await
+	}
+
+	/// Listings of the tracks that aren't in the library yet
+	pub async fn find_new_tracks(
+		&self,
+		context: &mut crate::Context,
+	) -> Result<Vec<&ProfileTrackListing>, Error> {
+		use crate::schema::soundgasm_tracks::dsl::*;
+		use diesel::prelude::*;
+
+		let known_slugs = soundgasm_tracks
+			.filter(profile_slug.eq(&self.slug))
+			.select(track_slug)
+			.load::<String>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load known tracks: {}", err)))?
+			.into_iter()
+			.collect::<HashSet<_>>();
+
+		Ok(
+			self
+				.tracks
+				.iter()
+				.filter(|track| !known_slugs.contains(&track.pointer.track_slug))
+				.collect(),
+		)
+	}
+
+	/// Marks the tracks of the profile that are no longer listed as deleted,
+	/// and clears the mark from tracks listed again. The archived audio is
+	/// kept. Returns how many tracks were newly marked, which is none when
+	/// some listings couldn't be read or none were listed at all.
+	pub async fn update_deleted_tracks(&self, context: &mut crate::Context) -> Result<usize, Error> {
+		use crate::schema::soundgasm_tracks::dsl::*;
+		use diesel::prelude::*;
+
+		if self.skipped_listings > 0 {
+			info!(
+				"Not checking Soundgasm profile {} for removed tracks, {} listings couldn't be read",
+				self.slug, self.skipped_listings
 			);
+			return Ok(0);
+		}
 
-			let Some((metadata, sound_pointer)) = track_pointer.fetch_track_page().await else {
-				return Err(format!(
-					"Failed to fetch sound pointer for track {}",
-					track_pointer.track_slug
-				));
-			};
+		if self.tracks.is_empty() {
+			let listed_count = soundgasm_tracks
+				.filter(profile_slug.eq(&self.slug))
+				.filter(deleted_at.is_null())
+				.count()
+				.get_result::<i64>(&mut context.conn)
+				.map_err(|err| Error::Database(format!("Failed to count listed tracks: {}", err)))?;
+
+			// More likely a page that changed than a profile emptied at once
+			if listed_count > 0 {
+				info!(
+					"Not checking Soundgasm profile {} for removed tracks, it lists none of its {} tracks",
+					self.slug, listed_count
+				);
+				return Ok(0);
+			}
+		}
+
+		let listed_slugs = self
+			.tracks
+			.iter()
+			.map(|track| track.pointer.track_slug.as_str())
+			.collect::<Vec<_>>();
+
+		let deleted_count = diesel::update(
+			soundgasm_tracks
+				.filter(profile_slug.eq(&self.slug))
+				.filter(track_slug.ne_all(&listed_slugs))
+				.filter(deleted_at.is_null()),
+		)
+		.set(deleted_at.eq(chrono::Utc::now().naive_utc()))
+		.execute(&mut context.conn)
+		.map_err(|err| Error::Database(format!("Failed to mark deleted tracks: {}", err)))?;
+
+		diesel::update(
+			soundgasm_tracks
+				.filter(profile_slug.eq(&self.slug))
+				.filter(track_slug.eq_any(&listed_slugs))
+				.filter(deleted_at.is_not_null()),
+		)
+		.set(deleted_at.eq(None::<chrono::NaiveDateTime>))
+		.execute(&mut context.conn)
+		.map_err(|err| Error::Database(format!("Failed to restore listed tracks: {}", err)))?;
+
+		if deleted_count > 0 {
+			info!(
+				"{} tracks were removed from Soundgasm profile {}",
+				deleted_count, self.slug
+			);
+		}
+
+		Ok(deleted_count)
+	}
+
+	/// Fetches the pages of the tracks, a few at a time, and adds them to the
+	/// library as they arrive. Failed tracks don't stop the others.
+	pub async fn add_tracks<'a>(
+		&self,
+		tracks: impl IntoIterator<Item = &'a ProfileTrackListing>,
+		context: &mut crate::Context,
+	) -> ProfileScanSummary {
+		let listings = tracks
+			.into_iter()
+			.map(|track| (track.pointer.clone(), track.metadata.title.clone()))
+			.collect::<Vec<_>>();
+		let mut job = context.progress.start_job(
+			JobKind::Scan,
+			format!("Scanning {}", self.slug),
+			Some(listings.len() as u64),
+		);
+
+		// Cloned so pages can be fetched while earlier ones are being saved.
+		// The stream owns what it fetches, which keeps the scan `Send` for
+		// running in the background.
+		let http = context.http.clone();
+		let mut pages = stream::iter(listings)
+			.map(|(track_pointer, listed_title)| {
+				let http = http.clone();
+				async move {
+					debug!(
+						"Fetching track {} ({}) of profile {}",
+						track_pointer.track_slug, listed_title, track_pointer.profile_slug
+					);
+					let page = track_pointer.fetch_track_page(&http).await;
+					(track_pointer, page)
+				}
+			})
+			.buffer_unordered(SCAN_CONCURRENCY);
 
-			let audio_track = SoundgasmAudioTrack::new(track_pointer.clone(), metadata, sound_pointer);
-			audio_track.add_to_library(context).await;
+		let mut summary = ProfileScanSummary::default();
+
+		while let Some((track_pointer, page)) = pages.next().await {
+			job.advance(1);
+
+			match page {
+				Ok((metadata, sound_pointer)) => {
+					let audio_track = SoundgasmAudioTrack::new(track_pointer, metadata, sound_pointer);
+					match audio_track.add_to_library(context).await {
+						Ok(_) => summary.added.push(audio_track),
+						Err(err) => {
+							error!(
+								"Failed to add track {}: {}",
+								audio_track.pointer.to_url(),
+								err
+							);
+							summary.failed.push((audio_track.pointer, err));
+						}
+					}
+				}
+				Err(Error::NotFound(_)) => {
+					info!("Soundgasm track was removed: {}", track_pointer.to_url());
+					match track_pointer.mark_deleted(context).await {
+						Ok(()) => summary.removed.push(track_pointer),
+						Err(err) => summary.failed.push((track_pointer, err)),
+					}
+				}
+				Err(err) => {
+					error!("Failed to scan track {}: {}", track_pointer.to_url(), err);
+					summary.failed.push((track_pointer, err));
+				}
+			}
+		}
+
+		job.finish();
+
+		summary
+	}
+}
+
+/// What happened to each track of a profile during a scan
+#[derive(Debug, Default)]
+pub struct ProfileScanSummary {
+	pub added: Vec<SoundgasmAudioTrack>,
+	/// Tracks whose page is gone, which were marked as deleted
+	pub removed: Vec<TrackPointer>,
+	/// How many tracks in the library the profile no longer lists
+	pub unlisted: usize,
+	pub failed: Vec<(TrackPointer, Error)>,
+}
+
+impl ProfileScanSummary {
+	/// Sums up the failed tracks as an error of the first failure's kind
+	pub fn get_error(&self) -> Option<Error> {
+		let (_, first_err) = self.failed.first()?;
+
+		Some(first_err.with_message(format!(
+			"{} tracks failed to scan, the first with: {}",
+			self.failed.len(),
+			first_err
+		)))
+	}
+}
+
+impl Display for ProfileScanSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Added {} tracks, {} removed, {} failed",
+			self.added.len(),
+			self.removed.len(),
+			self.failed.len()
+		)?;
+
+		for track in &self.added {
+			write!(f, "\n  added    {}", track.pointer.track_slug)?;
+		}
+		for track_pointer in &self.removed {
+			write!(f, "\n  removed  {}", track_pointer.track_slug)?;
+		}
+		for (track_pointer, err) in &self.failed {
+			write!(f, "\n  failed   {}: {}", track_pointer.track_slug, err)?;
+		}
+		if self.unlisted > 0 {
+			write!(
+				f,
+				"\n{} tracks are no longer listed on the profile",
+				self.unlisted
+			)?;
 		}
 
 		Ok(())
@@ -88,17 +311,12 @@ pub struct ProfileTrackListing {
 	pub(super) metadata: TrackMetadata,
 }
 
-lazy_static! {
-	static ref TRACK_SECTION_RE: Regex =
-		Regex::new("<div class=\"sound-details\">(.+?)</div>").unwrap();
-	static ref TRACK_URL_AND_TITLE_RE: Regex = Regex::new("<a href=\"(.+?)\">(.+?)</a>").unwrap();
-	static ref TRACK_DESCRIPTION_RE: Regex =
-		Regex::new("<span class=\"soundDescription\">(.+?)</span>").unwrap();
-}
-
 #[cfg(test)]
 mod tests {
+	use diesel::RunQueryDsl;
+
 	use super::Profile;
+	use crate::context::test_context;
 
 	#[test]
 	fn test_parse_profile_from_html() {
@@ -106,7 +324,7 @@ mod tests {
 			include_str!("../../../../test/fixtures/http/soundgasm/profiles/sgdl-test/index.html");
 
 		// With subdomain
-		let profile = Profile::from_html(profile_html).unwrap();
+		let profile = Profile::from_html("sgdl-test", profile_html).unwrap();
 		assert_eq!(profile.slug, "sgdl-test");
 		assert_eq!(profile.tracks.len(), 1);
 
@@ -124,4 +342,166 @@ mod tests {
 			"Test audio from https://freesound.org/people/klankbeeld/sounds/808487/"
 		);
 	}
+
+	#[test]
+	fn test_parse_profile_listings() {
+		let profile_html = "<header><a href=\"/\" class=\"logo\">Soundgasm.net Logo</a></header>\
+			<div class=\"sound-details\"><a href=\"https://soundgasm.net/u/sgdl-test/rain\">Rain &amp; Thunder</a></br>\
+			<span class=\"soundDescription\">First line\nSecond line</span></br><span class=\"playCount\">Play Count: 2</span></div>\
+			<div class=\"sound-details\"><a href=\"https://soundgasm.net/u/sgdl-test/quiet\">Quiet</a></br><span class=\"playCount\">Play Count: 0</span></div>\
+			<div class=\"sound-details\"><span class=\"soundDescription\">No link</span></div>";
+
+		let profile = Profile::from_html("sgdl-test", profile_html).unwrap();
+		assert_eq!(profile.tracks.len(), 2);
+		assert_eq!(profile.tracks[0].metadata.title, "Rain & Thunder");
+		assert_eq!(
+			profile.tracks[0].metadata.description,
+			"First line\nSecond line"
+		);
+		assert_eq!(profile.tracks[1].pointer.track_slug, "quiet");
+		assert_eq!(profile.tracks[1].metadata.description, "");
+		assert_eq!(profile.skipped_listings, 1);
+
+		// A profile without tracks
+		let profile = Profile::from_html(
+			"sgdl-test",
+			"<header><a href=\"/\" class=\"logo\">Soundgasm.net Logo</a></header>",
+		)
+		.unwrap();
+		assert_eq!(profile.slug, "sgdl-test");
+		assert!(profile.tracks.is_empty());
+		assert_eq!(profile.skipped_listings, 0);
+
+		// Not a profile page at all
+		assert_eq!(
+			Profile::from_html("sgdl-test", "<html><p>Down for maintenance</p></html>")
+				.err()
+				.unwrap()
+				.field,
+			"site header"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_find_new_tracks() {
+		let profile_html =
+			include_str!("../../../../test/fixtures/http/soundgasm/profiles/sgdl-test/index.html");
+		let profile = Profile::from_html("sgdl-test", profile_html).unwrap();
+		let mut context = test_context();
+
+		assert_eq!(
+			profile.find_new_tracks(&mut context).await.unwrap().len(),
+			1
+		);
+
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, created_at, updated_at) \
+			VALUES ('sgdl-test', 'shopping-mall-half-open-Netherlands-207-AM-161001_0998', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		assert!(profile
+			.find_new_tracks(&mut context)
+			.await
+			.unwrap()
+			.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_update_deleted_tracks() {
+		let profile_html =
+			include_str!("../../../../test/fixtures/http/soundgasm/profiles/sgdl-test/index.html");
+		let profile = Profile::from_html("sgdl-test", profile_html).unwrap();
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, created_at, updated_at, deleted_at) VALUES \
+			('sgdl-test', 'shopping-mall-half-open-Netherlands-207-AM-161001_0998', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00', '2026-10-18 00:00:00'), \
+			('sgdl-test', 'removed-track', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00', NULL), \
+			('other-profile', 'other-track', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00', NULL)",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		assert_eq!(
+			profile.update_deleted_tracks(&mut context).await.unwrap(),
+			1
+		);
+		// Already marked tracks aren't counted again
+		assert_eq!(
+			profile.update_deleted_tracks(&mut context).await.unwrap(),
+			0
+		);
+
+		let deleted_slugs = {
+			use crate::schema::soundgasm_tracks::dsl::*;
+			use diesel::prelude::*;
+
+			soundgasm_tracks
+				.filter(deleted_at.is_not_null())
+				.select(track_slug)
+				.load::<String>(&mut context.conn)
+				.unwrap()
+		};
+		assert_eq!(deleted_slugs, vec!["removed-track"]);
+	}
+
+	#[tokio::test]
+	async fn test_skipped_listings_keep_tracks() {
+		let profile_html = "<header><a href=\"/\" class=\"logo\">Soundgasm.net Logo</a></header>\
+			<div class=\"sound-details\"><a href=\"https://soundgasm.net/u/sgdl-test/listed\">Listed</a></div>\
+			<div class=\"sound-details\"><a href=\"/broken\">Broken</a></div>";
+		let profile = Profile::from_html("sgdl-test", profile_html).unwrap();
+		assert_eq!(profile.skipped_listings, 1);
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, created_at, updated_at) \
+			VALUES ('sgdl-test', 'broken-listing', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		// The track might be the one whose listing couldn't be read
+		assert_eq!(
+			profile.update_deleted_tracks(&mut context).await.unwrap(),
+			0
+		);
+	}
+
+	#[tokio::test]
+	async fn test_empty_listing_keeps_tracks() {
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, created_at, updated_at) \
+			VALUES ('sgdl-test', 'archived', '', '', '2026-10-18 00:00:00', '2026-10-18 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		// A page that lists nothing while the library still has tracks
+		let profile = Profile::from_html(
+			"sgdl-test",
+			"<header><a href=\"/\" class=\"logo\">Soundgasm.net Logo</a></header><p>Something went wrong</p>",
+		)
+		.unwrap();
+		assert_eq!(
+			profile.update_deleted_tracks(&mut context).await.unwrap(),
+			0
+		);
+
+		let deleted_count = {
+			use crate::schema::soundgasm_tracks::dsl::*;
+			use diesel::prelude::*;
+
+			soundgasm_tracks
+				.filter(deleted_at.is_not_null())
+				.count()
+				.get_result::<i64>(&mut context.conn)
+				.unwrap()
+		};
+		assert_eq!(deleted_count, 0);
+	}
 }
@@ -1,8 +1,16 @@
 use lazy_static::lazy_static;
+use log::debug;
 use regex::Regex;
 
 use super::super::track::TrackPointer;
-use crate::{common::fetch_text, media_sources::soundgasm::profile::Profile};
+use crate::{
+	error::Error,
+	http_client::HttpClient,
+	media_sources::{
+		soundgasm::profile::{Profile, ProfileScanSummary},
+		ProviderType,
+	},
+};
 
 pub const PROFILE_SLUG_PATTERN: &str = "a-zA-Z0-9_-";
 
@@ -12,25 +20,31 @@ pub struct ProfilePointer {
 }
 
 impl ProfilePointer {
-	pub fn from_slug(maybe_slug: &str) -> Option<Self> {
-		let profile_matches = PROFILE_SLUG_RE.captures(maybe_slug)?;
-		let slug = profile_matches.get(1)?.as_str().to_string();
+	pub async fn scan(&self, context: &mut crate::Context) -> Result<ProfileScanSummary, Error> {
+		let profile = self.fetch_profile(&context.http).await?;
 
-		Some(Self { slug })
+		let mut summary = profile.add_to_library(context).await;
+		summary.unlisted = profile.update_deleted_tracks(context).await?;
+
+		Ok(summary)
 	}
 
-	pub async fn scan(&self, context: &mut crate::Context) -> Result<String, String> {
-		let profile = self.fetch_profile().await.or(Err(format!(
-			"Failed to fetch Soundgasm profile for track: {}",
+	/// Adds the tracks that aren't in the library yet. Only their pages are
+	/// fetched, so this is much cheaper than scanning the whole profile.
+	pub async fn sync(&self, context: &mut crate::Context) -> Result<ProfileScanSummary, Error> {
+		let profile = self.fetch_profile(&context.http).await?;
+		let new_tracks = profile.find_new_tracks(context).await?;
+
+		debug!(
+			"Found {} new tracks on Soundgasm profile {}",
+			new_tracks.len(),
 			self.slug
-		)))?;
+		);
 
-		profile
-			.add_to_library(context)
-			.await
-			.map_err(|err| format!("Failed to add profile to library: {}", err))?;
+		let mut summary = profile.add_tracks(new_tracks, context).await;
+		summary.unlisted = profile.update_deleted_tracks(context).await?;
 
-		Ok("Profile added to library successfully".to_string())
+		Ok(summary)
 	}
 
 	pub fn from_url(url: &str) -> Option<Self> {
@@ -44,15 +58,23 @@ impl ProfilePointer {
 		format!("https://soundgasm.net/u/{}", self.slug)
 	}
 
-	pub async fn fetch_profile(&self) -> Result<Profile, String> {
-		let profile_html = fetch_text(self.get_url()).await.map_err(|err| {
-			format!(
-				"Failed to fetch Soundgasm profile page: {}\nError: {}",
+	pub async fn fetch_profile(&self, http: &HttpClient) -> Result<Profile, Error> {
+		let profile_html = http
+			.fetch_text(ProviderType::Soundgasm, &self.get_url())
+			.await
+			.map_err(|err| {
+				err.with_message(format!(
+					"Failed to fetch Soundgasm profile page {}: {}",
+					self.slug, err
+				))
+			})?;
+
+		Profile::from_html(&self.slug, &profile_html).map_err(|err| {
+			Error::Parse(format!(
+				"Failed to parse Soundgasm profile page {}: {}",
 				self.slug, err
-			)
-		})?;
-
-		Profile::from_html(&profile_html)
+			))
+		})
 	}
 }
 
@@ -68,8 +90,6 @@ lazy_static! {
 	static ref PROFILE_URL_RE: Regex =
 		Regex::new(format!("//(?:www.)?soundgasm.net/u/([{}]+)/?", PROFILE_SLUG_PATTERN).as_str())
 			.unwrap();
-	static ref PROFILE_SLUG_RE: Regex =
-		Regex::new(format!("([{}]+)", PROFILE_SLUG_PATTERN).as_str()).unwrap();
 }
 
 #[cfg(test)]
@@ -1,7 +1,5 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-
-use crate::media_types::MediaMetadata;
+use crate::media_sources::html::{Document, ParseError};
+use crate::media_types::{extract_tags, MediaMetadata};
 
 use super::SoundgasmAudioTrackRow;
 
@@ -12,14 +10,27 @@ pub struct TrackMetadata {
 }
 
 impl TrackMetadata {
-	pub fn from_html(track_page_html: &str) -> Option<Self> {
-		let title_matches = TRACK_TITLE_RE.captures(track_page_html)?;
-		let title = String::from(title_matches.get(1)?.as_str());
+	/// Reads the title and description from a track page. Tracks can be
+	/// uploaded without a description, so only the title is required.
+	pub fn from_document(track_page: &Document) -> Result<Self, ParseError> {
+		let title = track_page
+			.select_first("div.jp-title")
+			.map(|title| title.text().trim().to_string())
+			.filter(|title| !title.is_empty())
+			.ok_or(ParseError::missing("track", "title"))?;
+
+		let description = track_page
+			.select_first("div.jp-description")
+			.and_then(|description| description.select_first("p"))
+			.map(|description| description.text().trim().to_string())
+			.unwrap_or_default();
 
-		let description_matches = TRACK_DESCRIPTION_RE.captures(track_page_html)?;
-		let description = String::from(description_matches.get(1)?.as_str());
+		Ok(Self { title, description })
+	}
 
-		Some(Self { title, description })
+	/// Bracketed tags from the title, followed by any new ones from the description
+	pub fn extract_tags(&self) -> Vec<String> {
+		extract_tags(&format!("{}\n{}", self.title, self.description))
 	}
 }
 
@@ -51,9 +62,35 @@ impl MediaMetadata for TrackMetadata {
 	}
 }
 
-lazy_static! {
-	static ref TRACK_TITLE_RE: Regex =
-		Regex::new("<div class=\"jp-title\" aria-label=\"title\">(.+?)</div>").unwrap();
-	static ref TRACK_DESCRIPTION_RE: Regex =
-		Regex::new("<p style=\"white-space: pre-wrap;\">(.+?)</p>").unwrap();
+#[cfg(test)]
+mod tests {
+	use super::TrackMetadata;
+	use crate::media_sources::html::{Document, ParseError};
+
+	#[test]
+	fn test_track_metadata_from_document() {
+		let page = Document::parse(
+			"<div class=\"jp-title\" aria-label=\"title\">[F4M] Rain &amp; Thunder</div>\n\
+			<div class=\"jp-description\">\n\
+				<p style=\"white-space: pre-wrap;\">First line\nSecond line with &quot;quotes&quot; &#39;n more</p>\n\
+			</div>",
+		);
+
+		let metadata = TrackMetadata::from_document(&page).unwrap();
+		assert_eq!(metadata.title, "[F4M] Rain & Thunder");
+		assert_eq!(
+			metadata.description,
+			"First line\nSecond line with \"quotes\" 'n more"
+		);
+
+		// The description is optional, the title isn't
+		let page = Document::parse("<div class=\"jp-title\">Untitled</div>");
+		assert_eq!(TrackMetadata::from_document(&page).unwrap().description, "");
+
+		let page = Document::parse("<div class=\"jp-description\"><p>Only a description</p></div>");
+		assert_eq!(
+			TrackMetadata::from_document(&page).unwrap_err(),
+			ParseError::missing("track", "title")
+		);
+	}
 }
@@ -4,7 +4,8 @@ mod row;
 mod sound_pointer;
 mod stored_audio;
 
-use log::debug;
+use diesel::QueryableByName;
+use log::warn;
 
 pub use metadata::TrackMetadata;
 pub use pointer::TrackPointer;
@@ -12,7 +13,12 @@ pub use row::SoundgasmAudioTrackRow;
 pub use sound_pointer::TrackSoundPointer;
 pub use stored_audio::SoundgasmTrackAudio;
 
-use crate::media_types::{MediaBlobPointer, MediaPointer, MediaType};
+use crate::error::Error;
+use crate::file_store::StoredBlob;
+use crate::media_types::{
+	build_match_query, tagged_items_subquery, MediaBlobPointer, MediaMetadata, MediaType,
+	SearchFilters, TaggedItem,
+};
 use crate::{media_sources::ProviderType, media_types::MediaItem, Context};
 
 #[derive(Debug, Clone)]
@@ -48,82 +54,286 @@ impl MediaItem for SoundgasmAudioTrack {
 	}
 
 	fn get_title(&self) -> String {
-		self.metadata.title.clone()
+		self.metadata.get_title()
 	}
 
 	fn get_description(&self) -> String {
-		self.metadata.description.clone()
+		self.metadata.get_description()
 	}
 
 	fn get_author(&self) -> String {
 		self.pointer.profile_slug.clone()
 	}
 
-	fn get_pointer(&self) -> impl MediaPointer {
-		self.pointer.clone()
-	}
-
 	fn get_blob_pointer(&self) -> impl MediaBlobPointer {
 		self.sound_pointer.clone()
 	}
 
-	async fn search(context: &mut Context, query: &str) -> Vec<SoundgasmAudioTrack> {
+	async fn search(context: &mut Context, query: &str) -> Result<Vec<SoundgasmAudioTrack>, Error> {
+		let Some(match_query) = build_match_query(query) else {
+			return Ok(Vec::new());
+		};
+
+		let results = Self::search_ranked(context, Some(&match_query), &SearchFilters::default())
+			.await?
+			.into_iter()
+			.map(|(track, _)| track)
+			.collect();
+
+		Ok(results)
+	}
+}
+
+#[derive(QueryableByName)]
+struct RankedTrackRow {
+	#[diesel(embed)]
+	track_row: SoundgasmAudioTrackRow,
+	#[diesel(sql_type = diesel::sql_types::Double)]
+	score: f64,
+}
+
+impl SoundgasmAudioTrack {
+	/// Runs an FTS5 `match_query` (see `build_match_query`) and returns the
+	/// matching tracks with their BM25 score, best match first. Lower scores
+	/// are better, and titles weigh more than descriptions. Without a query
+	/// every track passing the filters matches with a score of 0.
+	pub async fn search_ranked(
+		context: &mut Context,
+		match_query: Option<&str>,
+		filters: &SearchFilters,
+	) -> Result<Vec<(SoundgasmAudioTrack, f64)>, Error> {
+		use diesel::prelude::*;
+		use diesel::sql_types::{BigInt, Text, Timestamp};
+
+		if filters
+			.media_type
+			.is_some_and(|media_type| media_type != MediaType::AudioMp3)
+		{
+			return Ok(Vec::new());
+		}
+
+		let mut conditions = Vec::new();
+
+		let select = match match_query {
+			Some(_) => {
+				conditions.push("`soundgasm_tracks_search` MATCH ?".to_string());
+
+				"SELECT `soundgasm_tracks`.*, \
+					bm25(`soundgasm_tracks_search`, 2.0, 0.0, 10.0, 1.0) AS `score` \
+				FROM `soundgasm_tracks_search` \
+				JOIN `soundgasm_tracks` ON `soundgasm_tracks`.`rowid` = `soundgasm_tracks_search`.`rowid`"
+			}
+			None => "SELECT `soundgasm_tracks`.*, 0.0 AS `score` FROM `soundgasm_tracks`",
+		};
+
+		if !filters.tags.is_empty() {
+			// Same format as `TaggedItem::soundgasm_track`
+			conditions.push(format!(
+				"(`soundgasm_tracks`.`profile_slug` || '/' || `soundgasm_tracks`.`track_slug`) IN ({})",
+				tagged_items_subquery(filters.tags.len())
+			));
+		}
+		if filters.author.is_some() {
+			conditions.push("`soundgasm_tracks`.`profile_slug` = ?".to_string());
+		}
+		match filters.is_downloaded {
+			Some(true) => conditions.push("`soundgasm_tracks`.`content_hash` IS NOT NULL".to_string()),
+			Some(false) => conditions.push("`soundgasm_tracks`.`content_hash` IS NULL".to_string()),
+			None => {}
+		}
+		match filters.is_deleted {
+			Some(true) => conditions.push("`soundgasm_tracks`.`deleted_at` IS NOT NULL".to_string()),
+			Some(false) => conditions.push("`soundgasm_tracks`.`deleted_at` IS NULL".to_string()),
+			None => {}
+		}
+		if filters.since.is_some() {
+			conditions.push("`soundgasm_tracks`.`created_at` >= ?".to_string());
+		}
+		if filters.until.is_some() {
+			conditions.push("`soundgasm_tracks`.`created_at` < ?".to_string());
+		}
+
+		let sql = format!(
+			"{} WHERE {} \
+			ORDER BY `score`, `soundgasm_tracks`.`profile_slug`, `soundgasm_tracks`.`track_slug` \
+			LIMIT ?",
+			select,
+			if conditions.is_empty() {
+				"1".to_string()
+			} else {
+				conditions.join(" AND ")
+			}
+		);
+
+		// Bound in the same order as the conditions above
+		let mut query = diesel::sql_query(sql).into_boxed();
+
+		if let Some(match_query) = match_query {
+			query = query.bind::<Text, _>(match_query.to_string());
+		}
+		if !filters.tags.is_empty() {
+			query = query.bind::<Text, _>(TaggedItem::SOUNDGASM_SOURCE);
+			for tag in &filters.tags {
+				query = query.bind::<Text, _>(tag.clone());
+			}
+		}
+		if let Some(author) = &filters.author {
+			query = query.bind::<Text, _>(author.clone());
+		}
+		if let Some(since) = filters.since {
+			query = query.bind::<Timestamp, _>(since);
+		}
+		if let Some(until) = filters.until {
+			query = query.bind::<Timestamp, _>(until);
+		}
+
+		let rows = query
+			.bind::<BigInt, _>(filters.get_limit())
+			.load::<RankedTrackRow>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to search tracks: {}", err)))?;
+
+		let results = rows
+			.iter()
+			.filter_map(|ranked| {
+				SoundgasmAudioTrack::try_from(&ranked.track_row)
+					.inspect_err(|err| warn!("Skipping track in search results: {}", err))
+					.ok()
+					.map(|track| (track, ranked.score))
+			})
+			.collect::<Vec<_>>();
+
+		Ok(results)
+	}
+
+	/// Points the track at its content-addressed audio and saves the hash
+	/// and length, leaving the rest of the row as it is.
+	pub async fn set_stored_audio(
+		&mut self,
+		blob: &StoredBlob,
+		context: &mut Context,
+	) -> Result<(), Error> {
+		SoundgasmAudioTrackRow::save_stored_audio(
+			context,
+			&self.pointer.profile_slug,
+			&self.pointer.track_slug,
+			&blob.content_hash,
+			blob.content_length,
+		)
+		.await?;
+
+		self.stored_audio = Some(SoundgasmTrackAudio::from_stored_blob(
+			self.sound_pointer.clone(),
+			blob,
+		));
+
+		Ok(())
+	}
+
+	/// Tracks that have been cataloged but whose audio isn't stored yet.
+	/// Tracks removed from soundgasm can't be downloaded anymore.
+	pub async fn find_undownloaded(
+		context: &mut Context,
+		filter_profile_slug: Option<&str>,
+	) -> Result<Vec<SoundgasmAudioTrack>, Error> {
 		use crate::schema::soundgasm_tracks::dsl::*;
-		use diesel::dsl::sql;
 		use diesel::prelude::*;
-		use diesel::sql_types::Bool;
-
-		// Split search terms into keywords and search for them in the title and description columns
-		let conditionals = query
-			.split_whitespace()
-			.filter_map(|term| {
-				if term.is_empty() {
-					None
-				} else {
-					Some(format!(
-						"title LIKE %{}% OR description LIKE %{}%",
-						term, term
-					))
-				}
+
+		let mut query = soundgasm_tracks
+			.filter(content_hash.is_null())
+			.filter(sound_id.is_not_null())
+			.filter(deleted_at.is_null())
+			.into_boxed();
+
+		if let Some(filter_profile_slug) = filter_profile_slug {
+			query = query.filter(profile_slug.eq(filter_profile_slug));
+		}
+
+		let rows = query
+			.order((profile_slug, track_slug))
+			.select(SoundgasmAudioTrackRow::as_select())
+			.load::<SoundgasmAudioTrackRow>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load undownloaded tracks: {}", err)))?;
+
+		let tracks = rows
+			.iter()
+			.filter_map(|row| {
+				SoundgasmAudioTrack::try_from(row)
+					.inspect_err(|err| warn!("Skipping undownloaded track: {}", err))
+					.ok()
 			})
-			.collect::<Vec<_>>()
-			.join(" OR ");
+			.collect::<Vec<_>>();
 
-		let conditionals = sql::<Bool>(&conditionals);
+		Ok(tracks)
+	}
 
-		// Fixes?
-		// Create a dedicated blocking thread that handles database requests from a queue and holds it's own connection handle
+	/// Tracks that have audio recorded in the store.
+	pub async fn find_downloaded(context: &mut Context) -> Vec<SoundgasmAudioTrack> {
+		use crate::schema::soundgasm_tracks::dsl::*;
+		use diesel::prelude::*;
 
-		let rows = crate::schema::soundgasm_tracks::table
-			.filter(conditionals)
+		let rows = soundgasm_tracks
+			.filter(content_hash.is_not_null())
+			.order((profile_slug, track_slug))
 			.select(SoundgasmAudioTrackRow::as_select())
 			.load::<SoundgasmAudioTrackRow>(&mut context.conn)
-			.unwrap_or_default();
-		// });
+			.unwrap_or_else(|err| {
+				log::error!("Failed to load downloaded tracks: {}", err);
+				Vec::new()
+			});
 
 		rows
 			.iter()
-			.filter_map(|row| SoundgasmAudioTrack::try_from(row).ok())
+			.filter_map(|row| {
+				SoundgasmAudioTrack::try_from(row)
+					.inspect_err(|err| warn!("Skipping downloaded track: {}", err))
+					.ok()
+			})
 			.collect::<Vec<_>>()
 	}
-}
 
-impl SoundgasmAudioTrack {
-	pub async fn add_to_library(&self, context: &mut Context) {
-		let row = SoundgasmAudioTrackRow::from(self.clone());
+	/// Forgets the stored audio so the next download run fetches it again.
+	pub async fn clear_stored_audio(&mut self, context: &mut Context) -> Result<(), Error> {
+		SoundgasmAudioTrackRow::clear_stored_audio(
+			context,
+			&self.pointer.profile_slug,
+			&self.pointer.track_slug,
+		)
+		.await?;
 
-		let result = row.add_to_library(context).await;
+		self.stored_audio = None;
 
-		if let Some(updated_row) = result {
-			debug!("Track metadata upserted successfully: {:?}", updated_row);
-		} else {
-			debug!("Failed to upsert track metadata");
-		};
+		Ok(())
+	}
+
+	pub fn get_tagged_item(&self) -> TaggedItem {
+		self.pointer.get_tagged_item()
+	}
+
+	/// Upserts the track. Tags that can't be saved are only logged, since the
+	/// track itself made it into the library.
+	pub async fn add_to_library(
+		&self,
+		context: &mut Context,
+	) -> Result<SoundgasmAudioTrackRow, Error> {
+		let row = SoundgasmAudioTrackRow::from(self.clone())
+			.add_to_library(context)
+			.await?;
+
+		let tags = self.metadata.extract_tags();
+		if let Err(err) = self
+			.get_tagged_item()
+			.set_extracted_tags(context, &tags)
+			.await
+		{
+			log::error!("{}", err);
+		}
+
+		Ok(row)
 	}
 }
 
 impl TryFrom<&SoundgasmAudioTrackRow> for SoundgasmAudioTrack {
-	type Error = String;
+	type Error = Error;
 
 	fn try_from(value: &SoundgasmAudioTrackRow) -> Result<Self, Self::Error> {
 		Ok(Self {
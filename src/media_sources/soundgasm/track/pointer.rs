@@ -1,12 +1,18 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use reqwest::{header::HeaderMap, Method};
 
 use super::sound_pointer::TrackSoundPointer;
 use crate::{
-	media_sources::soundgasm::{
-		profile::PROFILE_SLUG_PATTERN, track::TrackMetadata, SoundgasmAudioTrackRow,
+	error::Error,
+	http_client::{check_status, HttpClient},
+	media_sources::{
+		html::Document,
+		soundgasm::{profile::PROFILE_SLUG_PATTERN, track::TrackMetadata, SoundgasmAudioTrackRow},
+		ProviderType,
 	},
-	media_types::{MediaBlobPointer, MediaItem, MediaMetadata, MediaPointer},
+	media_types::{MediaBlobPointer, MediaMetadata, MediaPointer, MetadataVersion, TaggedItem},
+	Context,
 };
 
 pub const TRACK_SLUG_PATTERN: &str = "a-zA-Z0-9_-";
@@ -18,24 +24,27 @@ pub struct TrackPointer {
 }
 
 impl TrackPointer {
-	pub fn from_url(url: &str) -> Result<Self, String> {
+	pub fn from_url(url: &str) -> Result<Self, Error> {
 		let captures = TRACK_URL_RE.captures(url);
 
 		match captures {
 			Some(caps) => {
 				let profile_slug = caps
 					.get(1)
-					.ok_or(format!("Invalid profile slug in URL: {}", url))?;
+					.ok_or_else(|| Error::InvalidInput(format!("Invalid profile slug in URL: {}", url)))?;
 				let track_slug = caps
 					.get(2)
-					.ok_or(format!("Invalid track slug in URL: {}", url))?;
+					.ok_or_else(|| Error::InvalidInput(format!("Invalid track slug in URL: {}", url)))?;
 
 				Ok(Self {
 					profile_slug: profile_slug.as_str().to_string(),
 					track_slug: track_slug.as_str().to_string(),
 				})
 			}
-			None => Err("Unable to parse track pointer from URL".to_string()),
+			None => Err(Error::InvalidInput(format!(
+				"Not a soundgasm track URL: {}",
+				url
+			))),
 		}
 	}
 
@@ -46,14 +55,111 @@ impl TrackPointer {
 		)
 	}
 
-	pub async fn fetch_track_page(&self) -> Option<(TrackMetadata, TrackSoundPointer)> {
-		let response = reqwest::get(self.to_url()).await.ok()?;
-		let html = response.text().await.ok()?;
+	/// Fails with `Error::NotFound` if the track page is gone, which means
+	/// the track was removed from soundgasm.
+	pub async fn fetch_track_page(
+		&self,
+		http: &HttpClient,
+	) -> Result<(TrackMetadata, TrackSoundPointer), Error> {
+		let response = http
+			.send(
+				ProviderType::Soundgasm,
+				Method::GET,
+				&self.to_url(),
+				HeaderMap::new(),
+			)
+			.await
+			.map_err(|err| err.with_message(format!("Failed to fetch track page: {}", err)))?;
 
-		let meta = TrackMetadata::from_html(&html)?;
-		let sound = TrackSoundPointer::from_html(&html)?;
+		let html = check_status(response)
+			.map_err(|err| err.with_message(format!("Failed to fetch track page: {}", err)))?
+			.text()
+			.await
+			.map_err(|err| Error::Network(format!("Failed to read track page: {}", err)))?;
 
-		Some((meta, sound))
+		let page = Document::parse(&html);
+		let meta = TrackMetadata::from_document(&page)
+			.map_err(|err| Error::Parse(format!("Failed to parse {}: {}", self.to_url(), err)))?;
+		let sound = TrackSoundPointer::from_document(&page)
+			.map_err(|err| Error::Parse(format!("Failed to parse {}: {}", self.to_url(), err)))?;
+
+		Ok((meta, sound))
+	}
+
+	/// Every version of the track's title and description, oldest first.
+	/// Empty if the track isn't in the library.
+	pub async fn get_history(&self, context: &mut Context) -> Result<Vec<MetadataVersion>, Error> {
+		use crate::schema::{soundgasm_tracks, track_revisions};
+		use diesel::prelude::*;
+
+		let current = soundgasm_tracks::table
+			.find((&self.profile_slug, &self.track_slug))
+			.select((
+				soundgasm_tracks::created_at,
+				soundgasm_tracks::title,
+				soundgasm_tracks::description,
+			))
+			.first::<(chrono::NaiveDateTime, String, String)>(&mut context.conn)
+			.optional()
+			.map_err(|err| Error::Database(format!("Failed to load track: {}", err)))?;
+
+		let Some((created_at, title, description)) = current else {
+			return Ok(Vec::new());
+		};
+
+		let revisions = track_revisions::table
+			.filter(track_revisions::profile_slug.eq(&self.profile_slug))
+			.filter(track_revisions::track_slug.eq(&self.track_slug))
+			.order(track_revisions::id)
+			.select((
+				track_revisions::title,
+				track_revisions::description,
+				track_revisions::replaced_at,
+			))
+			.load(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load track revisions: {}", err)))?;
+
+		Ok(MetadataVersion::build_history(
+			created_at,
+			revisions,
+			(title, description),
+		))
+	}
+
+	/// Records that the track was removed from soundgasm. The archived audio
+	/// is kept, and the time it was first noticed missing isn't overwritten.
+	pub async fn mark_deleted(&self, context: &mut Context) -> Result<(), Error> {
+		use crate::schema::soundgasm_tracks::dsl::*;
+		use diesel::prelude::*;
+
+		diesel::update(
+			soundgasm_tracks
+				.find((&self.profile_slug, &self.track_slug))
+				.filter(deleted_at.is_null()),
+		)
+		.set(deleted_at.eq(chrono::Utc::now().naive_utc()))
+		.execute(&mut context.conn)
+		.map_err(|err| Error::Database(format!("Failed to mark track as deleted: {}", err)))?;
+
+		Ok(())
+	}
+
+	pub fn get_tagged_item(&self) -> TaggedItem {
+		TaggedItem::soundgasm_track(&self.profile_slug, &self.track_slug)
+	}
+
+	pub async fn is_in_library(&self, context: &mut Context) -> bool {
+		use crate::schema::soundgasm_tracks::dsl::*;
+		use diesel::prelude::*;
+
+		let count = soundgasm_tracks
+			.filter(profile_slug.eq(&self.profile_slug))
+			.filter(track_slug.eq(&self.track_slug))
+			.count()
+			.get_result::<i64>(&mut context.conn)
+			.unwrap_or_default();
+
+		count > 0
 	}
 }
 
@@ -76,16 +182,16 @@ impl From<&SoundgasmAudioTrackRow> for TrackPointer {
 }
 
 impl MediaPointer for TrackPointer {
-	async fn fetch_metadata(&self) -> Vec<impl MediaMetadata> {
-		let Some((metadata, _)) = self.fetch_track_page().await else {
+	async fn fetch_metadata(&self, http: &HttpClient) -> Vec<impl MediaMetadata> {
+		let Ok((metadata, _)) = self.fetch_track_page(http).await else {
 			return vec![];
 		};
 
 		vec![metadata]
 	}
 
-	async fn fetch_blob_pointer(&self) -> Option<impl MediaBlobPointer> {
-		let Some((_, sound_pointer)) = self.fetch_track_page().await else {
+	async fn fetch_blob_pointer(&self, http: &HttpClient) -> Option<impl MediaBlobPointer> {
+		let Ok((_, sound_pointer)) = self.fetch_track_page(http).await else {
 			return None;
 		};
 
@@ -5,6 +5,7 @@ use log::debug;
 
 // use crate::generate_update_type;
 use super::SoundgasmAudioTrack;
+use crate::error::Error;
 use crate::schema;
 use crate::Context;
 
@@ -26,7 +27,7 @@ pub struct SoundgasmAudioTrackRow {
 }
 
 impl SoundgasmAudioTrackRow {
-	pub async fn add_to_library(&self, context: &mut Context) -> Option<Self> {
+	pub async fn add_to_library(&self, context: &mut Context) -> Result<Self, Error> {
 		use schema::soundgasm_tracks::{profile_slug, table, track_slug};
 
 		// TODO: Only add columns that are not null
@@ -53,24 +54,65 @@ impl SoundgasmAudioTrackRow {
 			deleted_at: None,
 		};
 
-		let result = diesel::insert_into(table)
+		let updated_row = diesel::insert_into(table)
 			.values(self)
 			.on_conflict((profile_slug, track_slug))
 			.do_update()
 			.set(updates)
 			.returning(Self::as_returning())
-			.get_result(&mut context.conn);
-
-		match result {
-			Ok(updated_row) => {
-				debug!("Track metadata upserted successfully: {:?}", updated_row);
-				Some(updated_row)
-			}
-			Err(err) => {
-				debug!("Failed to upsert track metadata: {:?}", err);
-				None
-			}
-		}
+			.get_result(&mut context.conn)
+			.map_err(|err| {
+				Error::Database(format!(
+					"Failed to upsert Soundgasm track {}/{}: {}",
+					self.profile_slug, self.track_slug, err
+				))
+			})?;
+
+		debug!("Track metadata upserted successfully: {:?}", updated_row);
+
+		Ok(updated_row)
+	}
+
+	/// Only touches the stored file, so metadata from a scan that ran
+	/// during the download isn't overwritten
+	pub async fn save_stored_audio(
+		context: &mut Context,
+		row_profile_slug: &str,
+		row_track_slug: &str,
+		new_content_hash: &str,
+		new_content_length: i64,
+	) -> Result<(), Error> {
+		use schema::soundgasm_tracks::dsl::*;
+
+		diesel::update(soundgasm_tracks.find((row_profile_slug, row_track_slug)))
+			.set((
+				content_hash.eq(new_content_hash),
+				content_length.eq(new_content_length),
+				updated_at.eq(chrono::Utc::now().naive_utc()),
+			))
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to save stored audio: {}", err)))?;
+
+		Ok(())
+	}
+
+	pub async fn clear_stored_audio(
+		context: &mut Context,
+		row_profile_slug: &str,
+		row_track_slug: &str,
+	) -> Result<(), Error> {
+		use schema::soundgasm_tracks::dsl::*;
+
+		diesel::update(soundgasm_tracks.find((row_profile_slug, row_track_slug)))
+			.set((
+				content_hash.eq(None::<String>),
+				content_length.eq(None::<i64>),
+				updated_at.eq(chrono::Utc::now().naive_utc()),
+			))
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to clear stored audio: {}", err)))?;
+
+		Ok(())
 	}
 }
 
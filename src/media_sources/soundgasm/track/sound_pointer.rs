@@ -1,10 +1,17 @@
-use std::path::PathBuf;
-
 use lazy_static::lazy_static;
 use regex::Regex;
 use reqwest::Url;
 
-use crate::{media_sources::soundgasm::SoundgasmAudioTrackRow, media_types::MediaBlobPointer};
+use crate::{
+	error::Error,
+	media_sources::{
+		html::{Document, ParseError},
+		soundgasm::SoundgasmAudioTrackRow,
+	},
+	media_types::MediaBlobPointer,
+};
+
+pub const SOUNDGASM_AUDIO_NAMESPACE: &str = "soundgasm_audio";
 
 #[derive(Clone, Debug)]
 pub struct TrackSoundPointer {
@@ -13,13 +20,20 @@ pub struct TrackSoundPointer {
 }
 
 impl TrackSoundPointer {
-	pub fn from_html(track_page_html: &String) -> Option<Self> {
-		let url_matches = TRACK_DOWNLOAD_RE.captures(track_page_html.as_str())?;
-
-		let sound_id = String::from(url_matches.get(1).unwrap().as_str());
-		let file_extension = String::from(url_matches.get(2).unwrap().as_str());
+	/// Finds the audio file in the player setup script of a track page
+	pub fn from_document(track_page: &Document) -> Result<Self, ParseError> {
+		let (sound_id, file_extension) = track_page
+			.select("script")
+			.into_iter()
+			.find_map(|script| {
+				let script = script.text();
+				let url_matches = TRACK_DOWNLOAD_RE.captures(&script)?;
+
+				Some((url_matches[1].to_string(), url_matches[2].to_string()))
+			})
+			.ok_or(ParseError::missing("track", "audio URL"))?;
 
-		Some(Self {
+		Ok(Self {
 			sound_id,
 			file_extension,
 		})
@@ -27,46 +41,41 @@ impl TrackSoundPointer {
 }
 
 impl MediaBlobPointer for TrackSoundPointer {
-	fn get_path(&self) -> PathBuf {
-		PathBuf::from(format!("{}.{}", self.sound_id, self.file_extension))
+	fn get_namespace(&self) -> String {
+		SOUNDGASM_AUDIO_NAMESPACE.to_string()
 	}
 
-	#[cfg(not(test))]
-	fn get_download_url(&self) -> Url {
-		Url::parse(
-			format!(
-				"https://media.soundgasm.net/sounds/{}.{}",
-				self.sound_id, self.file_extension
-			)
-			.as_str(),
-		)
-		.unwrap()
+	fn get_extension(&self) -> String {
+		self.file_extension.clone()
 	}
 
-	// TODO: Change this to a mock URL for testing purposes
-	#[cfg(test)]
-	fn get_download_url(&self) -> Url {
-		Url::parse(
-			format!(
-				"http://localhost:5268/sounds/{}.{}",
-				self.sound_id, self.file_extension
-			)
-			.as_str(),
-		)
-		.unwrap()
+	fn get_download_url(&self) -> Result<Url, Error> {
+		let url = format!(
+			"https://media.soundgasm.net/sounds/{}.{}",
+			self.sound_id, self.file_extension
+		);
+
+		Url::parse(&url)
+			.map_err(|err| Error::Parse(format!("Invalid Soundgasm download URL {}: {}", url, err)))
 	}
 }
 
 impl TryFrom<&SoundgasmAudioTrackRow> for TrackSoundPointer {
-	type Error = String;
+	type Error = Error;
 
 	fn try_from(value: &SoundgasmAudioTrackRow) -> Result<Self, Self::Error> {
 		let Some(sound_id) = value.sound_id.clone() else {
-			return Err("sound_id is None".to_string());
+			return Err(Error::Database(format!(
+				"Soundgasm track {}/{} has no sound id",
+				value.profile_slug, value.track_slug
+			)));
 		};
 
 		let Some(file_extension) = value.file_extension.clone() else {
-			return Err("file_extension is None".to_string());
+			return Err(Error::Database(format!(
+				"Soundgasm track {}/{} has no file extension",
+				value.profile_slug, value.track_slug
+			)));
 		};
 
 		Ok(Self {
@@ -78,21 +87,26 @@ impl TryFrom<&SoundgasmAudioTrackRow> for TrackSoundPointer {
 
 lazy_static! {
 	static ref TRACK_DOWNLOAD_RE: Regex =
-		Regex::new("//media.soundgasm.net/sounds/([^/.]+).([^\"]+)").unwrap();
+		Regex::new(r#"//media\.soundgasm\.net/sounds/([^/."]+)\.([a-zA-Z0-9]+)"#).unwrap();
 }
 
 #[cfg(test)]
 mod tests {
 	use super::TrackSoundPointer;
+	use crate::media_sources::html::{Document, ParseError};
 
 	#[test]
 	fn test_track_sound_pointer_from_html() {
-		let html =
-			include_str!("../../../../test/fixtures/http/soundgasm/profiles/sgdl-test/tracks/shopping-mall-half-open-Netherlands-207-AM-161001_0998.html")
-				.to_string();
+		let page = Document::parse(include_str!("../../../../test/fixtures/http/soundgasm/profiles/sgdl-test/tracks/shopping-mall-half-open-Netherlands-207-AM-161001_0998.html"));
 
-		let pointer = TrackSoundPointer::from_html(&html).unwrap();
+		let pointer = TrackSoundPointer::from_document(&page).unwrap();
 		assert_eq!(pointer.sound_id, "7358137704b4386f24c1b5dad8b44fbdb0cf7731");
 		assert_eq!(pointer.file_extension, "m4a");
+
+		let page = Document::parse("<script>var player = {};</script>");
+		assert_eq!(
+			TrackSoundPointer::from_document(&page).unwrap_err(),
+			ParseError::missing("track", "audio URL")
+		);
 	}
 }
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
+use super::sound_pointer::SOUNDGASM_AUDIO_NAMESPACE;
 use crate::{
-	file_store::MediaBlob,
+	error::Error,
+	file_store::{FileStore, MediaBlob, StoredBlob},
 	media_sources::soundgasm::{SoundgasmAudioTrackRow, TrackSoundPointer},
 };
 
@@ -13,14 +15,15 @@ pub struct SoundgasmTrackAudio {
 }
 
 impl TryFrom<&SoundgasmAudioTrackRow> for SoundgasmTrackAudio {
-	type Error = String;
+	type Error = Error;
 
 	fn try_from(row: &SoundgasmAudioTrackRow) -> Result<Self, Self::Error> {
-		let Some(content_length) = row.content_length.clone() else {
-			return Err("content_length is None".to_string());
-		};
-		let Some(content_hash) = row.content_hash.clone() else {
-			return Err("content_hash is None".to_string());
+		let (Some(content_hash), Some(content_length)) = (row.content_hash.clone(), row.content_length)
+		else {
+			return Err(Error::NotFound(format!(
+				"Soundgasm track {}/{} isn't stored",
+				row.profile_slug, row.track_slug
+			)));
 		};
 
 		Ok(Self {
@@ -31,12 +34,23 @@ impl TryFrom<&SoundgasmAudioTrackRow> for SoundgasmTrackAudio {
 	}
 }
 
+impl SoundgasmTrackAudio {
+	pub fn from_stored_blob(sound_pointer: TrackSoundPointer, blob: &StoredBlob) -> Self {
+		Self {
+			sound_pointer,
+			content_hash: blob.content_hash.clone(),
+			content_length: blob.content_length,
+		}
+	}
+}
+
 impl MediaBlob for SoundgasmTrackAudio {
 	fn get_path(&self) -> PathBuf {
-		PathBuf::from(format!(
-			"data/soundgasm_audio/{}.{}",
-			self.content_hash, self.sound_pointer.file_extension
-		))
+		FileStore::get_relative_blob_path(
+			SOUNDGASM_AUDIO_NAMESPACE,
+			&self.content_hash,
+			&self.sound_pointer.file_extension,
+		)
 	}
 
 	fn get_content_length(&self) -> i64 {
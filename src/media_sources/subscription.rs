@@ -0,0 +1,211 @@
+use diesel::prelude::*;
+
+use super::{kemono, recognize_pointer_from_string, soundgasm, PointerType, ProviderType};
+use crate::error::Error;
+use crate::media_types::MediaItem;
+use crate::Context;
+
+/// A profile or creator that `sync` checks for new items
+#[derive(Debug, Clone)]
+pub enum SubscriptionTarget {
+	SoundgasmProfile(soundgasm::ProfilePointer),
+	KemonoCreator(kemono::ProfilePointer),
+}
+
+/// A track or post that `sync` added to the library
+#[derive(Debug, Clone)]
+pub struct SyncedItem {
+	pub title: String,
+	pub url: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+	pub new_items: Vec<SyncedItem>,
+	/// URLs of new items that couldn't be added, with the reason. They are
+	/// tried again on the next sync.
+	pub failed: Vec<(String, Error)>,
+}
+
+impl SubscriptionTarget {
+	/// Tracks and posts subscribe to the profile they were published on
+	pub fn from_pointer(pointer: PointerType) -> Self {
+		match pointer {
+			PointerType::SoundgasmTrack(track) => {
+				Self::SoundgasmProfile(soundgasm::ProfilePointer::from(track))
+			}
+			PointerType::SoundgasmProfile(profile) => Self::SoundgasmProfile(profile),
+			PointerType::KemonoPost(post) => Self::KemonoCreator(post.creator),
+			PointerType::KemonoProfile(creator) => Self::KemonoCreator(creator),
+		}
+	}
+
+	pub fn from_url(url: &str) -> Option<Self> {
+		recognize_pointer_from_string(url).map(Self::from_pointer)
+	}
+
+	pub fn get_source(&self) -> ProviderType {
+		match self {
+			Self::SoundgasmProfile(_) => ProviderType::Soundgasm,
+			Self::KemonoCreator(_) => ProviderType::Kemono,
+		}
+	}
+
+	/// Canonical URL of the profile, used to tell subscriptions apart
+	pub fn get_url(&self) -> String {
+		match self {
+			Self::SoundgasmProfile(profile) => profile.get_url(),
+			Self::KemonoCreator(creator) => creator.to_url(),
+		}
+	}
+
+	/// Adds everything published since the last sync to the library
+	pub async fn sync(&self, context: &mut Context) -> Result<SyncReport, Error> {
+		match self {
+			Self::SoundgasmProfile(profile) => {
+				let summary = profile.sync(context).await?;
+
+				Ok(SyncReport {
+					new_items: summary
+						.added
+						.iter()
+						.map(|track| SyncedItem {
+							title: track.get_title(),
+							url: track.pointer.to_url(),
+						})
+						.collect(),
+					failed: summary
+						.failed
+						.into_iter()
+						.map(|(track_pointer, err)| (track_pointer.to_url(), err))
+						.collect(),
+				})
+			}
+			Self::KemonoCreator(creator) => Ok(SyncReport {
+				new_items: creator
+					.sync(context)
+					.await?
+					.into_iter()
+					.map(|post| SyncedItem {
+						url: kemono::PostPointer {
+							creator: creator.clone(),
+							post_id: post.post_id,
+						}
+						.to_url(),
+						title: post.title,
+					})
+					.collect(),
+				failed: Vec::new(),
+			}),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::subscriptions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Subscription {
+	pub id: i32,
+	pub source: String,
+	pub url: String,
+	pub last_synced_at: Option<chrono::NaiveDateTime>,
+}
+
+impl Subscription {
+	/// Returns false if the profile was already subscribed to.
+	pub async fn add(context: &mut Context, target: &SubscriptionTarget) -> Result<bool, Error> {
+		use crate::schema::subscriptions::dsl::*;
+
+		let inserted = diesel::insert_into(subscriptions)
+			.values((
+				source.eq(target.get_source().as_ref().to_lowercase()),
+				url.eq(target.get_url()),
+				created_at.eq(chrono::Utc::now().naive_utc()),
+			))
+			.on_conflict(url)
+			.do_nothing()
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to add subscription: {}", err)))?;
+
+		Ok(inserted > 0)
+	}
+
+	/// Oldest subscription first
+	pub async fn list(context: &mut Context) -> Result<Vec<Self>, Error> {
+		use crate::schema::subscriptions::dsl::*;
+
+		subscriptions
+			.order(id)
+			.select(Self::as_select())
+			.load(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load subscriptions: {}", err)))
+	}
+
+	pub fn get_target(&self) -> Option<SubscriptionTarget> {
+		SubscriptionTarget::from_url(&self.url)
+	}
+
+	pub async fn set_synced(
+		&mut self,
+		context: &mut Context,
+		synced_at: chrono::NaiveDateTime,
+	) -> Result<(), Error> {
+		use crate::schema::subscriptions::dsl::*;
+
+		diesel::update(subscriptions.filter(id.eq(self.id)))
+			.set(last_synced_at.eq(synced_at))
+			.execute(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to update subscription: {}", err)))?;
+
+		self.last_synced_at = Some(synced_at);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Subscription, SubscriptionTarget};
+	use crate::context::test_context;
+
+	#[test]
+	fn test_subscription_target_from_url() {
+		let target = SubscriptionTarget::from_url(
+			"https://soundgasm.net/u/sgdl-test/shopping-mall-half-open-Netherlands-207-AM-161001_0998",
+		)
+		.unwrap();
+		assert_eq!(target.get_url(), "https://soundgasm.net/u/sgdl-test");
+
+		let target =
+			SubscriptionTarget::from_url("https://coomer.su/onlyfans/user/heidiv/post/29526377").unwrap();
+		assert_eq!(target.get_url(), "https://coomer.su/onlyfans/user/heidiv");
+
+		assert!(SubscriptionTarget::from_url("https://example.com/u/sgdl-test").is_none());
+	}
+
+	#[tokio::test]
+	async fn test_subscribe_once_per_profile() {
+		let mut context = test_context();
+
+		let profile = SubscriptionTarget::from_url("https://soundgasm.net/u/sgdl-test").unwrap();
+		let track =
+			SubscriptionTarget::from_url("https://soundgasm.net/u/sgdl-test/some-track").unwrap();
+
+		assert!(Subscription::add(&mut context, &profile).await.unwrap());
+		assert!(!Subscription::add(&mut context, &track).await.unwrap());
+
+		let mut subscriptions = Subscription::list(&mut context).await.unwrap();
+		assert_eq!(subscriptions.len(), 1);
+		assert_eq!(subscriptions[0].source, "soundgasm");
+		assert!(subscriptions[0].last_synced_at.is_none());
+
+		let synced_at = chrono::Utc::now().naive_utc();
+		subscriptions[0]
+			.set_synced(&mut context, synced_at)
+			.await
+			.unwrap();
+
+		let subscriptions = Subscription::list(&mut context).await.unwrap();
+		assert_eq!(subscriptions[0].last_synced_at, Some(synced_at));
+	}
+}
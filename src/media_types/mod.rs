@@ -1,10 +1,20 @@
-use std::path::PathBuf;
+use std::str::FromStr;
 
 use reqwest::Url;
 
-use crate::{media_sources::ProviderType, Context};
+use crate::{error::Error, http_client::HttpClient, media_sources::ProviderType, Context};
 
-#[derive(strum_macros::Display, strum_macros::AsRefStr)]
+mod revision;
+mod search;
+mod tags;
+
+pub use revision::MetadataVersion;
+pub use search::{build_match_query, LibraryItem, SearchFilters};
+pub use tags::{
+	extract_tags, list_tags_with_counts, normalize_tag, tagged_items_subquery, TaggedItem,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::AsRefStr)]
 pub enum MediaType {
 	AudioMp3 = 0,
 	VideoMp4 = 100,
@@ -16,7 +26,7 @@ pub enum MediaType {
 }
 
 impl MediaType {
-	fn get_extension(&self) -> &str {
+	pub fn get_extension(&self) -> &str {
 		match self {
 			Self::AudioMp3 => "mp3",
 			Self::VideoMp4 => "mp4",
@@ -27,6 +37,73 @@ impl MediaType {
 			Self::Pdf => "pdf",
 		}
 	}
+
+	/// Broad kind of media, used to group blobs on disk
+	pub fn get_category(&self) -> &str {
+		match self {
+			Self::AudioMp3 => "audio",
+			Self::VideoMp4 | Self::VideoWebm => "video",
+			Self::ImageJpg | Self::ImagePng => "image",
+			Self::Text | Self::Pdf => "document",
+		}
+	}
+
+	pub fn from_extension(extension: &str) -> Option<Self> {
+		match extension.to_ascii_lowercase().as_str() {
+			"mp3" => Some(Self::AudioMp3),
+			"mp4" | "m4v" => Some(Self::VideoMp4),
+			"webm" => Some(Self::VideoWebm),
+			"jpg" | "jpeg" | "jpe" => Some(Self::ImageJpg),
+			"png" => Some(Self::ImagePng),
+			"txt" => Some(Self::Text),
+			"pdf" => Some(Self::Pdf),
+			_ => None,
+		}
+	}
+
+	/// Detects the type from the extension of a file name or URL path.
+	pub fn from_path(path: &str) -> Option<Self> {
+		let file_name = path.rsplit('/').next()?;
+		let (_, extension) = file_name.rsplit_once('.')?;
+
+		Self::from_extension(extension)
+	}
+
+	/// Parses a `Content-Type` header value, ignoring any parameters.
+	pub fn from_content_type(content_type: &str) -> Option<Self> {
+		let mime_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+
+		match mime_type.as_str() {
+			"audio/mpeg" | "audio/mp3" => Some(Self::AudioMp3),
+			"video/mp4" => Some(Self::VideoMp4),
+			"video/webm" => Some(Self::VideoWebm),
+			"image/jpeg" | "image/jpg" => Some(Self::ImageJpg),
+			"image/png" => Some(Self::ImagePng),
+			"text/plain" => Some(Self::Text),
+			"application/pdf" => Some(Self::Pdf),
+			_ => None,
+		}
+	}
+}
+
+/// Parses the names written by `Display`, as stored in the database
+impl FromStr for MediaType {
+	type Err = String;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		[
+			Self::AudioMp3,
+			Self::VideoMp4,
+			Self::VideoWebm,
+			Self::ImageJpg,
+			Self::ImagePng,
+			Self::Text,
+			Self::Pdf,
+		]
+		.into_iter()
+		.find(|media_type| media_type.as_ref() == name)
+		.ok_or_else(|| format!("Unknown media type: {}", name))
+	}
 }
 
 // impl Display for MediaType {
@@ -50,14 +127,16 @@ impl MediaType {
 
 pub trait MediaPointer {
 	#[allow(warnings)]
-	async fn fetch_metadata(&self) -> Vec<impl MediaMetadata>;
+	async fn fetch_metadata(&self, http: &HttpClient) -> Vec<impl MediaMetadata>;
 	#[allow(warnings)]
-	async fn fetch_blob_pointer(&self) -> Option<impl MediaBlobPointer>;
+	async fn fetch_blob_pointer(&self, http: &HttpClient) -> Option<impl MediaBlobPointer>;
 }
 
 pub trait MediaBlobPointer {
-	fn get_path(&self) -> PathBuf;
-	fn get_download_url(&self) -> Url;
+	/// Directory under `data/` that blobs of this kind are stored in
+	fn get_namespace(&self) -> String;
+	fn get_extension(&self) -> String;
+	fn get_download_url(&self) -> Result<Url, Error>;
 }
 
 pub trait MediaMetadata {
@@ -75,9 +154,48 @@ where
 	fn get_description(&self) -> String;
 	fn get_author(&self) -> String;
 
-	fn get_pointer(&self) -> impl MediaPointer;
 	fn get_blob_pointer(&self) -> impl MediaBlobPointer;
 
 	#[allow(warnings)]
-	async fn search(context: &mut Context, query: &str) -> Vec<Self>;
+	async fn search(context: &mut Context, query: &str) -> Result<Vec<Self>, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::MediaType;
+
+	#[test]
+	fn test_media_type_from_path() {
+		assert_eq!(
+			MediaType::from_path("/d7/d1/d7d1a2.JPEG"),
+			Some(MediaType::ImageJpg)
+		);
+		assert_eq!(
+			MediaType::from_path("clip.webm"),
+			Some(MediaType::VideoWebm)
+		);
+		assert_eq!(MediaType::from_path("/data/archive.zip"), None);
+		assert_eq!(MediaType::from_path("/data/no_extension"), None);
+	}
+
+	#[test]
+	fn test_media_type_from_content_type() {
+		assert_eq!(
+			MediaType::from_content_type("application/pdf; charset=binary"),
+			Some(MediaType::Pdf)
+		);
+		assert_eq!(
+			MediaType::from_content_type("audio/mpeg"),
+			Some(MediaType::AudioMp3)
+		);
+		assert_eq!(MediaType::from_content_type("application/zip"), None);
+	}
+
+	#[test]
+	fn test_media_type_round_trips_through_name() {
+		let media_type = MediaType::VideoMp4;
+		assert_eq!(MediaType::from_str(media_type.as_ref()), Ok(media_type));
+	}
 }
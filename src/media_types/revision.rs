@@ -0,0 +1,113 @@
+/// One version of an item's title and description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataVersion {
+	pub title: String,
+	pub description: String,
+	/// When this version was first seen
+	pub since: chrono::NaiveDateTime,
+}
+
+impl MetadataVersion {
+	/// Puts the replaced versions of an item and its current one in order,
+	/// oldest first. `revisions` hold each replaced title and description
+	/// with the time it was replaced, oldest first, so each version starts
+	/// when the one before it was replaced.
+	pub fn build_history(
+		created_at: chrono::NaiveDateTime,
+		revisions: Vec<(String, String, chrono::NaiveDateTime)>,
+		current: (String, String),
+	) -> Vec<Self> {
+		let mut history = Vec::with_capacity(revisions.len() + 1);
+		let mut since = created_at;
+
+		for (title, description, replaced_at) in revisions {
+			history.push(Self {
+				title,
+				description,
+				since,
+			});
+			since = replaced_at;
+		}
+
+		let (title, description) = current;
+		history.push(Self {
+			title,
+			description,
+			since,
+		});
+
+		history
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use diesel::RunQueryDsl;
+
+	use crate::context::test_context;
+	use crate::media_sources::{kemono::PostPointer, soundgasm::TrackPointer};
+
+	#[tokio::test]
+	async fn test_history_records_changes() {
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO soundgasm_tracks (profile_slug, track_slug, title, description, created_at, updated_at) \
+			VALUES ('sgdl-test', 'rain', 'Rain', 'First', '2026-10-01 00:00:00', '2026-10-01 00:00:00')",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+
+		for statement in [
+			// Unchanged metadata isn't a new revision
+			"UPDATE soundgasm_tracks SET title = 'Rain', updated_at = '2026-10-02 00:00:00'",
+			"UPDATE soundgasm_tracks SET description = 'Second'",
+			"UPDATE soundgasm_tracks SET title = '[F4M] Rain'",
+		] {
+			diesel::sql_query(statement)
+				.execute(&mut context.conn)
+				.unwrap();
+		}
+
+		let track = TrackPointer::from_url("https://soundgasm.net/u/sgdl-test/rain").unwrap();
+		let history = track.get_history(&mut context).await.unwrap();
+
+		let versions = history
+			.iter()
+			.map(|version| (version.title.as_str(), version.description.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			versions,
+			vec![
+				("Rain", "First"),
+				("Rain", "Second"),
+				("[F4M] Rain", "Second")
+			]
+		);
+		assert_eq!(history[0].since.to_string(), "2026-10-01 00:00:00");
+
+		let missing = TrackPointer::from_url("https://soundgasm.net/u/sgdl-test/missing").unwrap();
+		assert!(missing.get_history(&mut context).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_history_records_kemono_edits() {
+		let mut context = test_context();
+
+		diesel::sql_query(
+			"INSERT INTO kemono_posts VALUES ('coomer.su', 'onlyfans', 'creator', '1', 'Beach day', 'Photos', NULL, NULL, '2026-10-18 00:00:00', '2026-10-18 00:00:00', NULL)",
+		)
+		.execute(&mut context.conn)
+		.unwrap();
+		diesel::sql_query("UPDATE kemono_posts SET content = 'Photos and a video'")
+			.execute(&mut context.conn)
+			.unwrap();
+
+		let post = PostPointer::from_url("https://coomer.su/onlyfans/user/creator/post/1").unwrap();
+		let history = post.get_history(&mut context).await.unwrap();
+
+		assert_eq!(history.len(), 2);
+		assert_eq!(history[0].description, "Photos");
+		assert_eq!(history[1].description, "Photos and a video");
+	}
+}
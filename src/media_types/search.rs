@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+
+use crate::file_store::{BlobStatus, FileStore, MediaBlob, StoredBlob};
+use crate::media_sources::{
+	kemono::KemonoPostAttachment, soundgasm::SoundgasmAudioTrack, ProviderType,
+};
+
+use super::{normalize_tag, MediaItem, MediaType, TaggedItem};
+use crate::progress::ProgressSender;
+use crate::{error::Error, Context};
+
+/// Most results returned from each source for one search
+pub const SEARCH_RESULT_LIMIT: i64 = 200;
+
+/// Narrows down `Context::search`. Anything left unset matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+	pub media_type: Option<MediaType>,
+	/// Results must have every one of these tags
+	pub tags: Vec<String>,
+	/// Soundgasm profile slug or Kemono creator id
+	pub author: Option<String>,
+	pub is_downloaded: Option<bool>,
+	/// Whether the item has been removed from its source since it was
+	/// cataloged
+	pub is_deleted: Option<bool>,
+	/// Compared with when the item was published, or when it was added to
+	/// the library if the source doesn't say
+	pub since: Option<chrono::NaiveDateTime>,
+	pub until: Option<chrono::NaiveDateTime>,
+	/// Defaults to `SEARCH_RESULT_LIMIT`
+	pub limit: Option<i64>,
+}
+
+impl SearchFilters {
+	pub fn get_limit(&self) -> i64 {
+		self.limit.unwrap_or(SEARCH_RESULT_LIMIT)
+	}
+
+	/// Normalizes the tags and drops duplicates, since every tag given is
+	/// counted when matching.
+	pub fn normalized(&self) -> Self {
+		let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+		for tag in self.tags.iter().filter_map(|tag| normalize_tag(tag)) {
+			if !tags
+				.iter()
+				.any(|existing| existing.eq_ignore_ascii_case(&tag))
+			{
+				tags.push(tag);
+			}
+		}
+
+		Self {
+			tags,
+			..self.clone()
+		}
+	}
+
+	/// True if there is anything to list even without a text query
+	pub fn is_narrowing(&self) -> bool {
+		!self.tags.is_empty()
+			|| self.author.is_some()
+			|| self.is_downloaded.is_some()
+			|| self.is_deleted.is_some()
+			|| self.since.is_some()
+			|| self.until.is_some()
+			|| self.media_type.is_some()
+	}
+}
+
+/// Turns what the user typed into an FTS5 query. Every word has to match,
+/// `"quoted text"` matches as a phrase and a trailing `*` matches words
+/// starting with the prefix. Everything else is quoted, so FTS5 operators
+/// in the input are searched for as text instead of being interpreted.
+pub fn build_match_query(query: &str) -> Option<String> {
+	let mut terms = Vec::new();
+	let mut chars = query.chars().peekable();
+
+	while let Some(&next) = chars.peek() {
+		if next.is_whitespace() {
+			chars.next();
+			continue;
+		}
+
+		let text = if next == '"' {
+			chars.next();
+			chars.by_ref().take_while(|&c| c != '"').collect::<String>()
+		} else {
+			let mut word = String::new();
+			while let Some(&c) = chars.peek() {
+				if c.is_whitespace() || c == '"' {
+					break;
+				}
+				word.push(c);
+				chars.next();
+			}
+			word
+		};
+
+		let (text, is_prefix) = match text.strip_suffix('*') {
+			Some(text) => (text.to_string(), true),
+			None => (text, chars.next_if_eq(&'*').is_some()),
+		};
+
+		// Quote characters can't appear here, but stray `*` can
+		let text = text.replace('*', " ");
+
+		// A term without any letters or digits has no tokens and can't match
+		if !text.chars().any(char::is_alphanumeric) {
+			continue;
+		}
+
+		terms.push(format!(
+			"\"{}\"{}",
+			text.trim(),
+			if is_prefix { "*" } else { "" }
+		));
+	}
+
+	if terms.is_empty() {
+		None
+	} else {
+		Some(terms.join(" "))
+	}
+}
+
+/// Anything in the library that can be returned from a search
+#[derive(Debug, Clone)]
+pub enum LibraryItem {
+	SoundgasmTrack(SoundgasmAudioTrack),
+	KemonoAttachment(KemonoPostAttachment),
+}
+
+impl LibraryItem {
+	pub fn get_source(&self) -> ProviderType {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_source(),
+			Self::KemonoAttachment(attachment) => attachment.get_source(),
+		}
+	}
+
+	pub fn get_type(&self) -> MediaType {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_type(),
+			Self::KemonoAttachment(attachment) => attachment.get_type(),
+		}
+	}
+
+	pub fn get_title(&self) -> String {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_title(),
+			Self::KemonoAttachment(attachment) => attachment.get_title(),
+		}
+	}
+
+	pub fn get_description(&self) -> String {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_description(),
+			Self::KemonoAttachment(attachment) => attachment.get_description(),
+		}
+	}
+
+	pub fn get_author(&self) -> String {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_author(),
+			Self::KemonoAttachment(attachment) => attachment.get_author(),
+		}
+	}
+
+	/// Page of the item on the site it came from
+	pub fn get_url(&self) -> String {
+		match self {
+			Self::SoundgasmTrack(track) => track.pointer.to_url(),
+			Self::KemonoAttachment(attachment) => attachment.post.to_url(),
+		}
+	}
+
+	pub fn get_label(&self) -> String {
+		match self {
+			Self::SoundgasmTrack(track) => format!(
+				"{}/{}",
+				track.pointer.profile_slug, track.pointer.track_slug
+			),
+			Self::KemonoAttachment(attachment) => attachment.get_label(),
+		}
+	}
+
+	/// Path of the stored file relative to the `FileStore`, if downloaded
+	pub fn get_stored_path(&self) -> Option<PathBuf> {
+		match self {
+			Self::SoundgasmTrack(track) => track.stored_audio.as_ref().map(|blob| blob.get_path()),
+			Self::KemonoAttachment(attachment) => attachment
+				.stored_attachment
+				.as_ref()
+				.map(|blob| blob.get_path()),
+		}
+	}
+
+	/// Size in bytes recorded for the stored file, if downloaded
+	pub fn get_stored_size(&self) -> Option<u64> {
+		let content_length = match self {
+			Self::SoundgasmTrack(track) => track.stored_audio.as_ref()?.get_content_length(),
+			Self::KemonoAttachment(attachment) => {
+				attachment.stored_attachment.as_ref()?.get_content_length()
+			}
+		};
+
+		Some(content_length as u64)
+	}
+
+	pub async fn verify_stored(
+		&self,
+		file_store: &FileStore,
+		progress: &ProgressSender,
+	) -> BlobStatus {
+		let status = match self {
+			Self::SoundgasmTrack(track) => match &track.stored_audio {
+				Some(blob) => Some(blob.verify(file_store, progress).await),
+				None => None,
+			},
+			Self::KemonoAttachment(attachment) => match &attachment.stored_attachment {
+				Some(blob) => Some(blob.verify(file_store, progress).await),
+				None => None,
+			},
+		};
+
+		status.unwrap_or(BlobStatus::Missing)
+	}
+
+	/// Records a finished download on the item and in the library. Only
+	/// the stored file is saved, the item's metadata may be older than the
+	/// library's by now.
+	pub async fn set_stored_blob(&mut self, blob: &StoredBlob, context: &mut Context) {
+		let result = match self {
+			Self::SoundgasmTrack(track) => track.set_stored_audio(blob, context).await,
+			Self::KemonoAttachment(attachment) => attachment.set_stored_attachment(blob, context).await,
+		};
+
+		if let Err(err) = result {
+			log::error!("{}", err);
+		}
+	}
+
+	/// Forgets the stored file so the next download run fetches it again
+	pub async fn clear_stored(&mut self, context: &mut Context) -> Result<(), Error> {
+		match self {
+			Self::SoundgasmTrack(track) => track.clear_stored_audio(context).await,
+			Self::KemonoAttachment(attachment) => attachment.clear_stored_attachment(context).await,
+		}
+	}
+
+	/// Kemono tags belong to the post, so they are shared by its attachments
+	pub fn get_tagged_item(&self) -> TaggedItem {
+		match self {
+			Self::SoundgasmTrack(track) => track.get_tagged_item(),
+			Self::KemonoAttachment(attachment) => attachment.post.get_tagged_item(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::build_match_query;
+
+	#[test]
+	fn test_build_match_query() {
+		assert_eq!(
+			build_match_query("rainy night").as_deref(),
+			Some("\"rainy\" \"night\"")
+		);
+		assert_eq!(
+			build_match_query("\"rainy night\" walk*").as_deref(),
+			Some("\"rainy night\" \"walk\"*")
+		);
+		assert_eq!(
+			build_match_query("\"half open\"* AND").as_deref(),
+			Some("\"half open\"* \"AND\"")
+		);
+		assert_eq!(
+			build_match_query("title:foo NEAR(").as_deref(),
+			Some("\"title:foo\" \"NEAR(\"")
+		);
+		assert_eq!(build_match_query("  - \"\" * ").as_deref(), None);
+	}
+}
@@ -0,0 +1,246 @@
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{error::Error, Context};
+
+/// Longest bracketed text that is still treated as a tag
+const MAX_TAG_LENGTH: usize = 40;
+
+/// Pulls `[bracketed]` tags like `[F4M] [Whisper]` out of a title or
+/// description, in order and without duplicates.
+pub fn extract_tags(text: &str) -> Vec<String> {
+	let mut tags: Vec<String> = Vec::new();
+
+	for captures in TAG_RE.captures_iter(text) {
+		let Some(tag) = normalize_tag(&captures[1]) else {
+			continue;
+		};
+
+		if !tags
+			.iter()
+			.any(|existing| existing.eq_ignore_ascii_case(&tag))
+		{
+			tags.push(tag);
+		}
+	}
+
+	tags
+}
+
+/// Collapses whitespace. Returns `None` for text that can't be a tag.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+	let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+
+	if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+		return None;
+	}
+
+	Some(tag)
+}
+
+/// SQL selecting the `item_id`s of one source that have every one of
+/// `tag_count` tags. The source and each tag name are bound in that order.
+pub fn tagged_items_subquery(tag_count: usize) -> String {
+	format!(
+		"SELECT `item_tags`.`item_id` FROM `item_tags` \
+		JOIN `tags` ON `tags`.`id` = `item_tags`.`tag_id` \
+		WHERE `item_tags`.`source` = ? AND `tags`.`name` IN ({}) \
+		GROUP BY `item_tags`.`item_id` \
+		HAVING COUNT(DISTINCT `tags`.`id`) = {}",
+		vec!["?"; tag_count].join(", "),
+		tag_count
+	)
+}
+
+/// A library item that tags can be attached to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedItem {
+	pub source: &'static str,
+	pub item_id: String,
+}
+
+impl TaggedItem {
+	pub const SOUNDGASM_SOURCE: &'static str = "soundgasm";
+	pub const KEMONO_SOURCE: &'static str = "kemono";
+
+	pub fn soundgasm_track(profile_slug: &str, track_slug: &str) -> Self {
+		Self {
+			source: Self::SOUNDGASM_SOURCE,
+			item_id: format!("{}/{}", profile_slug, track_slug),
+		}
+	}
+
+	pub fn kemono_post(service: &str, creator_id: &str, post_id: &str) -> Self {
+		Self {
+			source: Self::KEMONO_SOURCE,
+			item_id: format!("{}/{}/{}", service, creator_id, post_id),
+		}
+	}
+
+	pub async fn get_tags(&self, context: &mut Context) -> Result<Vec<String>, Error> {
+		use crate::schema::{item_tags, tags};
+
+		item_tags::table
+			.inner_join(tags::table)
+			.filter(item_tags::source.eq(self.source))
+			.filter(item_tags::item_id.eq(&self.item_id))
+			.order(tags::name)
+			.select(tags::name)
+			.load::<String>(&mut context.conn)
+			.map_err(|err| Error::Database(format!("Failed to load tags: {}", err)))
+	}
+
+	/// Replaces the tags extracted from the item's metadata. Tags that were
+	/// added by hand are kept.
+	pub async fn set_extracted_tags(
+		&self,
+		context: &mut Context,
+		names: &[String],
+	) -> Result<(), Error> {
+		use crate::schema::item_tags::dsl::*;
+
+		context
+			.conn
+			.transaction(|conn| {
+				diesel::delete(
+					item_tags
+						.filter(source.eq(self.source))
+						.filter(item_id.eq(&self.item_id))
+						.filter(is_manual.eq(false)),
+				)
+				.execute(conn)?;
+
+				for name in names {
+					let new_tag_id = find_or_create_tag(conn, name)?;
+
+					diesel::insert_into(item_tags)
+						.values(ItemTagRow::new(new_tag_id, self, false))
+						.on_conflict((tag_id, source, item_id))
+						.do_nothing()
+						.execute(conn)?;
+				}
+
+				Ok(())
+			})
+			.map_err(|err: diesel::result::Error| {
+				Error::Database(format!("Failed to save extracted tags: {}", err))
+			})
+	}
+
+	/// Tags the item by hand. Manual tags survive rescans.
+	pub async fn add_tags(&self, context: &mut Context, names: &[String]) -> Result<(), Error> {
+		use crate::schema::item_tags::dsl::*;
+
+		context
+			.conn
+			.transaction(|conn| {
+				for name in names {
+					let new_tag_id = find_or_create_tag(conn, name)?;
+
+					diesel::insert_into(item_tags)
+						.values(ItemTagRow::new(new_tag_id, self, true))
+						.on_conflict((tag_id, source, item_id))
+						.do_update()
+						.set(is_manual.eq(true))
+						.execute(conn)?;
+				}
+
+				Ok(())
+			})
+			.map_err(|err: diesel::result::Error| Error::Database(format!("Failed to add tags: {}", err)))
+	}
+
+	/// Returns how many of the tags were removed. Extracted tags come back
+	/// if they are still in the metadata the next time the item is scanned.
+	pub async fn remove_tags(&self, context: &mut Context, names: &[String]) -> Result<usize, Error> {
+		use crate::schema::{item_tags, tags};
+
+		let tag_ids = tags::table
+			.filter(tags::name.eq_any(names))
+			.select(tags::id);
+
+		diesel::delete(
+			item_tags::table
+				.filter(item_tags::source.eq(self.source))
+				.filter(item_tags::item_id.eq(&self.item_id))
+				.filter(item_tags::tag_id.eq_any(tag_ids)),
+		)
+		.execute(&mut context.conn)
+		.map_err(|err| Error::Database(format!("Failed to remove tags: {}", err)))
+	}
+}
+
+/// Tag names are case-insensitive, the first spelling seen is kept.
+fn find_or_create_tag(conn: &mut SqliteConnection, tag_name: &str) -> QueryResult<i32> {
+	use crate::schema::tags::dsl::*;
+
+	diesel::insert_into(tags)
+		.values((
+			name.eq(tag_name),
+			created_at.eq(chrono::Utc::now().naive_utc()),
+		))
+		.on_conflict(name)
+		.do_nothing()
+		.execute(conn)?;
+
+	tags.filter(name.eq(tag_name)).select(id).first(conn)
+}
+
+/// Every tag with the number of items it is attached to, most used first.
+pub async fn list_tags_with_counts(context: &mut Context) -> Result<Vec<(String, i64)>, Error> {
+	use crate::schema::{item_tags, tags};
+	use diesel::dsl::count_star;
+
+	tags::table
+		.inner_join(item_tags::table)
+		.group_by((tags::id, tags::name))
+		.select((tags::name, count_star()))
+		.order((count_star().desc(), tags::name))
+		.load::<(String, i64)>(&mut context.conn)
+		.map_err(|err| Error::Database(format!("Failed to list tags: {}", err)))
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::item_tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+struct ItemTagRow {
+	tag_id: i32,
+	source: String,
+	item_id: String,
+	is_manual: bool,
+	created_at: chrono::NaiveDateTime,
+}
+
+impl ItemTagRow {
+	fn new(tag_id: i32, item: &TaggedItem, is_manual: bool) -> Self {
+		Self {
+			tag_id,
+			source: item.source.to_string(),
+			item_id: item.item_id.clone(),
+			is_manual,
+			created_at: chrono::Utc::now().naive_utc(),
+		}
+	}
+}
+
+lazy_static! {
+	static ref TAG_RE: Regex = Regex::new(r"\[([^\[\]]+)\]").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::extract_tags;
+
+	#[test]
+	fn test_extract_tags() {
+		assert_eq!(
+			extract_tags("[F4M] [Whisper]  Rainy night [ soft  spoken ] [f4m]"),
+			vec!["F4M", "Whisper", "soft spoken"]
+		);
+		assert_eq!(extract_tags("No tags [] here ["), Vec::<String>::new());
+		assert!(
+			extract_tags("[This bracketed sentence is far too long to be a tag of any kind]").is_empty()
+		);
+	}
+}
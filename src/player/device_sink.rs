@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use rodio::{OutputStream, OutputStreamBuilder, Sink};
+
+use super::{AudioSink, AudioSource};
+use crate::error::Error;
+
+/// Plays through the default sound card
+pub struct DeviceSink {
+	/// Sound stops once the stream is dropped
+	stream: OutputStream,
+	sink: Sink,
+}
+
+impl DeviceSink {
+	pub fn open() -> Result<Self, Error> {
+		let mut stream = OutputStreamBuilder::open_default_stream()
+			.map_err(|err| Error::Config(format!("Unable to open the audio device: {}", err)))?;
+		// It would print to stderr, over the TUI
+		stream.log_on_drop(false);
+
+		let sink = Sink::connect_new(stream.mixer());
+
+		Ok(Self { stream, sink })
+	}
+}
+
+impl AudioSink for DeviceSink {
+	fn play(&mut self, source: AudioSource) {
+		// Dropping the old sink stops it, without waiting for the sound
+		// card like clearing it would
+		self.sink = Sink::connect_new(self.stream.mixer());
+		self.sink.append(source);
+	}
+
+	fn pause(&mut self) {
+		self.sink.pause();
+	}
+
+	fn resume(&mut self) {
+		self.sink.play();
+	}
+
+	fn is_paused(&self) -> bool {
+		self.sink.is_paused()
+	}
+
+	fn seek(&mut self, position: Duration) -> Result<(), Error> {
+		self
+			.sink
+			.try_seek(position)
+			.map_err(|err| Error::InvalidInput(format!("Unable to seek: {}", err)))
+	}
+
+	fn get_position(&self) -> Duration {
+		self.sink.get_pos()
+	}
+
+	fn set_volume(&mut self, volume: f32) {
+		self.sink.set_volume(volume);
+	}
+
+	fn is_finished(&self) -> bool {
+		self.sink.empty()
+	}
+}
@@ -0,0 +1,462 @@
+#[cfg(feature = "audio-output")]
+mod device_sink;
+mod null_sink;
+mod stream_buffer;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::{header::HeaderMap, Method, Url};
+use rodio::{Decoder, Source};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use crate::error::Error;
+use crate::file_store::{FileStore, MediaBlob};
+use crate::http_client::{check_status, HttpClient};
+use crate::media_sources::soundgasm::SoundgasmAudioTrack;
+use crate::media_sources::ProviderType;
+use crate::media_types::MediaBlobPointer;
+
+#[cfg(feature = "audio-output")]
+use device_sink::DeviceSink;
+use null_sink::NullSink;
+use stream_buffer::StreamBuffer;
+
+/// A decoded track, ready to be played by an `AudioSink`
+pub type AudioSource = Box<dyn Source + Send>;
+
+/// Where the sound goes
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioOutput {
+	/// the default sound card
+	Device,
+	/// nowhere, only the position is kept, for running without a sound card
+	Null,
+}
+
+/// The sound card if sgdl was built with it
+impl Default for AudioOutput {
+	fn default() -> Self {
+		match cfg!(feature = "audio-output") {
+			true => Self::Device,
+			false => Self::Null,
+		}
+	}
+}
+
+impl AudioOutput {
+	pub fn open(self) -> Result<Box<dyn AudioSink>, Error> {
+		match self {
+			Self::Device => open_device(),
+			Self::Null => Ok(Box::new(NullSink::default())),
+		}
+	}
+}
+
+#[cfg(feature = "audio-output")]
+fn open_device() -> Result<Box<dyn AudioSink>, Error> {
+	Ok(Box::new(DeviceSink::open()?))
+}
+
+#[cfg(not(feature = "audio-output"))]
+fn open_device() -> Result<Box<dyn AudioSink>, Error> {
+	Err(Error::Config(
+		"sgdl was built without the audio-output feature, only the null audio output is available"
+			.to_string(),
+	))
+}
+
+/// Plays one source at a time
+pub trait AudioSink {
+	/// Stops what was playing and starts `source` from the beginning
+	fn play(&mut self, source: AudioSource);
+	fn pause(&mut self);
+	fn resume(&mut self);
+	fn is_paused(&self) -> bool;
+	fn seek(&mut self, position: Duration) -> Result<(), Error>;
+	fn get_position(&self) -> Duration;
+	/// 1.0 plays the source as it is
+	fn set_volume(&mut self, volume: f32);
+	/// True once the source has played to its end
+	fn is_finished(&self) -> bool;
+}
+
+/// Where a track is played from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaybackSource {
+	File(PathBuf),
+	/// Played while it downloads, for tracks that aren't stored yet
+	Stream(Url),
+}
+
+impl PlaybackSource {
+	/// The stored file if the track was downloaded, its soundgasm media URL
+	/// otherwise
+	pub fn from_track(track: &SoundgasmAudioTrack, file_store: &FileStore) -> Result<Self, Error> {
+		let stored_path = track
+			.stored_audio
+			.as_ref()
+			.map(|audio| file_store.resolve_path(audio.get_path()))
+			.filter(|path| path.exists());
+
+		match stored_path {
+			Some(path) => Ok(Self::File(path)),
+			None => Ok(Self::Stream(track.sound_pointer.get_download_url()?)),
+		}
+	}
+
+	/// Opens and decodes the source off the async threads, since decoding
+	/// a stream waits for its bytes to arrive
+	async fn open(self, http: HttpClient) -> Result<AudioSource, Error> {
+		fn decode_error(err: rodio::decoder::DecoderError) -> Error {
+			Error::Parse(format!("Unable to decode audio: {}", err))
+		}
+
+		let source = match self {
+			Self::File(path) => {
+				tokio::task::spawn_blocking(move || {
+					let file = File::open(&path).map_err(|err| {
+						Error::Filesystem(format!("Unable to open {}: {}", path.display(), err))
+					})?;
+					let byte_len = file.metadata().map(|metadata| metadata.len()).ok();
+
+					let mut builder = Decoder::builder()
+						.with_data(BufReader::new(file))
+						.with_seekable(true);
+					if let Some(byte_len) = byte_len {
+						builder = builder.with_byte_len(byte_len);
+					}
+					if let Some(extension) = path.extension() {
+						builder = builder.with_hint(&extension.to_string_lossy());
+					}
+
+					let decoder = builder.build().map_err(decode_error)?;
+					Ok::<AudioSource, Error>(Box::new(decoder))
+				})
+				.await
+			}
+			Self::Stream(url) => {
+				let response = http
+					.send(
+						ProviderType::Soundgasm,
+						Method::GET,
+						url.as_str(),
+						HeaderMap::new(),
+					)
+					.await?;
+				let response = check_status(response)?;
+
+				let content_length = response.content_length();
+				let (buffer, writer) = StreamBuffer::new(content_length);
+
+				tokio::spawn(async move {
+					let mut chunks = response.bytes_stream();
+
+					while let Some(chunk) = chunks.next().await {
+						match chunk {
+							Ok(bytes) if writer.push(&bytes) => {}
+							Ok(_) => return,
+							Err(err) => {
+								writer.fail(format!("Failed to stream {}: {}", url, err));
+								return;
+							}
+						}
+					}
+				});
+
+				tokio::task::spawn_blocking(move || {
+					let mut builder = Decoder::builder().with_data(buffer).with_seekable(true);
+					if let Some(content_length) = content_length {
+						builder = builder.with_byte_len(content_length);
+					}
+
+					let decoder = builder.build().map_err(decode_error)?;
+					Ok::<AudioSource, Error>(Box::new(decoder))
+				})
+				.await
+			}
+		};
+
+		source.map_err(|err| Error::Filesystem(format!("Audio decoding stopped: {}", err)))?
+	}
+}
+
+/// The track in the player
+#[derive(Debug, Clone)]
+pub struct PlayerTrack {
+	/// Tells whether another item is the one playing, like its URL
+	pub id: String,
+	pub label: String,
+	pub source: PlaybackSource,
+	/// Unknown for some streams
+	pub duration: Option<Duration>,
+}
+
+/// Plays one track at a time, loading it in the background
+pub struct Player {
+	sink: Box<dyn AudioSink>,
+	http: HttpClient,
+	volume: f32,
+	track: Option<PlayerTrack>,
+	loading: Option<(PlayerTrack, oneshot::Receiver<Result<AudioSource, Error>>)>,
+}
+
+impl Player {
+	pub fn new(output: AudioOutput, http: HttpClient) -> Result<Player, Error> {
+		Ok(Player {
+			sink: output.open()?,
+			http,
+			volume: 1.0,
+			track: None,
+			loading: None,
+		})
+	}
+
+	/// Starts loading a track, which plays once `poll_loaded` finds it
+	/// ready. Whatever was loading before is dropped.
+	pub fn load(&mut self, id: String, label: String, source: PlaybackSource) {
+		let (loaded_tx, loaded_rx) = oneshot::channel();
+
+		let http = self.http.clone();
+		let opened = source.clone().open(http);
+		tokio::spawn(async move {
+			let _ = loaded_tx.send(opened.await);
+		});
+
+		let track = PlayerTrack {
+			id,
+			label,
+			source,
+			duration: None,
+		};
+		self.loading = Some((track, loaded_rx));
+	}
+
+	/// Plays the loading track once it's ready. Returns `None` while it's
+	/// still loading or if nothing is.
+	pub fn poll_loaded(&mut self) -> Option<Result<&PlayerTrack, Error>> {
+		let (_, loaded_rx) = self.loading.as_mut()?;
+
+		let result = match loaded_rx.try_recv() {
+			Ok(result) => result,
+			Err(TryRecvError::Empty) => return None,
+			Err(TryRecvError::Closed) => Err(Error::Filesystem(
+				"Loading the track stopped unexpectedly".to_string(),
+			)),
+		};
+		let (mut track, _) = self.loading.take()?;
+
+		match result {
+			Ok(source) => {
+				track.duration = source.total_duration();
+				self.sink.play(source);
+				self.sink.set_volume(self.volume);
+
+				Some(Ok(self.track.insert(track)))
+			}
+			Err(err) => Some(Err(err)),
+		}
+	}
+
+	pub fn get_track(&self) -> Option<&PlayerTrack> {
+		self.track.as_ref()
+	}
+
+	/// The track being loaded, if any
+	pub fn get_loading(&self) -> Option<&PlayerTrack> {
+		self.loading.as_ref().map(|(track, _)| track)
+	}
+
+	/// Pauses or resumes, starting over if the track played to its end
+	pub fn toggle_pause(&mut self) {
+		let Some(track) = &self.track else {
+			return;
+		};
+
+		if self.sink.is_finished() {
+			self.load(track.id.clone(), track.label.clone(), track.source.clone());
+		} else if self.sink.is_paused() {
+			self.sink.resume();
+		} else {
+			self.sink.pause();
+		}
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.sink.is_paused()
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.sink.is_finished()
+	}
+
+	/// Moves to a position in the track, stopping at its end
+	pub fn seek(&mut self, position: Duration) -> Result<(), Error> {
+		let Some(track) = &self.track else {
+			return Ok(());
+		};
+		if self.sink.is_finished() {
+			return Ok(());
+		}
+
+		let position = match track.duration {
+			Some(duration) => position.min(duration),
+			None => position,
+		};
+
+		self.sink.seek(position)
+	}
+
+	pub fn get_position(&self) -> Duration {
+		self.sink.get_position()
+	}
+
+	pub fn get_volume(&self) -> f32 {
+		self.volume
+	}
+
+	/// Between silent at 0.0 and as loud as the track is at 1.0
+	pub fn set_volume(&mut self, volume: f32) {
+		self.volume = volume.clamp(0.0, 1.0);
+		self.sink.set_volume(self.volume);
+	}
+}
+
+/// One second of silence as 8 kHz, 16-bit mono WAV
+#[cfg(test)]
+pub(crate) fn silent_wav() -> Vec<u8> {
+	let sample_rate: u32 = 8000;
+	let data_len = sample_rate * 2;
+
+	let mut wav = Vec::new();
+	wav.extend_from_slice(b"RIFF");
+	wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+	wav.extend_from_slice(b"WAVEfmt ");
+	wav.extend_from_slice(&16u32.to_le_bytes());
+	wav.extend_from_slice(&1u16.to_le_bytes());
+	wav.extend_from_slice(&1u16.to_le_bytes());
+	wav.extend_from_slice(&sample_rate.to_le_bytes());
+	wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+	wav.extend_from_slice(&2u16.to_le_bytes());
+	wav.extend_from_slice(&16u16.to_le_bytes());
+	wav.extend_from_slice(b"data");
+	wav.extend_from_slice(&data_len.to_le_bytes());
+	wav.resize(wav.len() + data_len as usize, 0);
+
+	wav
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use httpmock::prelude::*;
+
+	use super::{silent_wav, AudioOutput, PlaybackSource, Player};
+	use crate::config::Config;
+	use crate::http_client::HttpClient;
+
+	async fn wait_until_loaded(player: &mut Player) {
+		for _ in 0..500 {
+			if let Some(result) = player.poll_loaded() {
+				result.unwrap();
+				return;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		panic!("The track didn't load");
+	}
+
+	#[tokio::test]
+	async fn test_play_stored_file() {
+		let path = std::env::temp_dir().join("sgdl-player-test.wav");
+		std::fs::write(&path, silent_wav()).unwrap();
+
+		let mut player =
+			Player::new(AudioOutput::Null, HttpClient::new(&Config::new()).unwrap()).unwrap();
+		player.load(
+			"stored".to_string(),
+			"sgdl-test/stored".to_string(),
+			PlaybackSource::File(path),
+		);
+		assert_eq!(player.get_loading().unwrap().label, "sgdl-test/stored");
+
+		wait_until_loaded(&mut player).await;
+		assert!(player.get_loading().is_none());
+		assert_eq!(
+			player.get_track().unwrap().duration,
+			Some(Duration::from_secs(1))
+		);
+		assert!(!player.is_paused());
+
+		player.toggle_pause();
+		assert!(player.is_paused());
+		player.seek(Duration::from_millis(250)).unwrap();
+		assert_eq!(player.get_position(), Duration::from_millis(250));
+
+		// Seeking stops at the end
+		player.seek(Duration::from_secs(5)).unwrap();
+		assert_eq!(player.get_position(), Duration::from_secs(1));
+		assert!(player.is_finished());
+
+		player.set_volume(1.5);
+		assert_eq!(player.get_volume(), 1.0);
+		player.set_volume(-0.5);
+		assert_eq!(player.get_volume(), 0.0);
+	}
+
+	#[tokio::test]
+	async fn test_stream_missing_file() {
+		let server = MockServer::start_async().await;
+		let sound = server.mock(|when, then| {
+			when.method(GET).path("/sounds/streamed.wav");
+			then.status(200).body(silent_wav());
+		});
+		server.mock(|when, then| {
+			when.method(GET).path("/sounds/gone.wav");
+			then.status(404);
+		});
+
+		let mut config = Config::new();
+		config.base_urls.soundgasm_media = server.base_url();
+		let mut player = Player::new(AudioOutput::Null, HttpClient::new(&config).unwrap()).unwrap();
+
+		let stream = |name: &str| {
+			PlaybackSource::Stream(
+				reqwest::Url::parse(&format!("https://media.soundgasm.net/sounds/{}.wav", name)).unwrap(),
+			)
+		};
+
+		player.load(
+			"streamed".to_string(),
+			"sgdl-test/streamed".to_string(),
+			stream("streamed"),
+		);
+		wait_until_loaded(&mut player).await;
+		sound.assert();
+		assert_eq!(
+			player.get_track().unwrap().duration,
+			Some(Duration::from_secs(1))
+		);
+
+		player.load(
+			"gone".to_string(),
+			"sgdl-test/gone".to_string(),
+			stream("gone"),
+		);
+		let error = loop {
+			if let Some(result) = player.poll_loaded() {
+				break result.unwrap_err();
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		};
+		assert!(matches!(error, crate::error::Error::NotFound(_)));
+
+		// The track that was playing keeps playing
+		assert_eq!(player.get_track().unwrap().id, "streamed");
+	}
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use super::{AudioSink, AudioSource};
+use crate::error::Error;
+
+/// Plays nothing, but keeps time like a sound card would. The samples are
+/// never decoded, only the length of the source is kept.
+#[derive(Debug, Default)]
+pub struct NullSink {
+	is_loaded: bool,
+	duration: Option<Duration>,
+	/// Where the track was when it was last paused or seeked
+	position: Duration,
+	/// Unset while paused
+	resumed_at: Option<Instant>,
+}
+
+impl AudioSink for NullSink {
+	fn play(&mut self, source: AudioSource) {
+		*self = Self {
+			is_loaded: true,
+			duration: source.total_duration(),
+			position: Duration::ZERO,
+			resumed_at: Some(Instant::now()),
+		};
+	}
+
+	fn pause(&mut self) {
+		self.position = self.get_position();
+		self.resumed_at = None;
+	}
+
+	fn resume(&mut self) {
+		if self.resumed_at.is_none() {
+			self.resumed_at = Some(Instant::now());
+		}
+	}
+
+	fn is_paused(&self) -> bool {
+		self.resumed_at.is_none()
+	}
+
+	fn seek(&mut self, position: Duration) -> Result<(), Error> {
+		self.position = position;
+		if self.resumed_at.is_some() {
+			self.resumed_at = Some(Instant::now());
+		}
+
+		Ok(())
+	}
+
+	fn get_position(&self) -> Duration {
+		let position = match self.resumed_at {
+			Some(resumed_at) => self.position + resumed_at.elapsed(),
+			None => self.position,
+		};
+
+		match self.duration {
+			Some(duration) => position.min(duration),
+			None => position,
+		}
+	}
+
+	fn set_volume(&mut self, _volume: f32) {}
+
+	fn is_finished(&self) -> bool {
+		self.is_loaded
+			&& self
+				.duration
+				.is_some_and(|duration| self.get_position() >= duration)
+	}
+}
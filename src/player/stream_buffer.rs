@@ -0,0 +1,210 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// A file that is still being downloaded, readable while its bytes arrive.
+/// Reads and seeks past what has arrived so far wait for the rest, so this
+/// should only be read from a thread that is allowed to block.
+#[derive(Debug)]
+pub struct StreamBuffer {
+	shared: Arc<Shared>,
+	position: u64,
+}
+
+/// Fills a `StreamBuffer`. Dropping it ends the file, so a reader never
+/// waits on a download that was given up on.
+#[derive(Debug)]
+pub struct StreamWriter {
+	shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	state: Mutex<BufferState>,
+	arrived: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+	data: Vec<u8>,
+	/// From the response headers, needed to seek from the end before
+	/// everything has arrived
+	content_length: Option<u64>,
+	is_complete: bool,
+	/// Why the download stopped early, returned to the reader once it has
+	/// read everything before it
+	error: Option<String>,
+	/// Set once the reader is gone, so the download can stop
+	is_abandoned: bool,
+}
+
+impl StreamBuffer {
+	pub fn new(content_length: Option<u64>) -> (StreamBuffer, StreamWriter) {
+		let shared = Arc::new(Shared {
+			state: Mutex::new(BufferState {
+				content_length,
+				..Default::default()
+			}),
+			arrived: Condvar::new(),
+		});
+
+		let buffer = StreamBuffer {
+			shared: shared.clone(),
+			position: 0,
+		};
+
+		(buffer, StreamWriter { shared })
+	}
+}
+
+impl Shared {
+	/// Waits until the byte at `position` has arrived or the download ended
+	fn wait_for(&self, position: u64) -> MutexGuard<'_, BufferState> {
+		let state = self.state.lock().unwrap();
+
+		self
+			.arrived
+			.wait_while(state, |state| {
+				(state.data.len() as u64) <= position && !state.is_complete
+			})
+			.unwrap()
+	}
+
+	/// Waits until the length of the file is known
+	fn wait_for_length(&self) -> u64 {
+		let state = self.state.lock().unwrap();
+
+		let state = self
+			.arrived
+			.wait_while(state, |state| {
+				state.content_length.is_none() && !state.is_complete
+			})
+			.unwrap();
+
+		state.content_length.unwrap_or(state.data.len() as u64)
+	}
+}
+
+impl Read for StreamBuffer {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		let state = self.shared.wait_for(self.position);
+		let start = self.position as usize;
+
+		if start >= state.data.len() {
+			return match &state.error {
+				Some(err) => Err(io::Error::other(err.clone())),
+				None => Ok(0),
+			};
+		}
+
+		let count = buf.len().min(state.data.len() - start);
+		buf[..count].copy_from_slice(&state.data[start..start + count]);
+		self.position += count as u64;
+
+		Ok(count)
+	}
+}
+
+impl Seek for StreamBuffer {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => Some(offset),
+			SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+			SeekFrom::End(offset) => self.shared.wait_for_length().checked_add_signed(offset),
+		};
+
+		self.position = position.ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Seek to before the start of the stream",
+			)
+		})?;
+
+		Ok(self.position)
+	}
+}
+
+impl Drop for StreamBuffer {
+	fn drop(&mut self) {
+		self.shared.state.lock().unwrap().is_abandoned = true;
+	}
+}
+
+impl StreamWriter {
+	/// Adds the next bytes of the file. Returns false once the reader is
+	/// gone and the rest isn't needed.
+	pub fn push(&self, bytes: &[u8]) -> bool {
+		let mut state = self.shared.state.lock().unwrap();
+		state.data.extend_from_slice(bytes);
+		self.shared.arrived.notify_all();
+
+		!state.is_abandoned
+	}
+
+	/// Ends the file early. The reader gets the error once it has read
+	/// everything before it.
+	pub fn fail(self, error: String) {
+		self.shared.state.lock().unwrap().error = Some(error);
+	}
+}
+
+impl Drop for StreamWriter {
+	fn drop(&mut self) {
+		self.shared.state.lock().unwrap().is_complete = true;
+		self.shared.arrived.notify_all();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Seek, SeekFrom};
+
+	use super::StreamBuffer;
+
+	#[test]
+	fn test_read_while_downloading() {
+		let (mut buffer, writer) = StreamBuffer::new(Some(7));
+
+		let reader = std::thread::spawn(move || {
+			// Waits for the length, then for the bytes at the end
+			buffer.seek(SeekFrom::End(-2)).unwrap();
+			let mut end = String::new();
+			buffer.read_to_string(&mut end).unwrap();
+
+			buffer.seek(SeekFrom::Start(0)).unwrap();
+			let mut all = String::new();
+			buffer.read_to_string(&mut all).unwrap();
+
+			(end, all)
+		});
+
+		assert!(writer.push(b"sou"));
+		assert!(writer.push(b"nds!"));
+		drop(writer);
+
+		let (end, all) = reader.join().unwrap();
+		assert_eq!(end, "s!");
+		assert_eq!(all, "sounds!");
+	}
+
+	#[test]
+	fn test_failed_download() {
+		let (mut buffer, writer) = StreamBuffer::new(None);
+
+		assert!(writer.push(b"par"));
+		writer.fail("Connection reset".to_string());
+
+		let mut start = [0; 3];
+		buffer.read_exact(&mut start).unwrap();
+		assert_eq!(&start, b"par");
+		assert!(buffer.read(&mut start).is_err());
+
+		// The download stops once nobody reads it
+		let (buffer, writer) = StreamBuffer::new(None);
+		drop(buffer);
+		assert!(!writer.push(b"unwanted"));
+	}
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+/// What a job is doing, which also decides what its progress counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+	/// Fetching the track pages of a profile, counted in tracks
+	Scan,
+	/// A run over many items, like every pending download, counted in items
+	Batch,
+	/// Downloading a single file, counted in bytes
+	Download,
+	/// Hashing a file, counted in bytes
+	Hash,
+}
+
+impl JobKind {
+	pub fn counts_bytes(&self) -> bool {
+		matches!(self, Self::Download | Self::Hash)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+	Started {
+		job: JobId,
+		kind: JobKind,
+		label: String,
+		total: Option<u64>,
+	},
+	/// `done` is the running count, not the size of the last step. A new
+	/// `total` is sent once it is known, like after the response headers of
+	/// a download arrive.
+	Advanced {
+		job: JobId,
+		done: u64,
+		total: Option<u64>,
+	},
+	Finished {
+		job: JobId,
+	},
+	Failed {
+		job: JobId,
+		message: String,
+	},
+}
+
+/// Where scans, downloads and hashing report their progress. Cloned into
+/// every task that does work, sending never blocks, and events are simply
+/// dropped if nothing is listening.
+#[derive(Debug, Clone)]
+pub struct ProgressSender {
+	tx: Option<UnboundedSender<ProgressEvent>>,
+	next_id: Arc<AtomicU64>,
+}
+
+impl ProgressSender {
+	pub fn channel() -> (Self, UnboundedReceiver<ProgressEvent>) {
+		let (tx, rx) = mpsc::unbounded_channel();
+
+		let this = Self {
+			tx: Some(tx),
+			next_id: Arc::new(AtomicU64::new(0)),
+		};
+
+		(this, rx)
+	}
+
+	/// A sender nobody listens to, for runs without progress output
+	pub fn disabled() -> Self {
+		Self {
+			tx: None,
+			next_id: Arc::new(AtomicU64::new(0)),
+		}
+	}
+
+	pub fn start_job(
+		&self,
+		kind: JobKind,
+		label: impl Into<String>,
+		total: Option<u64>,
+	) -> ProgressJob {
+		let job = JobId(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+		self.send(ProgressEvent::Started {
+			job,
+			kind,
+			label: label.into(),
+			total,
+		});
+
+		ProgressJob {
+			job,
+			sender: self.clone(),
+			done: 0,
+			is_over: false,
+		}
+	}
+
+	fn send(&self, event: ProgressEvent) {
+		if let Some(tx) = &self.tx {
+			let _ = tx.send(event);
+		}
+	}
+}
+
+/// A running job. A job dropped before it finished or failed, like an
+/// aborted download, is reported as failed.
+pub struct ProgressJob {
+	job: JobId,
+	sender: ProgressSender,
+	done: u64,
+	is_over: bool,
+}
+
+impl ProgressJob {
+	pub fn get_id(&self) -> JobId {
+		self.job
+	}
+
+	pub fn set_done(&mut self, done: u64, total: Option<u64>) {
+		self.done = done;
+		self.sender.send(ProgressEvent::Advanced {
+			job: self.job,
+			done,
+			total,
+		});
+	}
+
+	pub fn advance(&mut self, step: u64) {
+		self.set_done(self.done + step, None);
+	}
+
+	pub fn finish(mut self) {
+		self.is_over = true;
+		self.sender.send(ProgressEvent::Finished { job: self.job });
+	}
+
+	pub fn fail(mut self, err: &Error) {
+		self.is_over = true;
+		self.sender.send(ProgressEvent::Failed {
+			job: self.job,
+			message: err.to_string(),
+		});
+	}
+}
+
+impl Drop for ProgressJob {
+	fn drop(&mut self) {
+		if !self.is_over {
+			self.sender.send(ProgressEvent::Failed {
+				job: self.job,
+				message: "Stopped before it finished".to_string(),
+			});
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct JobProgress {
+	pub kind: JobKind,
+	pub label: String,
+	pub done: u64,
+	pub total: Option<u64>,
+}
+
+/// The running jobs and what happened to the rest, as seen through the
+/// events. Shared by the CLI progress bars and the TUI.
+#[derive(Debug, Default)]
+pub struct ProgressState {
+	pub jobs: BTreeMap<JobId, JobProgress>,
+	pub finished: usize,
+	pub failed: Vec<(String, String)>,
+	/// Bytes of finished downloads, so the overall count doesn't drop when
+	/// a file completes
+	finished_bytes: u64,
+}
+
+impl ProgressState {
+	pub fn apply(&mut self, event: &ProgressEvent) {
+		match event {
+			ProgressEvent::Started {
+				job,
+				kind,
+				label,
+				total,
+			} => {
+				self.jobs.insert(
+					*job,
+					JobProgress {
+						kind: *kind,
+						label: label.clone(),
+						done: 0,
+						total: *total,
+					},
+				);
+			}
+			ProgressEvent::Advanced { job, done, total } => {
+				if let Some(progress) = self.jobs.get_mut(job) {
+					progress.done = *done;
+					progress.total = total.or(progress.total);
+				}
+			}
+			ProgressEvent::Finished { job } => {
+				if let Some(progress) = self.jobs.remove(job) {
+					if progress.kind == JobKind::Download {
+						self.finished_bytes += progress.done;
+					}
+					self.finished += 1;
+				}
+			}
+			ProgressEvent::Failed { job, message } => {
+				if let Some(progress) = self.jobs.remove(job) {
+					self.failed.push((progress.label, message.clone()));
+				}
+			}
+		}
+	}
+
+	/// Bytes downloaded so far by every download, running or finished
+	pub fn get_downloaded_bytes(&self) -> u64 {
+		self.finished_bytes
+			+ self
+				.jobs
+				.values()
+				.filter(|progress| progress.kind == JobKind::Download)
+				.map(|progress| progress.done)
+				.sum::<u64>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{JobKind, ProgressSender, ProgressState};
+	use crate::error::Error;
+
+	#[test]
+	fn test_progress_events() {
+		let (progress, mut rx) = ProgressSender::channel();
+		let mut state = ProgressState::default();
+
+		let mut batch = progress.start_job(JobKind::Batch, "downloads", Some(3));
+		let mut first = progress.start_job(JobKind::Download, "first.m4a", None);
+		first.set_done(4, Some(10));
+		first.set_done(10, None);
+		first.finish();
+		batch.advance(1);
+
+		let mut second = progress.start_job(JobKind::Download, "second.m4a", Some(20));
+		second.set_done(5, None);
+		let third = progress.start_job(JobKind::Download, "third.m4a", Some(20));
+		third.fail(&Error::Network("timed out".to_string()));
+
+		while let Ok(event) = rx.try_recv() {
+			state.apply(&event);
+		}
+
+		assert_eq!(state.finished, 1);
+		assert_eq!(
+			state.failed,
+			vec![("third.m4a".to_string(), "timed out".to_string())]
+		);
+		assert_eq!(state.get_downloaded_bytes(), 15);
+
+		let running = state.jobs.values().collect::<Vec<_>>();
+		assert_eq!(running.len(), 2);
+		assert_eq!((running[0].done, running[0].total), (1, Some(3)));
+		assert_eq!((running[1].done, running[1].total), (5, Some(20)));
+
+		// Dropping a job that is still running reports it as failed
+		drop(second);
+		state.apply(&rx.try_recv().unwrap());
+		assert_eq!(state.jobs.len(), 1);
+		assert_eq!(
+			state.failed[1],
+			(
+				"second.m4a".to_string(),
+				"Stopped before it finished".to_string()
+			)
+		);
+	}
+}
@@ -1,11 +1,11 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    downloaded_segments (rowid) {
-        rowid -> Integer,
+    downloaded_segments (id) {
+        id -> Integer,
         download_id -> Integer,
-        start_index -> Integer,
-        end_index -> Integer,
+        start_index -> BigInt,
+        end_index -> BigInt,
     }
 }
 
@@ -15,6 +15,79 @@ diesel::table! {
         url -> Text,
         file_path -> Text,
         created_at -> Timestamp,
+        content_length -> Nullable<BigInt>,
+        state -> Text,
+        priority -> Integer,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        label -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    item_tags (tag_id, source, item_id) {
+        tag_id -> Integer,
+        source -> Text,
+        item_id -> Text,
+        is_manual -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    kemono_attachments (service, creator_id, post_id, path) {
+        service -> Text,
+        creator_id -> Text,
+        post_id -> Text,
+        path -> Text,
+        name -> Text,
+        position -> Integer,
+        created_at -> Timestamp,
+        media_type -> Nullable<Text>,
+        content_hash -> Nullable<Text>,
+        content_length -> Nullable<BigInt>,
+        media_type_checked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    kemono_creators (service, creator_id) {
+        provider_domain -> Text,
+        service -> Text,
+        creator_id -> Text,
+        name -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    kemono_post_revisions (id) {
+        id -> Integer,
+        service -> Text,
+        creator_id -> Text,
+        post_id -> Text,
+        title -> Text,
+        content -> Text,
+        edited_at -> Nullable<Timestamp>,
+        replaced_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    kemono_posts (service, creator_id, post_id) {
+        provider_domain -> Text,
+        service -> Text,
+        creator_id -> Text,
+        post_id -> Text,
+        title -> Text,
+        content -> Text,
+        published_at -> Nullable<Timestamp>,
+        edited_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -34,10 +107,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    subscriptions (id) {
+        id -> Integer,
+        source -> Text,
+        url -> Text,
+        created_at -> Timestamp,
+        last_synced_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    track_revisions (id) {
+        id -> Integer,
+        profile_slug -> Text,
+        track_slug -> Text,
+        title -> Text,
+        description -> Text,
+        replaced_at -> Timestamp,
+    }
+}
+
 diesel::joinable!(downloaded_segments -> file_downloads (download_id));
+diesel::joinable!(item_tags -> tags (tag_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     downloaded_segments,
     file_downloads,
+    item_tags,
+    kemono_attachments,
+    kemono_creators,
+    kemono_post_revisions,
+    kemono_posts,
     soundgasm_tracks,
+    subscriptions,
+    tags,
+    track_revisions,
 );
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::debug;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Method, Response, StatusCode, Url};
+use tokio::time::{sleep_until, Instant};
+
+use crate::error::Error;
+
+/// Spaces out requests to the same host. Requests to different hosts don't
+/// wait for each other.
+pub struct HostRateLimiter {
+	min_interval: Duration,
+	next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostRateLimiter {
+	pub fn new(min_interval: Duration) -> Self {
+		Self {
+			min_interval,
+			next_slots: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Waits until a request to the host of `url` is allowed
+	pub async fn wait(&self, url: &Url) {
+		let host = url.host_str().unwrap_or_default().to_string();
+
+		let slot = {
+			let mut next_slots = self.next_slots.lock().unwrap();
+			let now = Instant::now();
+			let slot = next_slots
+				.get(&host)
+				.copied()
+				.filter(|next_slot| *next_slot > now)
+				.unwrap_or(now);
+
+			next_slots.insert(host, slot + self.min_interval);
+			slot
+		};
+
+		sleep_until(slot).await;
+	}
+}
+
+/// How often and how patiently to retry requests that fail because the
+/// server is overloaded (429 or 5xx) or can't be reached
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+	pub max_attempts: u32,
+	/// Doubled after every failed attempt
+	pub initial_backoff: Duration,
+	/// Also caps how long a `Retry-After` header can make us wait
+	pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 4,
+			initial_backoff: Duration::from_millis(500),
+			max_backoff: Duration::from_secs(30),
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn is_retryable(status: StatusCode) -> bool {
+		status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+	}
+
+	/// How long to wait before retrying after `attempt` failed attempts
+	pub fn get_backoff(&self, attempt: u32) -> Duration {
+		self
+			.initial_backoff
+			.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+			.min(self.max_backoff)
+	}
+
+	fn get_retry_after(&self, response: &Response) -> Option<Duration> {
+		let seconds = response
+			.headers()
+			.get(RETRY_AFTER)?
+			.to_str()
+			.ok()?
+			.trim()
+			.parse::<u64>()
+			.ok()?;
+
+		Some(Duration::from_secs(seconds).min(self.max_backoff))
+	}
+}
+
+/// Sends a request once the rate limit allows it, retrying with exponential
+/// backoff while the server is overloaded or unreachable. Other error
+/// statuses, like 404, are returned as they are. So is the last response
+/// if every attempt was overloaded.
+pub async fn send_with_retry(
+	client: &Client,
+	rate_limiter: &HostRateLimiter,
+	retry_policy: &RetryPolicy,
+	method: Method,
+	url: &str,
+	headers: HeaderMap,
+) -> Result<Response, Error> {
+	let url =
+		Url::parse(url).map_err(|err| Error::InvalidInput(format!("Invalid URL {}: {}", url, err)))?;
+	let mut attempt = 0;
+
+	loop {
+		attempt += 1;
+		rate_limiter.wait(&url).await;
+
+		let result = client
+			.request(method.clone(), url.clone())
+			.headers(headers.clone())
+			.send()
+			.await;
+		let is_last_attempt = attempt >= retry_policy.max_attempts;
+
+		let backoff = match result {
+			Ok(response) if !RetryPolicy::is_retryable(response.status()) || is_last_attempt => {
+				return Ok(response);
+			}
+			Ok(response) => {
+				debug!(
+					"{} responded with {}, attempt {} of {}",
+					url,
+					response.status(),
+					attempt,
+					retry_policy.max_attempts
+				);
+				retry_policy
+					.get_retry_after(&response)
+					.unwrap_or_else(|| retry_policy.get_backoff(attempt))
+			}
+			Err(err) if (err.is_connect() || err.is_timeout()) && !is_last_attempt => {
+				debug!(
+					"Failed to reach {}, attempt {} of {}: {}",
+					url, attempt, retry_policy.max_attempts, err
+				);
+				retry_policy.get_backoff(attempt)
+			}
+			Err(err) => {
+				return Err(Error::Network(format!(
+					"Request to {} failed: {}",
+					url, err
+				)))
+			}
+		};
+
+		tokio::time::sleep(backoff).await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use httpmock::prelude::*;
+	use reqwest::header::HeaderMap;
+	use reqwest::{Client, Method, StatusCode, Url};
+	use tokio::time::Instant;
+
+	use super::{send_with_retry, HostRateLimiter, RetryPolicy};
+
+	fn test_policy() -> RetryPolicy {
+		RetryPolicy {
+			max_attempts: 3,
+			initial_backoff: Duration::from_millis(10),
+			max_backoff: Duration::from_millis(50),
+		}
+	}
+
+	#[test]
+	fn test_backoff_doubles_up_to_max() {
+		let policy = test_policy();
+		assert_eq!(policy.get_backoff(1), Duration::from_millis(10));
+		assert_eq!(policy.get_backoff(2), Duration::from_millis(20));
+		assert_eq!(policy.get_backoff(3), Duration::from_millis(40));
+		assert_eq!(policy.get_backoff(4), Duration::from_millis(50));
+	}
+
+	#[tokio::test]
+	async fn test_rate_limiter_spaces_requests_per_host() {
+		let rate_limiter = HostRateLimiter::new(Duration::from_millis(100));
+		let first = Url::parse("https://soundgasm.net/u/a").unwrap();
+		let other_host = Url::parse("https://kemono.su/api").unwrap();
+
+		let start = Instant::now();
+		rate_limiter.wait(&first).await;
+		rate_limiter.wait(&other_host).await;
+		assert!(start.elapsed() < Duration::from_millis(100));
+
+		rate_limiter.wait(&first).await;
+		assert!(start.elapsed() >= Duration::from_millis(100));
+	}
+
+	#[tokio::test]
+	async fn test_retries_overloaded_server() {
+		let server = MockServer::start();
+		let overloaded = server.mock(|when, then| {
+			when.method(GET).path("/busy");
+			then.status(503);
+		});
+		let missing = server.mock(|when, then| {
+			when.method(GET).path("/missing");
+			then.status(404);
+		});
+
+		let client = Client::new();
+		let rate_limiter = HostRateLimiter::new(Duration::ZERO);
+
+		let response = send_with_retry(
+			&client,
+			&rate_limiter,
+			&test_policy(),
+			Method::GET,
+			&server.url("/busy"),
+			HeaderMap::new(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+		overloaded.assert_hits(3);
+
+		// Missing pages aren't worth retrying
+		let response = send_with_retry(
+			&client,
+			&rate_limiter,
+			&test_policy(),
+			Method::GET,
+			&server.url("/missing"),
+			HeaderMap::new(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+		missing.assert_hits(1);
+	}
+}